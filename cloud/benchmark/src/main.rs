@@ -2,7 +2,7 @@ use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::time::Instant;
 
-use hyperopic::search::{SearchParameters, TranspositionsImpl};
+use hyperopic::search::{MultiPv, SearchParameters, TranspositionsImpl};
 use itertools::Itertools;
 use lambda_runtime::{service_fn, Context, Error, LambdaEvent};
 use simple_logger::SimpleLogger;
@@ -51,7 +51,13 @@ async fn handler(event: LambdaEvent<BenchStartEvent>) -> Result<BenchOutput, Err
         }
         let search_result = hyperopic::search::search(
             position.into(),
-            SearchParameters { end: e.depth, table: &mut TranspositionsImpl::new(e.table_size) },
+            SearchParameters {
+                end: e.depth,
+                table: &mut TranspositionsImpl::new(e.table_size),
+                observer: &mut (),
+                multi_pv: MultiPv::default(),
+                tablebase: None,
+            },
         )?;
         search_result.best_move.hash(&mut hasher);
         moves.push(search_result);
@@ -10,11 +10,10 @@ use rusoto_lambda::{InvocationRequest, InvokeAsyncRequest, Lambda, LambdaClient}
 use simple_logger::SimpleLogger;
 
 use anyhow::{anyhow, Result};
-use hyperopic::moves::Move;
 use hyperopic::position::Position;
 use lambda_payloads::chessgame::*;
 use lambda_payloads::chessmove::{ChooseMoveEvent, ChooseMoveEventClock, ChooseMoveOutput};
-use lichess_game::{CancellationHook, MoveChooser};
+use lichess_game::{CancellationHook, ChosenMove, MoveChooser};
 
 const CANCEL_PERIOD_SECS: u64 = 60;
 
@@ -105,12 +104,14 @@ impl From<(Region, String)> for MoveLambdaClient {
 impl MoveChooser for MoveLambdaClient {
     async fn choose(
         &mut self,
+        initial_fen: &str,
         moves_played: &str,
         remaining: Duration,
         increment: Duration,
-    ) -> Result<Move> {
+    ) -> Result<ChosenMove> {
         let timer = Instant::now();
         let request = ChooseMoveEvent {
+            initial_fen: Some(initial_fen.to_owned()),
             moves_played: moves_played.to_owned(),
             features: vec![],
             clock_millis: ChooseMoveEventClock {
@@ -138,12 +139,17 @@ impl MoveChooser for MoveLambdaClient {
                 let decoded = String::from_utf8(raw_bytes.to_vec())?;
                 log::info!("Response payload: {}", decoded);
                 let response = serde_json::from_str::<ChooseMoveOutput>(decoded.as_str())?;
-                let mut position = moves_played.parse::<Position>()?;
-                position
+                let mut position = initial_fen.parse::<Position>()?;
+                position.play(moves_played)?;
+                let best_move = position
                     .play(&response.best_move)?
                     .first()
                     .cloned()
-                    .ok_or(anyhow!("Could not parse {}", response.best_move))
+                    .ok_or(anyhow!("Could not parse {}", response.best_move))?;
+                Ok(ChosenMove {
+                    best_move,
+                    relative_eval: response.search_details.map(|details| details.eval),
+                })
             }
         }
     }
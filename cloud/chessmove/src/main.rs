@@ -6,10 +6,11 @@ use simple_logger::SimpleLogger;
 
 use anyhow::anyhow;
 use hyperopic::position::Position;
+use hyperopic::search::Score;
 use hyperopic::{ComputeMoveInput, Engine, LookupMoveService};
 use lambda_payloads::chessmove::*;
 use lichess_api::LichessEndgameClient;
-use openings::{DynamoOpeningService, OpeningTable};
+use openings::OpeningSource;
 
 const TABLE_SIZE: usize = 10000;
 const TABLE_ENV_KEY: &'static str = "APP_CONFIG";
@@ -23,7 +24,14 @@ async fn main() -> Result<(), Error> {
 
 async fn move_handler(event: LambdaEvent<ChooseMoveEvent>) -> Result<ChooseMoveOutput, Error> {
     let choose_move = &event.payload;
-    let position = choose_move.moves_played.parse::<Position>()?;
+    let position = match &choose_move.initial_fen {
+        None => choose_move.moves_played.parse::<Position>()?,
+        Some(fen) => {
+            let mut position = fen.parse::<Position>()?;
+            position.play(&choose_move.moves_played)?;
+            position
+        }
+    };
     let mut engine = Engine::new(TABLE_SIZE, load_lookup_services(&choose_move.features));
     let output = engine.compute_move(ComputeMoveInput {
         position,
@@ -36,6 +44,10 @@ async fn move_handler(event: LambdaEvent<ChooseMoveEvent>) -> Result<ChooseMoveO
             depth_searched: details.depth as usize,
             search_duration_millis: details.time.as_millis() as u64,
             eval: details.relative_eval,
+            score: match details.score {
+                Score::Centipawns(cp) => SearchScore::Centipawns(cp),
+                Score::Mate(moves) => SearchScore::Mate(moves),
+            },
         }),
     })
 }
@@ -45,11 +57,12 @@ fn load_lookup_services(features: &Vec<ChooseMoveFeature>) -> Vec<Box<dyn Lookup
     if !features.contains(&ChooseMoveFeature::DisableOpeningsLookup) {
         let table_var = std::env::var(TABLE_ENV_KEY)
             .expect(format!("No value found for env var {}", TABLE_ENV_KEY).as_str());
-        let service = serde_json::from_str::<OpeningTable>(table_var.as_str())
+        // Either a dynamodb table or a file of openings deployed with the lambda
+        let service = serde_json::from_str::<OpeningSource>(table_var.as_str())
             .map_err(|e| anyhow!(e))
-            .and_then(|table| DynamoOpeningService::try_from(table))
+            .and_then(|source| source.into_service())
             .expect(format!("Could not parse table config {}", table_var).as_str());
-        services.push(Box::new(service));
+        services.push(service);
     }
     if !features.contains(&ChooseMoveFeature::DisableEndgameLookup) {
         services.push(Box::new(LichessEndgameClient::default()));
@@ -1,5 +1,8 @@
 use crate::challenge_table::ChallengeTableClient;
 use anyhow::Result;
+use hyperopic::constants::piece;
+use hyperopic::moves::Move;
+use hyperopic::position::Position;
 use lichess_api::LichessClient;
 use lichess_events::events::{Challenge, TimeControl};
 
@@ -94,13 +97,36 @@ trait ValidityCheck {
 }
 
 const STANDARD_VARIANT_KEY: &'static str = "standard";
-// TODO Support custom FEN variant
-//const FEN_VARIANT_KEY: &'static str = "fromPosition";
+const CHESS960_VARIANT_KEY: &'static str = "chess960";
+const FEN_VARIANT_KEY: &'static str = "fromPosition";
 
 struct VariantCheck;
 impl ValidityCheck for VariantCheck {
     fn accepts(&self, challenge: &Challenge) -> bool {
-        challenge.variant.key.as_str() == STANDARD_VARIANT_KEY
+        match challenge.variant.key.as_str() {
+            STANDARD_VARIANT_KEY | CHESS960_VARIANT_KEY => true,
+            FEN_VARIANT_KEY => {
+                challenge.initial_fen.as_ref().map(|fen| is_playable(fen)).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A custom start position must parse as FEN, have exactly one king per side
+/// with the side not to move out of check, and not already be terminal.
+fn is_playable(fen: &str) -> bool {
+    match Position::from_fen(fen) {
+        Err(e) => {
+            log::info!("Cannot parse challenge position {}: {}", fen, e);
+            false
+        }
+        Ok(mut position) => {
+            let kings = [piece::WK, piece::BK].map(|k| position.piece_boards[k].count_ones());
+            let playable = kings == [1, 1] && position.compute_terminal_state().is_none();
+            // After passing the move the side which just moved must not be in check
+            playable && position.make(Move::Null).is_ok() && !position.in_check()
+        }
     }
 }
 
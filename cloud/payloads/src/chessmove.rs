@@ -2,6 +2,10 @@ use serde_derive::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub struct ChooseMoveEvent {
+    /// The FEN of the position the moves are played from, the standard
+    /// starting position is assumed if absent
+    #[serde(rename = "initialFen", default)]
+    pub initial_fen: Option<String>,
     #[serde(rename = "movesPlayed")]
     pub moves_played: String,
     #[serde(rename = "clockMillis")]
@@ -41,4 +45,15 @@ pub struct SearchDetails {
     #[serde(rename = "searchDurationMillis")]
     pub search_duration_millis: u64,
     pub eval: i32,
+    pub score: SearchScore,
+}
+
+/// Evaluation from the perspective of the side to move, either in centipawns
+/// or as the number of moves to a forced mate which is negative when losing.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SearchScore {
+    #[serde(rename = "cp")]
+    Centipawns(i32),
+    #[serde(rename = "mate")]
+    Mate(i32),
 }
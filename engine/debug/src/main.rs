@@ -1,10 +1,16 @@
+mod suite;
+
+use std::time::{Duration, Instant};
+
 use clap::{Parser, Subcommand};
 use itertools::Itertools;
 
 use hyperopic::moves::Moves;
 use hyperopic::node::TreeNode;
 use hyperopic::position::Position;
-use hyperopic::search::{NodeType, SearchParameters, TableEntry, Transpositions};
+use hyperopic::search::observer::{IterationSummary, SearchObserver};
+use hyperopic::search::{MultiPv, NodeType, Score, SearchParameters, TableEntry, Transpositions};
+use hyperopic::EvalParams;
 
 #[derive(Parser)]
 struct Cli {
@@ -34,6 +40,44 @@ enum Commands {
         #[arg(long)]
         fen: String,
     },
+    /// Print the contribution of each evaluation facet for the position given
+    /// by exactly one of a FEN or a PGN
+    Eval {
+        #[arg(long)]
+        fen: Option<String>,
+        #[arg(long)]
+        pgn: Option<String>,
+        /// Evaluation params to use instead of the compiled defaults
+        #[arg(long)]
+        params: Option<String>,
+        /// Names of facets to leave out of the evaluation
+        #[arg(long, value_delimiter = ',')]
+        without: Vec<String>,
+    },
+    Perft {
+        #[arg(long)]
+        fen: String,
+        #[arg(long)]
+        depth: usize,
+    },
+    /// Search every position of an EPD test suite under exactly one of the
+    /// depth, node or time budgets and report which were solved, progress
+    /// goes to standard error so standard output only holds the summary
+    Suite {
+        #[arg(long)]
+        epd: String,
+        #[arg(long)]
+        depth: Option<usize>,
+        #[arg(long)]
+        nodes: Option<u64>,
+        #[arg(long)]
+        time_ms: Option<u64>,
+        #[arg(long, default_value_t = 100000)]
+        table_size: usize,
+        /// Write the JSON summary to this file rather than standard output
+        #[arg(long)]
+        output: Option<String>,
+    },
 }
 
 fn main() {
@@ -50,6 +94,28 @@ fn main() {
                 board.moves(&Moves::All).into_iter().map(|m| m.to_string()).collect();
             println!("{}", serde_json::to_string_pretty(&moves).unwrap());
         }
+        Commands::Eval { fen, pgn, params, without } => {
+            let position = match (fen, pgn) {
+                (Some(fen), None) => fen.parse::<Position>().unwrap(),
+                (None, Some(pgn)) => pgn.parse::<Position>().unwrap(),
+                _ => panic!("Exactly one of --fen or --pgn is required"),
+            };
+            let params = params.map(|p| EvalParams::load(p).unwrap()).unwrap_or_default();
+            let builder = without
+                .iter()
+                .fold(TreeNode::builder().with_params(params), |b, name| b.without_facet(name));
+            print_eval(&builder.build(position));
+        }
+        Commands::Perft { fen, depth } => run_perft(fen.parse::<Position>().unwrap(), depth),
+        Commands::Suite { epd, depth, nodes, time_ms, table_size, output } => {
+            let budget = match (depth, nodes, time_ms) {
+                (Some(depth), None, None) => suite::Budget::Depth(depth),
+                (None, Some(nodes), None) => suite::Budget::Nodes(nodes),
+                (None, None, Some(ms)) => suite::Budget::Time(Duration::from_millis(ms)),
+                _ => panic!("Exactly one of --depth, --nodes or --time-ms is required"),
+            };
+            suite::run_suite(epd.as_str(), budget, table_size, output)
+        }
     }
 }
 
@@ -64,7 +130,7 @@ impl DebugTranspositions {
 }
 
 impl Transpositions for DebugTranspositions {
-    fn get(&self, pos: &Position) -> Option<&TableEntry> {
+    fn get(&self, pos: &Position) -> Option<TableEntry> {
         let index = (pos.key % self.store.len() as u64) as usize;
         if let Some((existing, n)) = self.store[index].as_ref() {
             if n.key == pos.key {
@@ -72,7 +138,7 @@ impl Transpositions for DebugTranspositions {
                 if existing.as_str() != new_pos.as_str() {
                     panic!("Collision: {} <-> {}", existing, new_pos)
                 }
-                Some(n)
+                Some(n.clone())
             } else {
                 None
             }
@@ -94,12 +160,32 @@ impl Transpositions for DebugTranspositions {
         let entry = TableEntry { key: pos.key, root_index, depth, eval, node_type };
         self.store[index] = Some((to_table_id(&pos), entry))
     }
+
+    fn hashfull(&self) -> u16 {
+        let sample = &self.store[..self.store.len().min(1000)];
+        (sample.iter().filter(|e| e.is_some()).count() * 1000 / sample.len().max(1)) as u16
+    }
 }
 
 fn to_table_id(pos: &Position) -> String {
     pos.to_string().split_whitespace().take(4).join(" ")
 }
 
+struct ProgressPrinter;
+
+impl SearchObserver for ProgressPrinter {
+    fn on_iteration_complete(&mut self, summary: &IterationSummary) {
+        println!(
+            "Depth {}, score {}, nodes {}, {}ms: {}",
+            summary.depth,
+            Score::from(summary.relative_eval),
+            summary.nodes,
+            summary.elapsed.as_millis(),
+            summary.optimal_path.iter().join(" ")
+        );
+    }
+}
+
 fn run_search(mut state: TreeNode, depth: usize, table_size: usize) {
     if depth == 0 {
         println!("Static: {}", state.relative_eval());
@@ -107,8 +193,53 @@ fn run_search(mut state: TreeNode, depth: usize, table_size: usize) {
     } else {
         let outcome = hyperopic::search::search(
             state,
-            SearchParameters { end: depth, table: &mut DebugTranspositions::new(table_size) },
+            SearchParameters {
+                end: depth,
+                table: &mut DebugTranspositions::new(table_size),
+                observer: &mut ProgressPrinter,
+                multi_pv: MultiPv::default(),
+                tablebase: None,
+            },
         );
         println!("{}", serde_json::to_string_pretty(&outcome.unwrap()).unwrap());
     }
 }
+
+fn print_eval(node: &TreeNode) {
+    let breakdown = node.eval_breakdown();
+    let pair = |(mid, end): (i32, i32)| format!("{:>6} {:>6}", mid, end);
+    println!("{}", node.position());
+    println!("Phase: {} / 256", breakdown.phase);
+    println!();
+    let phases = format!("{:>6} {:>6}", "Mid", "End");
+    println!("{:>17} | {:^13} | {:^13} | {:^13} |", "", "White", "Black", "Total");
+    println!("{:>17} | {} | {} | {} | Tapered", "Facet", phases, phases, phases);
+    println!("{}", "-".repeat(75));
+    for facet in breakdown.facets.iter() {
+        println!(
+            "{:>17} | {} | {} | {} | {:>7}",
+            facet.name,
+            pair(facet.white),
+            pair(facet.black),
+            pair(facet.total),
+            facet.tapered
+        );
+    }
+    println!("{}", "-".repeat(75));
+    println!("Total (white): {}", breakdown.total);
+    println!("Relative (side to move): {}", breakdown.relative);
+}
+
+fn run_perft(mut position: Position, depth: usize) {
+    let start = Instant::now();
+    let divided = hyperopic::perft::divide(&mut position, depth).unwrap();
+    let elapsed = start.elapsed();
+    for (m, count) in divided.iter().sorted_by_key(|(m, _)| m.to_string()) {
+        println!("{}: {}", m, count);
+    }
+    let total: u64 = divided.iter().map(|(_, count)| count).sum();
+    println!();
+    println!("Nodes: {}", total);
+    println!("Time: {}ms", elapsed.as_millis());
+    println!("Nodes/sec: {}", (total as f64 / elapsed.as_secs_f64()).round() as u64);
+}
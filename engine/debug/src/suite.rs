@@ -0,0 +1,149 @@
+use std::fs;
+use std::time::Duration;
+
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+use hyperopic::epd::EpdEntry;
+use hyperopic::search::end::{NodeLimit, SearchEnd};
+use hyperopic::search::{search, MultiPv, SearchOutcome, SearchParameters, TranspositionsImpl};
+
+/// The limit applied to the search of every position in a suite
+#[derive(Debug, Copy, Clone)]
+pub enum Budget {
+    Depth(usize),
+    Nodes(u64),
+    Time(Duration),
+}
+
+impl Budget {
+    fn to_json(self) -> Value {
+        match self {
+            Budget::Depth(depth) => json!({ "depth": depth }),
+            Budget::Nodes(nodes) => json!({ "nodes": nodes }),
+            Budget::Time(time) => json!({ "time_ms": time.as_millis() as u64 }),
+        }
+    }
+
+    fn search(self, entry: &EpdEntry, table_size: usize) -> Result<SearchOutcome, String> {
+        match self {
+            Budget::Depth(depth) => search_with(entry, depth, table_size),
+            Budget::Nodes(nodes) => search_with(entry, NodeLimit(nodes), table_size),
+            Budget::Time(time) => search_with(entry, time, table_size),
+        }
+    }
+}
+
+/// Each position gets a fresh table so results don't depend on suite order
+fn search_with<E: SearchEnd>(
+    entry: &EpdEntry,
+    end: E,
+    table_size: usize,
+) -> Result<SearchOutcome, String> {
+    let mut table = TranspositionsImpl::new(table_size);
+    let params = SearchParameters {
+        end,
+        table: &mut table,
+        observer: &mut (),
+        multi_pv: MultiPv::default(),
+        tablebase: None,
+    };
+    search(entry.position.clone().into(), params).map_err(|e| e.to_string())
+}
+
+fn san_list(entry: &EpdEntry, moves: &[hyperopic::moves::Move]) -> Vec<String> {
+    moves.iter().map(|m| entry.position.to_san(m).unwrap()).collect()
+}
+
+pub fn run_suite(path: &str, budget: Budget, table_size: usize, output: Option<String>) {
+    let content = fs::read_to_string(path).unwrap();
+    let (mut solved, mut failed, mut errors) = (0, 0, 0);
+    let (mut total_nodes, mut total_time) = (0u64, Duration::ZERO);
+    let mut results = vec![];
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = match line.parse::<EpdEntry>() {
+            Err(e) => {
+                eprintln!("ERROR line {}: {}", line_number, e);
+                errors += 1;
+                results.push(json!({ "line": line_number, "error": e.to_string() }));
+                continue;
+            }
+            Ok(entry) => entry,
+        };
+        let id = entry.id.clone().unwrap_or(format!("line {}", line_number));
+        let expected = san_list(&entry, &entry.best_moves);
+        let avoid = san_list(&entry, &entry.avoid_moves);
+        match budget.search(&entry, table_size) {
+            Err(e) => {
+                eprintln!("ERROR {}: {}", id, e);
+                errors += 1;
+                results.push(json!({ "line": line_number, "id": id, "error": e }));
+            }
+            Ok(outcome) => {
+                let is_solved = entry.is_solved(&outcome.best_move, outcome.score);
+                if is_solved {
+                    solved += 1
+                } else {
+                    failed += 1
+                }
+                total_nodes += outcome.statistics.nodes;
+                total_time += outcome.time;
+                let found = entry.position.to_san(&outcome.best_move).unwrap();
+                eprintln!(
+                    "{} {}: found {}, expected {}{}{}, score {}, depth {}, {} nodes, {}ms",
+                    if is_solved { "PASS" } else { "FAIL" },
+                    id,
+                    found,
+                    if expected.is_empty() { "-".to_owned() } else { expected.iter().join(" ") },
+                    if avoid.is_empty() {
+                        "".to_owned()
+                    } else {
+                        format!(" avoiding {}", avoid.iter().join(" "))
+                    },
+                    entry.direct_mate.map(|n| format!(" mate in {}", n)).unwrap_or_default(),
+                    outcome.score,
+                    outcome.depth,
+                    outcome.statistics.nodes,
+                    outcome.time.as_millis()
+                );
+                results.push(json!({
+                    "line": line_number,
+                    "id": id,
+                    "fen": entry.position.to_string(),
+                    "solved": is_solved,
+                    "best_move": found,
+                    "expected": expected,
+                    "avoid": avoid,
+                    "score": outcome.score,
+                    "depth": outcome.depth,
+                    "nodes": outcome.statistics.nodes,
+                    "time_ms": outcome.time.as_millis() as u64,
+                }));
+            }
+        }
+    }
+    let attempted = solved + failed;
+    eprintln!();
+    eprintln!("Solved: {}/{}", solved, attempted);
+    eprintln!("Failed: {}", failed);
+    eprintln!("Errors: {}", errors);
+    let summary = json!({
+        "suite": path,
+        "budget": budget.to_json(),
+        "solved": solved,
+        "failed": failed,
+        "errors": errors,
+        "nodes": total_nodes,
+        "time_ms": total_time.as_millis() as u64,
+        "positions": results,
+    });
+    let summary = serde_json::to_string_pretty(&summary).unwrap();
+    match output {
+        Some(path) => fs::write(path, summary).unwrap(),
+        None => println!("{}", summary),
+    }
+}
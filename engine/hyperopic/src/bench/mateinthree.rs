@@ -5,10 +5,10 @@ use std::time::Duration;
 
 use regex::Regex;
 
-use crate::node::{TreeNode, WIN_VALUE};
+use crate::node::TreeNode;
 use crate::position::Position;
-use crate::search::{search, SearchParameters};
-use crate::{Move, TranspositionsImpl};
+use crate::search::{search, MultiPv, Score, SearchParameters, TranspositionsImpl};
+use crate::Move;
 
 #[rustfmt::skip]
 ///
@@ -143,11 +143,11 @@ fn benchmark() {
             print_progress(case_count, err_count, search_duration.clone());
         }
         let board_fen = test_case.eval.position().to_string();
-        match search(test_case.eval, SearchParameters { end: depth, table: &mut TranspositionsImpl::new(table_size) }) {
+        match search(test_case.eval, SearchParameters { end: depth, table: &mut TranspositionsImpl::new(table_size), observer: &mut (), multi_pv: MultiPv::default(), tablebase: None }) {
             Err(message) => panic!("{}", message),
             Ok(outcome) => {
                 search_duration += outcome.time;
-                if test_case.expected_move != outcome.best_move || WIN_VALUE != outcome.relative_eval {
+                if test_case.expected_move != outcome.best_move || outcome.score != Score::Mate(3) {
                     err_count += 1;
                     println!(
                         "Error at {}: Position {}, expected {}, actual {}",
@@ -4,8 +4,7 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::time::Instant;
 
-use crate::search::SearchParameters;
-use crate::TranspositionsImpl;
+use crate::search::{MultiPv, SearchParameters, TranspositionsImpl};
 
 #[rustfmt::skip]
 /// Run on system76
@@ -81,6 +80,9 @@ fn benchmark() -> Result<(), Box<dyn Error>> {
         best_moves.push(crate::search::search(position.into(), SearchParameters {
             end: depth,
             table: &mut TranspositionsImpl::new(table_size),
+            observer: &mut (),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
         })?)
     }
     println!("Successfully computed {} moves at depth {} in {}ms", best_moves.len(), depth, start.elapsed().as_millis());
@@ -47,6 +47,7 @@ pub mod boards {
     pub const EMPTY: Board = 0u64;
     pub const ALL: Board = !0u64;
     pub const RIM: Board = board!(A1 => A8, H1; H8 => A8, H1);
+    pub const LIGHT_SQUARES: Board = 0xAA55AA55AA55AA55u64;
 
     pub const RANKS: [Board; 8] = [
         board!(A1 => H1),
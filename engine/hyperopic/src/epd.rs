@@ -0,0 +1,214 @@
+use std::str::FromStr;
+
+use anyhow::{anyhow, Error, Result};
+
+use crate::moves::{Move, Notation};
+use crate::position::Position;
+use crate::search::Score;
+
+/// A single line of an EPD file, i.e. a position without move counters
+/// followed by a list of operations. The operations used by test suites
+/// are given their own fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpdEntry {
+    pub position: Position,
+    /// The "id" operation naming the position within its suite
+    pub id: Option<String>,
+    /// The "bm" operation, any one of these moves solves the position
+    pub best_moves: Vec<Move>,
+    /// The "am" operation, none of these moves may be played
+    pub avoid_moves: Vec<Move>,
+    /// The "ce" operation, the evaluation of the position in centipawns
+    pub centipawns: Option<i32>,
+    /// The "dm" operation, the side to move can force mate in this many moves
+    pub direct_mate: Option<i32>,
+    /// Every operation in the order given, including the ones above
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl EpdEntry {
+    pub fn operation(&self, opcode: &str) -> Option<&[String]> {
+        self.operations.iter().find(|(op, _)| op == opcode).map(|(_, operands)| operands.as_slice())
+    }
+
+    /// Whether a search result satisfies every criterion of this entry, an
+    /// entry with no criteria is trivially solved.
+    pub fn is_solved(&self, best_move: &Move, score: Score) -> bool {
+        let best = self.best_moves.is_empty() || self.best_moves.contains(best_move);
+        let avoided = !self.avoid_moves.contains(best_move);
+        let mate = match (self.direct_mate, score) {
+            (None, _) => true,
+            (Some(expected), Score::Mate(actual)) => 0 < actual && actual <= expected,
+            (Some(_), Score::Centipawns(_)) => false,
+        };
+        best && avoided && mate
+    }
+}
+
+impl FromStr for EpdEntry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s.trim();
+        let mut fen_fields = vec![];
+        for _ in 0..4 {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            if end == 0 {
+                return Err(anyhow!("Expected four position fields in {}", s));
+            }
+            fen_fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+        let operations = parse_operations(rest)?;
+        let operand = |opcode: &str| {
+            operations
+                .iter()
+                .find(|(op, _)| op == opcode)
+                .and_then(|(_, operands)| operands.first())
+                .map(|o| o.as_str())
+        };
+        let fen = format!(
+            "{} {} {}",
+            fen_fields.join(" "),
+            operand("hmvc").unwrap_or("0"),
+            operand("fmvn").unwrap_or("1")
+        );
+        let position = Position::from_fen(fen.as_str())?;
+        let parse_moves = |opcode: &str| -> Result<Vec<Move>> {
+            let operands = operations.iter().filter(|(op, _)| op == opcode).flat_map(|(_, o)| o);
+            operands.map(|m| parse_move(&position, m)).collect()
+        };
+        Ok(EpdEntry {
+            id: operand("id").map(|s| s.to_owned()),
+            best_moves: parse_moves("bm")?,
+            avoid_moves: parse_moves("am")?,
+            centipawns: operand("ce").map(|s| s.parse()).transpose()?,
+            direct_mate: operand("dm").map(|s| s.parse()).transpose()?,
+            operations: operations.clone(),
+            position,
+        })
+    }
+}
+
+/// Suites conventionally give moves in SAN but some use UCI notation
+fn parse_move(position: &Position, input: &str) -> Result<Move> {
+    position
+        .parse_move(input, Notation::San)
+        .or_else(|_| position.parse_move(input, Notation::Uci))
+        .map_err(|e| anyhow!("Bad move {} in {}: {}", input, position, e))
+}
+
+/// Operations are an opcode followed by zero or more operands and terminated
+/// by a semicolon, operands containing whitespace are double quoted.
+fn parse_operations(input: &str) -> Result<Vec<(String, Vec<String>)>> {
+    let mut operations = vec![];
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(operations);
+        }
+        let mut opcode = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+            opcode.push(c);
+        }
+        let mut operands = vec![];
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            match chars.next() {
+                None | Some(';') => break,
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next() {
+                            None => return Err(anyhow!("Unterminated string in {}", input)),
+                            Some('"') => break,
+                            Some(c) => operand.push(c),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = c.to_string();
+                    while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != ';') {
+                        operand.push(c);
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+        operations.push((opcode, operands));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EpdEntry;
+    use crate::constants::piece;
+    use crate::constants::square::*;
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::search::Score;
+    use anyhow::Result;
+
+    #[test]
+    fn parse_wac_entry() -> Result<()> {
+        let entry: EpdEntry =
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";"
+                .parse()?;
+        assert_eq!(
+            Position::from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1")?,
+            entry.position
+        );
+        assert_eq!(Some("WAC.001".to_owned()), entry.id);
+        assert_eq!(
+            vec![Move::Normal { moving: piece::WQ, from: G3, dest: G6, capture: None }],
+            entry.best_moves
+        );
+        assert!(entry.avoid_moves.is_empty());
+        assert_eq!(2, entry.operations.len());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_all_operations() -> Result<()> {
+        let entry: EpdEntry = "8/8/8/4Q3/8/6R1/2n1pkBK/8 w - - am Kh1 Kh3; bm Rd3 Rc3;ce 32765; \
+            dm 2; c0 \"quoted; operand\"; hmvc 12; fmvn 40"
+            .parse()?;
+        assert_eq!(2, entry.best_moves.len());
+        assert_eq!(2, entry.avoid_moves.len());
+        assert_eq!(Some(32765), entry.centipawns);
+        assert_eq!(Some(2), entry.direct_mate);
+        assert_eq!(Some(&["quoted; operand".to_owned()][..]), entry.operation("c0"));
+        assert_eq!(12, entry.position.clock);
+        assert_eq!(None, entry.id);
+        Ok(())
+    }
+
+    #[test]
+    fn uci_moves_accepted() -> Result<()> {
+        let entry: EpdEntry = "4k3/8/8/8/8/8/4P3/4K3 w - - bm e2e4;".parse()?;
+        assert_eq!("e2e4", entry.best_moves[0].to_string());
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_entries() {
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w -".parse::<EpdEntry>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - bm Qh5;".parse::<EpdEntry>().is_err());
+        assert!("4k3/8/8/8/8/8/4P3/4K3 w - - id \"unterminated;".parse::<EpdEntry>().is_err());
+    }
+
+    #[test]
+    fn solved_criteria() -> Result<()> {
+        let entry: EpdEntry = "8/8/8/4Q3/8/6R1/2n1pkBK/8 w - - bm Rd3; am Kh3; dm 2;".parse()?;
+        let best = entry.best_moves[0].clone();
+        let avoid = entry.avoid_moves[0].clone();
+        assert!(entry.is_solved(&best, Score::Mate(2)));
+        assert!(entry.is_solved(&best, Score::Mate(1)));
+        assert!(!entry.is_solved(&best, Score::Mate(3)));
+        assert!(!entry.is_solved(&best, Score::Centipawns(900)));
+        assert!(!entry.is_solved(&avoid, Score::Mate(2)));
+        Ok(())
+    }
+}
@@ -1,9 +1,27 @@
-use crate::constants::{corner_side, side};
+use crate::constants::square::*;
+use crate::constants::{class, corner_side, create_piece, side};
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
 use crate::Side;
-use crate::SideMap;
+use crate::{board, Board, SideMap};
+use serde_derive::{Deserialize, Serialize};
+
+/// The penalty for each castling right lost without having castled
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CastlingParams {
+    pub penalty: i32,
+}
+
+impl Default for CastlingParams {
+    fn default() -> Self {
+        CastlingParams { penalty: 70 }
+    }
+}
+
+/// Squares the king of each side is found on after castling
+const CASTLED_KING: SideMap<Board> = [board!(A1, B1, C1, G1, H1), board!(A8, B8, C8, G8, H8)];
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub struct CastlingFacet {
@@ -13,11 +31,34 @@ pub struct CastlingFacet {
 
 impl Default for CastlingFacet {
     fn default() -> Self {
-        CastlingFacet { castling_status: Default::default(), penalty: 70 }
+        CastlingFacet {
+            castling_status: Default::default(),
+            penalty: CastlingParams::default().penalty,
+        }
+    }
+}
+
+impl From<&Position> for CastlingFacet {
+    fn from(value: &Position) -> Self {
+        CastlingFacet::new(&CastlingParams::default(), value)
     }
 }
 
 impl CastlingFacet {
+    /// The history of the position is not available so a side is considered
+    /// castled if it has no rights left and its king is on a castled square.
+    pub fn new(params: &CastlingParams, position: &Position) -> CastlingFacet {
+        let castled = |side: Side| {
+            let rights = &position.castling_rights[2 * side..2 * side + 2];
+            let king = position.piece_boards[create_piece(side, class::K)];
+            rights.iter().all(|&r| !r) && king & CASTLED_KING[side] != 0
+        };
+        CastlingFacet {
+            castling_status: [castled(side::W), castled(side::B)],
+            penalty: params.penalty,
+        }
+    }
+
     fn penalty(&self, side: Side, rights_left: usize) -> i32 {
         if self.castling_status[side] {
             0
@@ -37,6 +78,15 @@ impl EvalFacet for CastlingFacet {
         )
     }
 
+    fn name(&self) -> &'static str {
+        "Castling"
+    }
+
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+        let rights = &board.castling_rights[2 * side..2 * side + 2];
+        Evaluation::Single(-self.penalty(side, rights.iter().filter(|&&r| r).count()))
+    }
+
     fn make(&mut self, mv: &Move, _: &Position) {
         if let Move::Castle { corner, .. } = mv {
             self.castling_status[corner_side(*corner)] = true
@@ -54,8 +104,30 @@ impl EvalFacet for CastlingFacet {
 mod test {
     use crate::constants::side;
     use crate::eval::castling::CastlingFacet;
+    use crate::position::Position;
     use crate::test::facets::test_facet_evolution;
 
+    fn castling_status(fen: &str) -> [bool; 2] {
+        CastlingFacet::from(&fen.parse::<Position>().unwrap()).castling_status
+    }
+
+    #[test]
+    fn from_position() {
+        assert_eq!([false, false], CastlingFacet::from(&Position::default()).castling_status);
+        assert_eq!(
+            [true, false],
+            castling_status("r1bqk2r/pppp1ppp/2n2n2/2b1p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4")
+        );
+        assert_eq!(
+            [false, true],
+            castling_status("2kr3r/ppp2ppp/2n5/8/8/2N5/PPP2PPP/R3K2R w - - 0 12")
+        );
+        assert_eq!(
+            [false, false],
+            castling_status("r3k2r/ppp2ppp/2n5/8/8/2N5/PPP2PPP/R4K1R w - - 0 12")
+        );
+    }
+
     #[test]
     fn evaluation_not_castled() {
         let under_test = CastlingFacet { penalty: 100, castling_status: [false, false] };
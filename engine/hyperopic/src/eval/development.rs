@@ -1,14 +1,15 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 
-use crate::constants::side;
 use crate::constants::square::*;
+use crate::constants::{class, create_piece, side};
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
 use crate::{square_map, SideMap};
-use crate::{Side, SquareMap};
+use crate::{Class, Side, SquareMap};
 use lazy_static::lazy_static;
 use rustc_hash::FxHashSet;
+use serde_derive::{Deserialize, Serialize};
 
 type DevPiece = usize;
 type DevPieceMap<T> = [T; 6];
@@ -24,6 +25,10 @@ mod dev_piece {
     pub const FB: DevPiece = 5;
 }
 
+/// The class of each piece tracked for development
+const DEV_CLASSES: DevPieceMap<Class> =
+    [class::P, class::P, class::N, class::N, class::B, class::B];
+
 lazy_static! {
     static ref START_LOCS: SquareMap<Option<(Side, DevPiece)>> = square_map! {
         E2 => Some((side::W, dev_piece::EP)),
@@ -41,7 +46,22 @@ lazy_static! {
     };
 }
 
-const MAX_PENALTY: i32 = 300;
+/// Each side is penalised for its undeveloped central pawns and minor pieces,
+/// the cost of each doubles every time the given number of moves is played.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DevelopmentParams {
+    pub undeveloped_cost: usize,
+    pub move_index_divisor: usize,
+    pub max_penalty: i32,
+}
+
+impl Default for DevelopmentParams {
+    fn default() -> Self {
+        DevelopmentParams { undeveloped_cost: 10, move_index_divisor: 10, max_penalty: 300 }
+    }
+}
+
 type PiecesMoved = SideMap<DevPieceMap<Option<usize>>>;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -56,18 +76,49 @@ pub struct DevelopmentFacet {
 
 impl Default for DevelopmentFacet {
     fn default() -> Self {
+        let params = DevelopmentParams::default();
         DevelopmentFacet {
             move_index: 0,
             pieces_moved: Default::default(),
-            undeveloped_cost: 10,
-            move_index_divisor: 10,
-            max_penalty: MAX_PENALTY,
+            undeveloped_cost: params.undeveloped_cost,
+            move_index_divisor: params.move_index_divisor,
+            max_penalty: params.max_penalty,
             dev_indices: FxHashSet::default(),
         }
     }
 }
 
+impl From<&Position> for DevelopmentFacet {
+    fn from(value: &Position) -> Self {
+        DevelopmentFacet::new(&DevelopmentParams::default(), value)
+    }
+}
+
 impl DevelopmentFacet {
+    /// Any piece not on its starting square is taken to have been developed.
+    /// The number of moves played before the position is not known so it is
+    /// estimated as the fewest needed to develop the pieces of each side.
+    pub fn new(params: &DevelopmentParams, position: &Position) -> DevelopmentFacet {
+        let mut pieces_moved = PiecesMoved::default();
+        for (square, start) in START_LOCS.iter().enumerate() {
+            if let Some((side, piece)) = *start {
+                if position.piece_locs[square] != Some(create_piece(side, DEV_CLASSES[piece])) {
+                    // Earlier than any move which can be unmade from here
+                    pieces_moved[side][piece] = Some(0);
+                }
+            }
+        }
+        let developed = |side: Side| pieces_moved[side].iter().filter(|m| m.is_some()).count();
+        DevelopmentFacet {
+            move_index: max((2 * developed(side::W)).saturating_sub(1), 2 * developed(side::B)),
+            pieces_moved,
+            undeveloped_cost: params.undeveloped_cost,
+            move_index_divisor: params.move_index_divisor,
+            max_penalty: params.max_penalty,
+            dev_indices: FxHashSet::default(),
+        }
+    }
+
     fn matching_piece(&self, move_count: usize) -> Option<(Side, DevPiece)> {
         if !self.dev_indices.contains(&move_count) {
             return None;
@@ -102,6 +153,14 @@ impl EvalFacet for DevelopmentFacet {
         Evaluation::Single(self.penalty(side::B) - self.penalty(side::W))
     }
 
+    fn name(&self) -> &'static str {
+        "Development"
+    }
+
+    fn side_eval(&self, _: &Position, side: Side) -> Evaluation {
+        Evaluation::Single(-self.penalty(side))
+    }
+
     fn make(&mut self, mv: &Move, _: &Position) {
         if let &Move::Normal { from, .. } = mv {
             if let Some((side, piece)) = START_LOCS[from] {
@@ -134,6 +193,18 @@ mod test {
     use crate::position::Position;
     use crate::test::facets::test_facet_evolution;
 
+    #[test]
+    fn from_position() {
+        let position = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+        let under_test = DevelopmentFacet::from(&position.parse::<Position>().unwrap());
+        assert_eq!(3, under_test.move_index);
+        assert_eq!(
+            [[Some(0), None, None, Some(0), None, None], [Some(0), None, None, None, None, None]],
+            under_test.pieces_moved
+        );
+        assert!(under_test.dev_indices.is_empty());
+    }
+
     #[test]
     fn penalty_test() {
         let under_test = DevelopmentFacet {
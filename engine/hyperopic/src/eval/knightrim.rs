@@ -1,11 +1,13 @@
+use crate::board::iter;
 use crate::constants::boards::RIM;
-use crate::constants::in_board;
+use crate::constants::{class, create_piece, in_board};
 use crate::constants::{side, square::*};
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
 use crate::{square_map, Side, SideMap, Square, SquareMap};
 use lazy_static::lazy_static;
+use serde_derive::{Deserialize, Serialize};
 
 type Knight = usize;
 type KnightMap<T> = [T; 2];
@@ -19,6 +21,19 @@ mod knight {
 
 type FirstMoveStore = SideMap<KnightMap<Option<(usize, Square)>>>;
 
+/// The penalty for each knight whose first move is onto the rim
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KnightRimParams {
+    pub penalty: i32,
+}
+
+impl Default for KnightRimParams {
+    fn default() -> Self {
+        KnightRimParams { penalty: 80 }
+    }
+}
+
 /// Give penalty for each knight whose first move is onto the board rim
 #[derive(Debug, Clone, PartialEq)]
 pub struct KnightRimFacet {
@@ -29,11 +44,45 @@ pub struct KnightRimFacet {
 
 impl Default for KnightRimFacet {
     fn default() -> Self {
-        KnightRimFacet { penalty: 80, first_move: Default::default(), move_index: 0 }
+        KnightRimFacet {
+            penalty: KnightRimParams::default().penalty,
+            first_move: Default::default(),
+            move_index: 0,
+        }
+    }
+}
+
+impl From<&Position> for KnightRimFacet {
+    fn from(value: &Position) -> Self {
+        KnightRimFacet::new(&KnightRimParams::default(), value)
     }
 }
 
 impl KnightRimFacet {
+    /// The first move of a knight which has left its starting square is not
+    /// known so it is taken to be the square one of the moved knights of that
+    /// side stands on now. These moves are placed before any which can be
+    /// unmade.
+    pub fn new(params: &KnightRimParams, position: &Position) -> KnightRimFacet {
+        let mut first_move = FirstMoveStore::default();
+        for side in [side::W, side::B] {
+            let knight = create_piece(side, class::N);
+            let starts: Vec<_> = START_LOCS
+                .iter()
+                .enumerate()
+                .filter_map(|(sq, start)| start.filter(|(s, _)| *s == side).map(|(_, k)| (sq, k)))
+                .collect();
+            let mut moved_knights = iter(position.piece_boards[knight])
+                .filter(|sq| starts.iter().all(|(start, _)| start != sq));
+            for (start, k) in starts.iter() {
+                if position.piece_locs[*start] != Some(knight) {
+                    first_move[side][*k] = moved_knights.next().map(|sq| (0, sq));
+                }
+            }
+        }
+        KnightRimFacet { penalty: params.penalty, first_move, move_index: 1 }
+    }
+
     fn pattern_count(&self, side: Side) -> i32 {
         self.first_move[side]
             .iter()
@@ -58,6 +107,14 @@ impl EvalFacet for KnightRimFacet {
         )
     }
 
+    fn name(&self) -> &'static str {
+        "KnightRim"
+    }
+
+    fn side_eval(&self, _: &Position, side: Side) -> Evaluation {
+        Evaluation::Single(-self.penalty * self.pattern_count(side))
+    }
+
     fn make(&mut self, mv: &Move, _: &Position) {
         if let Move::Normal { from, dest, .. } = mv {
             if let Some((side, knight)) = START_LOCS[*from] {
@@ -92,6 +149,18 @@ mod test {
     use crate::position::Position;
     use crate::test::facets::test_facet_evolution;
 
+    #[test]
+    fn from_position() {
+        let position = "r1bqkbnr/pppppppp/2n5/8/8/N7/PPPPPPPP/R1BQKBNR w KQkq - 2 2";
+        let facet = KnightRimFacet::from(&position.parse::<Position>().unwrap());
+        assert_eq!([[Some((0, A3)), None], [Some((0, C6)), None]], facet.first_move);
+        assert_eq!(Evaluation::Single(-80), facet.static_eval(&Position::default()));
+        // A captured knight can no longer be on the rim
+        let position = "rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let facet = KnightRimFacet::from(&position.parse::<Position>().unwrap());
+        assert_eq!([[None, None], [None, None]], facet.first_move);
+    }
+
     #[test]
     fn evaluation() {
         let facet = KnightRimFacet {
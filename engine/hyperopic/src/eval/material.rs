@@ -1,12 +1,30 @@
 use crate::constants::{class, create_piece, piece_class, piece_side, reflect_side, side_parity};
-use crate::{ClassMap, Piece};
+use crate::{ClassMap, Piece, Side};
 
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
+use serde_derive::{Deserialize, Serialize};
 
 pub type PieceValues = ClassMap<i32>;
 
+/// The value of each class of piece in the midgame and endgame
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MaterialParams {
+    pub mid_values: PieceValues,
+    pub end_values: PieceValues,
+}
+
+impl Default for MaterialParams {
+    fn default() -> Self {
+        MaterialParams {
+            mid_values: [230, 782, 830, 1289, 2529, 100_000],
+            end_values: [300, 865, 918, 1378, 2687, 100_000],
+        }
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct MaterialFacet {
     mid_values: PieceValues,
@@ -17,27 +35,37 @@ pub struct MaterialFacet {
 
 impl Default for MaterialFacet {
     fn default() -> Self {
+        let params = MaterialParams::default();
         MaterialFacet {
             mid_eval: 0,
             end_eval: 0,
-            mid_values: [230, 782, 830, 1289, 2529, 100_000],
-            end_values: [300, 865, 918, 1378, 2687, 100_000],
+            mid_values: params.mid_values,
+            end_values: params.end_values,
         }
     }
 }
 
 impl<'a> From<&'a Position> for MaterialFacet {
     fn from(value: &Position) -> Self {
-        let mut facet = MaterialFacet::default();
-        facet.mid_eval = facet.compute_midgame_eval(value);
-        facet.end_eval = facet.compute_endgame_eval(value);
-        facet
+        MaterialFacet::new(&MaterialParams::default(), value)
     }
 }
 
 type UpdateFn = fn(&mut MaterialFacet, Piece) -> ();
 
 impl MaterialFacet {
+    pub fn new(params: &MaterialParams, position: &Position) -> MaterialFacet {
+        let mut facet = MaterialFacet {
+            mid_eval: 0,
+            end_eval: 0,
+            mid_values: params.mid_values,
+            end_values: params.end_values,
+        };
+        facet.mid_eval = facet.compute_midgame_eval(position);
+        facet.end_eval = facet.compute_endgame_eval(position);
+        facet
+    }
+
     pub fn mid_values(&self) -> &PieceValues {
         &self.mid_values
     }
@@ -98,6 +126,23 @@ impl EvalFacet for MaterialFacet {
         Evaluation::Phased { mid: self.mid_eval, end: self.end_eval }
     }
 
+    fn name(&self) -> &'static str {
+        "Material"
+    }
+
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+        // Kings are always on the board and cancel out
+        let classes = (0..64)
+            .flat_map(|square| board.piece_locs[square])
+            .filter(|&p| piece_side(p) == side && piece_class(p) != class::K)
+            .map(piece_class)
+            .collect::<Vec<_>>();
+        Evaluation::Phased {
+            mid: classes.iter().map(|&c| self.mid_values[c]).sum(),
+            end: classes.iter().map(|&c| self.end_values[c]).sum(),
+        }
+    }
+
     fn make(&mut self, mv: &Move, _: &Position) {
         self.make_impl(mv, MaterialFacet::add, MaterialFacet::remove)
     }
@@ -2,6 +2,7 @@ mod castling;
 mod development;
 mod knightrim;
 pub mod material;
+mod params;
 mod pawns;
 mod safety;
 pub mod tables;
@@ -10,6 +11,7 @@ pub use castling::CastlingFacet;
 pub use development::DevelopmentFacet;
 pub use knightrim::KnightRimFacet;
 pub use material::{MaterialFacet, PieceValues};
+pub use params::EvalParams;
 pub use pawns::PawnStructureFacet;
 pub use safety::SafetyFacet;
 pub use tables::{PieceSquareTablesFacet, PositionTables};
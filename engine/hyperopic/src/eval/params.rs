@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::eval::castling::CastlingParams;
+use crate::eval::development::DevelopmentParams;
+use crate::eval::knightrim::KnightRimParams;
+use crate::eval::material::MaterialParams;
+use crate::eval::pawns::PawnParams;
+use crate::eval::safety::SafetyParams;
+use crate::eval::tables::TableParams;
+
+/// Every weight used by the evaluation facets. The default is the tuned set
+/// compiled into the engine, a config only needs to list the values which
+/// differ from it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalParams {
+    pub material: MaterialParams,
+    pub tables: TableParams,
+    pub pawns: PawnParams,
+    pub safety: SafetyParams,
+    pub development: DevelopmentParams,
+    pub knight_rim: KnightRimParams,
+    pub castling: CastlingParams,
+}
+
+impl EvalParams {
+    pub fn from_json(json: &str) -> Result<EvalParams> {
+        serde_json::from_str(json).map_err(|e| anyhow!("Bad eval params: {}", e))
+    }
+
+    pub fn from_toml(toml: &str) -> Result<EvalParams> {
+        toml::from_str(toml).map_err(|e| anyhow!("Bad eval params: {}", e))
+    }
+
+    /// Load params from a file, parsed as toml if it has the toml extension
+    /// and as json otherwise.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<EvalParams> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => EvalParams::from_toml(&content),
+            _ => EvalParams::from_json(&content),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::EvalParams;
+
+    #[test]
+    fn json_round_trip() {
+        let params = EvalParams::default();
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(params, EvalParams::from_json(&json).unwrap());
+    }
+
+    #[test]
+    fn partial_json() {
+        let params =
+            EvalParams::from_json(r#"{"castling": {"penalty": 20}, "material": {}}"#).unwrap();
+        let mut expected = EvalParams::default();
+        expected.castling.penalty = 20;
+        assert_eq!(expected, params);
+    }
+
+    #[test]
+    fn partial_toml() {
+        let params = EvalParams::from_toml(
+            "[pawns]\nisolated_pawn_penalty = [-20, -10]\n\n[knight_rim]\npenalty = 0\n",
+        )
+        .unwrap();
+        let mut expected = EvalParams::default();
+        expected.pawns.isolated_pawn_penalty = (-20, -10);
+        expected.knight_rim.penalty = 0;
+        assert_eq!(expected, params);
+    }
+
+    #[test]
+    fn bad_value() {
+        assert!(EvalParams::from_json(r#"{"safety": {"control_bonus": "high"}}"#).is_err());
+    }
+}
@@ -4,40 +4,57 @@ use itertools::Itertools;
 use std::hash::Hasher;
 
 use rustc_hash::FxHasher;
+use serde_derive::{Deserialize, Serialize};
 
-use crate::board::iter;
-use crate::constants::boards::{ADJACENT_FILES, EMPTY, FILES, RANKS};
-use crate::constants::{class, create_piece, lift, side, square_rank};
+use crate::board::{control, iter};
+use crate::constants::boards::{ADJACENT_FILES, ALL, EMPTY, FILES, RANKS};
+use crate::constants::{
+    class, create_piece, first_square, in_board, lift, reflect_side, side, square_file, square_rank,
+};
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
-use crate::Board;
+use crate::{Board, Side, Square};
 
 const WHITE_HALF: Board = RANKS[0] | RANKS[1] | RANKS[2] | RANKS[3];
 const BLACK_HALF: Board = RANKS[4] | RANKS[5] | RANKS[6] | RANKS[7];
 
 type Score = (i32, i32);
 
-#[derive(Clone, PartialEq)]
+/// The evaluation of the terms which only depend on the pawns along with the
+/// passed pawns of each side, which the king terms are computed from.
+#[derive(Copy, Clone, PartialEq)]
 struct CachedEval {
     whites: Board,
     blacks: Board,
+    passers: (Board, Board),
     mid: i32,
     end: i32,
 }
 
-pub struct PawnStructureFacet {
-    doubled_pawn_penalty: Score,
-    isolated_pawn_penalty: Score,
-    connected_passer_bonus: Score,
-    passer_rank_bonuses: [Score; 6],
-    cache: RefCell<Vec<Option<CachedEval>>>,
+/// The (midgame, endgame) bonuses and penalties for features of the pawn
+/// structure, passed pawn bonuses are listed from the starting rank. The king
+/// proximity bonus is given for each square a passer's stop square is nearer
+/// to its own king than to the enemy king.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PawnParams {
+    pub doubled_pawn_penalty: Score,
+    pub isolated_pawn_penalty: Score,
+    pub connected_passer_bonus: Score,
+    pub passer_rank_bonuses: [Score; 6],
+    pub backward_pawn_penalty: Score,
+    pub pawn_island_penalty: Score,
+    pub phalanx_pawn_bonus: Score,
+    pub connected_pawn_bonus: Score,
+    pub candidate_passer_bonus: Score,
+    pub unstoppable_passer_bonus: Score,
+    pub king_passer_proximity: Score,
 }
 
-impl Default for PawnStructureFacet {
+impl Default for PawnParams {
     fn default() -> Self {
-        PawnStructureFacet {
-            cache: RefCell::new(vec![None; 10000]),
+        PawnParams {
             doubled_pawn_penalty: (-15, -25),
             isolated_pawn_penalty: (-10, -5),
             connected_passer_bonus: (60, 110),
@@ -51,6 +68,54 @@ impl Default for PawnStructureFacet {
                 // Last rank before promotion
                 (160, 200),
             ],
+            backward_pawn_penalty: (-8, -10),
+            pawn_island_penalty: (-5, -10),
+            phalanx_pawn_bonus: (5, 5),
+            connected_pawn_bonus: (8, 6),
+            candidate_passer_bonus: (10, 20),
+            unstoppable_passer_bonus: (0, 400),
+            king_passer_proximity: (0, 5),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PawnStructureFacet {
+    doubled_pawn_penalty: Score,
+    isolated_pawn_penalty: Score,
+    connected_passer_bonus: Score,
+    passer_rank_bonuses: [Score; 6],
+    backward_pawn_penalty: Score,
+    pawn_island_penalty: Score,
+    phalanx_pawn_bonus: Score,
+    connected_pawn_bonus: Score,
+    candidate_passer_bonus: Score,
+    unstoppable_passer_bonus: Score,
+    king_passer_proximity: Score,
+    cache: RefCell<Vec<Option<CachedEval>>>,
+}
+
+impl Default for PawnStructureFacet {
+    fn default() -> Self {
+        PawnStructureFacet::from(&PawnParams::default())
+    }
+}
+
+impl From<&PawnParams> for PawnStructureFacet {
+    fn from(params: &PawnParams) -> Self {
+        PawnStructureFacet {
+            cache: RefCell::new(vec![None; 10000]),
+            doubled_pawn_penalty: params.doubled_pawn_penalty,
+            isolated_pawn_penalty: params.isolated_pawn_penalty,
+            connected_passer_bonus: params.connected_passer_bonus,
+            passer_rank_bonuses: params.passer_rank_bonuses,
+            backward_pawn_penalty: params.backward_pawn_penalty,
+            pawn_island_penalty: params.pawn_island_penalty,
+            phalanx_pawn_bonus: params.phalanx_pawn_bonus,
+            connected_pawn_bonus: params.connected_pawn_bonus,
+            candidate_passer_bonus: params.candidate_passer_bonus,
+            unstoppable_passer_bonus: params.unstoppable_passer_bonus,
+            king_passer_proximity: params.king_passer_proximity,
         }
     }
 }
@@ -59,16 +124,83 @@ impl EvalFacet for PawnStructureFacet {
     fn static_eval(&self, board: &Position) -> Evaluation {
         let whites = board.piece_boards[create_piece(side::W, class::P)];
         let blacks = board.piece_boards[create_piece(side::B, class::P)];
+        let pawns = self.pawn_eval(whites, blacks);
+        let (king_mid, king_end) = self.evaluate_passer_kings(board, pawns.passers);
+        Evaluation::Phased { mid: pawns.mid + king_mid, end: pawns.end + king_end }
+    }
+
+    fn name(&self) -> &'static str {
+        "PawnStructure"
+    }
+
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+        let whites = board.piece_boards[create_piece(side::W, class::P)];
+        let blacks = board.piece_boards[create_piece(side::B, class::P)];
+        let (mid, end) = self.side_score(whites, blacks, side);
+        let passers = find_passed_pawns(whites, blacks);
+        let passers = if side == side::W { passers.0 } else { passers.1 };
+        let (king_mid, king_end) = self.side_passer_kings(board, side, passers);
+        Evaluation::Phased { mid: mid + king_mid, end: end + king_end }
+    }
+
+    fn make(&mut self, _: &Move, _: &Position) {}
+
+    fn unmake(&mut self, _: &Move) {}
+}
+
+impl PawnStructureFacet {
+    /// The score of the pawn structure of one side from its own perspective
+    fn side_score(&self, whites: Board, blacks: Board, side: Side) -> Score {
+        let (w_passers, b_passers) = find_passed_pawns(whites, blacks);
+        let (pawns, passers, opponent_half) = if side == side::W {
+            (whites, w_passers, BLACK_HALF)
+        } else {
+            (blacks, b_passers, WHITE_HALF)
+        };
+        let (mut mid, mut end) = (0i32, 0i32);
+        for (i, rank) in RANKS.iter().enumerate().take(7).skip(1) {
+            let count = (passers & rank).count_ones() as i32;
+            let (rank_mid, rank_end) =
+                self.passer_rank_bonuses[if side == side::W { i - 1 } else { 6 - i }];
+            mid += count * rank_mid;
+            end += count * rank_end;
+        }
+        let (con_mid, con_end) = self.connected_passer_bonus;
+        for i in 0..7 {
+            let count =
+                count_connections(FILES[i] & passers, FILES[i + 1] & passers & opponent_half);
+            mid += count * con_mid;
+            end += count * con_end;
+        }
+        // Doubling and isolation only depend on the pawns of the one side
+        let doubled = count_doubled_pawns(pawns, EMPTY);
+        let isolated = count_isolated_pawns(pawns, EMPTY);
+        mid += doubled * self.doubled_pawn_penalty.0 + isolated * self.isolated_pawn_penalty.0;
+        end += doubled * self.doubled_pawn_penalty.1 + isolated * self.isolated_pawn_penalty.1;
+        let other = if side == side::W { blacks } else { whites };
+        let (structure_mid, structure_end) =
+            self.structure_terms().iter().fold((0, 0), |(am, ae), &(feature, (nm, ne))| {
+                let count = feature(side, pawns, other).count_ones() as i32;
+                (am + count * nm, ae + count * ne)
+            });
+        let islands = count_islands(pawns);
+        mid += structure_mid + islands * self.pawn_island_penalty.0;
+        end += structure_end + islands * self.pawn_island_penalty.1;
+        (mid, end)
+    }
+
+    /// Evaluate the terms which only depend on the pawns, the result is cached
+    /// by the pawn boards.
+    fn pawn_eval(&self, whites: Board, blacks: Board) -> CachedEval {
         let mut cache_ref = self.cache.borrow_mut();
         let mut hasher = FxHasher::default();
         hasher.write_u64(whites);
         hasher.write_u64(blacks);
         let hash = hasher.finish();
         let index = (hash % cache_ref.len() as u64) as usize;
-        let existing = cache_ref[index].as_ref();
-        if let Some(entry) = existing {
+        if let Some(entry) = cache_ref[index] {
             if entry.whites == whites && entry.blacks == blacks {
-                return Evaluation::Phased { mid: entry.mid, end: entry.end };
+                return entry;
             }
         }
 
@@ -76,20 +208,55 @@ impl EvalFacet for PawnStructureFacet {
             self.evaluate_passed_pawns(whites, blacks),
             self.evaluate_doubled_pawns(whites, blacks),
             self.evaluate_isolated_pawns(whites, blacks),
+            self.evaluate_structure(whites, blacks),
         ]
         .iter()
         .fold((0, 0), |(am, ae), &(nm, ne)| (am + nm, ae + ne));
 
-        cache_ref[index] = Some(CachedEval { whites, blacks, mid, end });
-        Evaluation::Phased { mid, end }
+        let passers = find_passed_pawns(whites, blacks);
+        let entry = CachedEval { whites, blacks, passers, mid, end };
+        cache_ref[index] = Some(entry);
+        entry
     }
 
-    fn make(&mut self, _: &Move, _: &Position) {}
+    /// The features found separately for the pawns of each side along with
+    /// their scores
+    fn structure_terms(&self) -> [(PawnFeature, Score); 4] {
+        [
+            (backward_pawns, self.backward_pawn_penalty),
+            (phalanx_pawns, self.phalanx_pawn_bonus),
+            (connected_pawns, self.connected_pawn_bonus),
+            (candidate_passers, self.candidate_passer_bonus),
+        ]
+    }
 
-    fn unmake(&mut self, _: &Move) {}
-}
+    fn evaluate_structure(&self, whites: Board, blacks: Board) -> Score {
+        let (mut mid, mut end) = (0i32, 0i32);
+        for (feature, (f_mid, f_end)) in self.structure_terms() {
+            let count = count_feature(feature, whites, blacks);
+            mid += count * f_mid;
+            end += count * f_end;
+        }
+        let islands = count_pawn_islands(whites, blacks);
+        let (i_mid, i_end) = self.pawn_island_penalty;
+        (mid + islands * i_mid, end + islands * i_end)
+    }
+
+    /// The terms for passed pawns which depend on the position of the kings
+    fn evaluate_passer_kings(&self, board: &Position, passers: (Board, Board)) -> Score {
+        let (w_mid, w_end) = self.side_passer_kings(board, side::W, passers.0);
+        let (b_mid, b_end) = self.side_passer_kings(board, side::B, passers.1);
+        (w_mid - b_mid, w_end - b_end)
+    }
+
+    fn side_passer_kings(&self, board: &Position, side: Side, passers: Board) -> Score {
+        let unstoppable = unstoppable_passers(board, side, passers).count_ones() as i32;
+        let proximity = king_passer_proximity(board, side, passers);
+        let (u_mid, u_end) = self.unstoppable_passer_bonus;
+        let (p_mid, p_end) = self.king_passer_proximity;
+        (unstoppable * u_mid + proximity * p_mid, unstoppable * u_end + proximity * p_end)
+    }
 
-impl PawnStructureFacet {
     fn evaluate_passed_pawns(&self, whites: Board, blacks: Board) -> Score {
         let (w_passers, b_passers) = find_passed_pawns(whites, blacks);
         let (mut mid, mut end) = (0i32, 0i32);
@@ -131,6 +298,141 @@ impl PawnStructureFacet {
     }
 }
 
+/// Finds the pawns of the given side with some feature given those pawns and
+/// the pawns of the other side
+type PawnFeature = fn(Side, Board, Board) -> Board;
+
+/// The number of white pawns with the feature minus the number of black pawns
+fn count_feature(feature: PawnFeature, whites: Board, blacks: Board) -> i32 {
+    feature(side::W, whites, blacks).count_ones() as i32
+        - feature(side::B, blacks, whites).count_ones() as i32
+}
+
+/// The squares on the ranks in front of the given square for the given side
+fn ranks_ahead(side: Side, square: Square) -> Board {
+    let rank = square_rank(square);
+    if side == side::W {
+        ALL.checked_shl(8 * (rank as u32 + 1)).unwrap_or(EMPTY)
+    } else {
+        (1u64 << (8 * rank)) - 1
+    }
+}
+
+/// The square directly in front of a pawn of the given side
+fn stop_square(side: Side, square: Square) -> Square {
+    if side == side::W {
+        square + 8
+    } else {
+        square - 8
+    }
+}
+
+fn pawn_control(side: Side, pawns: Board) -> Board {
+    let pawn = create_piece(side, class::P);
+    iter(pawns).fold(EMPTY, |acc, sq| acc | control(pawn, sq, EMPTY))
+}
+
+fn distance(a: Square, b: Square) -> i32 {
+    let rank_diff = (square_rank(a) as i32 - square_rank(b) as i32).abs();
+    let file_diff = (square_file(a) as i32 - square_file(b) as i32).abs();
+    rank_diff.max(file_diff)
+}
+
+/// Pawns which no pawn on an adjacent file can support and whose advance is
+/// controlled by an enemy pawn
+fn backward_pawns(side: Side, pawns: Board, enemies: Board) -> Board {
+    let enemy_control = pawn_control(reflect_side(side), enemies);
+    iter(pawns)
+        .filter(|&sq| {
+            let supporters = ADJACENT_FILES[square_file(sq)] & pawns & !ranks_ahead(side, sq);
+            supporters == EMPTY && in_board(enemy_control, stop_square(side, sq))
+        })
+        .fold(EMPTY, |acc, sq| acc | lift(sq))
+}
+
+/// Pawns with a friendly pawn beside them on the same rank
+fn phalanx_pawns(_: Side, pawns: Board, _: Board) -> Board {
+    iter(pawns)
+        .filter(|&sq| ADJACENT_FILES[square_file(sq)] & RANKS[square_rank(sq)] & pawns != EMPTY)
+        .fold(EMPTY, |acc, sq| acc | lift(sq))
+}
+
+/// Pawns defended by a friendly pawn
+fn connected_pawns(side: Side, pawns: Board, _: Board) -> Board {
+    pawns & pawn_control(side, pawns)
+}
+
+/// Pawns which are not passed but are on a file with no enemy pawns in front
+/// and have at least as many friendly pawns level or behind on the adjacent
+/// files to support their advance as there are enemy pawns in front to stop it
+fn candidate_passers(side: Side, pawns: Board, enemies: Board) -> Board {
+    let passers = if side == side::W {
+        find_passed_pawns(pawns, enemies).0
+    } else {
+        find_passed_pawns(enemies, pawns).1
+    };
+    iter(pawns & !passers)
+        .filter(|&sq| {
+            let (file, ahead) = (square_file(sq), ranks_ahead(side, sq));
+            let helpers = (ADJACENT_FILES[file] & pawns & !ahead).count_ones();
+            let sentries = (ADJACENT_FILES[file] & enemies & ahead).count_ones();
+            FILES[file] & enemies & ahead == EMPTY && helpers >= sentries
+        })
+        .fold(EMPTY, |acc, sq| acc | lift(sq))
+}
+
+/// The number of groups of pawns on adjacent files
+fn count_islands(pawns: Board) -> i32 {
+    let occupied: Vec<_> = FILES.iter().map(|&file| file & pawns != EMPTY).collect();
+    occupied.iter().enumerate().filter(|&(i, &o)| o && (i == 0 || !occupied[i - 1])).count() as i32
+}
+
+fn count_pawn_islands(whites: Board, blacks: Board) -> i32 {
+    count_islands(whites) - count_islands(blacks)
+}
+
+/// Passed pawns which the enemy king cannot catch by the rule of the square,
+/// only considered once the enemy has no pieces left other than pawns.
+fn unstoppable_passers(board: &Position, side: Side, passers: Board) -> Board {
+    let enemy = reflect_side(side);
+    let enemy_king = board.piece_boards[create_piece(enemy, class::K)];
+    let enemy_pieces = [class::N, class::B, class::R, class::Q]
+        .iter()
+        .any(|&class| board.piece_boards[create_piece(enemy, class)] != EMPTY);
+    if enemy_pieces || enemy_king == EMPTY {
+        return EMPTY;
+    }
+    let occupied = board.side_boards[side::W] | board.side_boards[side::B];
+    let tempo = if board.active == enemy { 1 } else { 0 };
+    iter(passers)
+        .filter(|&sq| {
+            let path = FILES[square_file(sq)] & ranks_ahead(side, sq);
+            let promotion = square_file(sq) + if side == side::W { 56 } else { 0 };
+            // Pawns on their starting rank can advance two squares at once
+            let moves = distance(sq, promotion).min(5);
+            path & occupied == EMPTY
+                && distance(first_square(enemy_king), promotion) - tempo > moves
+        })
+        .fold(EMPTY, |acc, sq| acc | lift(sq))
+}
+
+/// For each passed pawn the distance from the enemy king to the square in
+/// front of it minus the distance from the friendly king
+fn king_passer_proximity(board: &Position, side: Side, passers: Board) -> i32 {
+    let king = board.piece_boards[create_piece(side, class::K)];
+    let enemy_king = board.piece_boards[create_piece(reflect_side(side), class::K)];
+    if king == EMPTY || enemy_king == EMPTY {
+        return 0;
+    }
+    let (king, enemy_king) = (first_square(king), first_square(enemy_king));
+    iter(passers)
+        .map(|sq| {
+            let stop = stop_square(side, sq);
+            distance(enemy_king, stop) - distance(king, stop)
+        })
+        .sum()
+}
+
 fn count_connections(a: Board, b: Board) -> i32 {
     let mut count = 0;
     for sq_a in iter(a) {
@@ -370,77 +672,173 @@ mod simple_test {
     }
 }
 
-//
-//fn count_backward_pawns(whites: BitBoard, blacks: BitBoard) -> i32 {
-//    let mut count = 0i32;
-//    for file_index in 1..7 {
-//        let file = BitBoard::FILES[file_index];
-//        let adj_files = ADJACENT_FILES[file_index];
-//        if let Some(candidate) = (file & whites).first() {
-//            let rank = candidate.rank_index();
-//            let adj_rank = (adj_files & whites).first()
-//                .map(|s| s.rank_index()).unwrap_or(10);
-//            if adj_rank > rank {
-//                count += 1
-//            }
-//        }
-//        if let Some(candidate) = (file & blacks).iter().last() {
-//            let rank = candidate.rank_index();
-//            let adj_rank = (adj_files & blacks).iter().last()
-//                .map(|s| s.rank_index()).unwrap_or(0);
-//            if adj_rank < rank {
-//                count -= 1
-//            }
-//        }
-//    }
-//    count
-//}
-//
-//#[cfg(test)]
-//mod backward_test {
-//    use crate::Reflectable;
-//    use super::*;
-//    use crate::Square::*;
-//
-//    fn execute_test(whites: BitBoard, blacks: BitBoard, expected: i32) {
-//        assert_eq!(count_backward_pawns(whites, blacks), expected);
-//        assert_eq!(count_backward_pawns(blacks.reflect(), whites.reflect()), -expected);
-//    }
-//
-//    #[test]
-//    fn case_0() {
-//        execute_test(
-//            A2 | B2 | C2 | D2 | E2 | F2 | G2 | H2,
-//            A7 | B7 | C7 | D7 | E7 | F7 | G7 | H7,
-//            0
-//        );
-//    }
-//
-//    #[test]
-//    fn case_1() {
-//        execute_test(
-//            C3 | D2 | E3 | F2 | G2 | H2,
-//            A7 | B7 | C7 | D7 | E7 | F7 | G7 | H7,
-//            1
-//        );
-//    }
-//
-//    #[test]
-//    fn case_2() {
-//        execute_test(
-//            C3 | D2 | F2 | G2 | H2,
-//            A7 | B7 | C7 | D7 | E7 | F7 | G7 | H7,
-//            1
-//        );
-//    }
-//
-//    #[test]
-//    fn case_3() {
-//        execute_test(
-//            A2 | C3 | D2 | F4 | G2,
-//            C7 | D6 | E7 | F7 | G6 | H7,
-//            1
-//        );
-//    }
-//}
-//
+#[cfg(test)]
+mod structure_test {
+    use crate::constants::boards::EMPTY;
+    use crate::constants::square::*;
+    use crate::eval::pawns::{
+        backward_pawns, candidate_passers, connected_pawns, count_feature, count_pawn_islands,
+        phalanx_pawns,
+    };
+    use crate::test::reflect_board;
+    use crate::{board, Board};
+
+    fn execute_test(
+        under_test: fn(Board, Board) -> i32,
+        whites: Board,
+        blacks: Board,
+        expected_count: i32,
+    ) {
+        assert_eq!(under_test(whites, blacks), expected_count);
+        assert_eq!(under_test(reflect_board(blacks), reflect_board(whites)), -expected_count);
+    }
+
+    #[test]
+    fn backward_case_0() {
+        let under_test = |w, b| count_feature(backward_pawns, w, b);
+        execute_test(under_test, board!(A2 => H2), board!(A7 => H7), 0)
+    }
+
+    #[test]
+    fn backward_case_1() {
+        let under_test = |w, b| count_feature(backward_pawns, w, b);
+        execute_test(under_test, board!(C3, D2, E3), board!(E4, F4), 1)
+    }
+
+    #[test]
+    fn backward_case_2() {
+        let under_test = |w, b| count_feature(backward_pawns, w, b);
+        execute_test(under_test, board!(D3, E3), board!(E5), -1)
+    }
+
+    #[test]
+    fn phalanx_case_0() {
+        let under_test = |w, b| count_feature(phalanx_pawns, w, b);
+        execute_test(under_test, board!(A2, B2, D4, E4, F4, H3), board!(C7, D7), 3)
+    }
+
+    #[test]
+    fn phalanx_case_1() {
+        let under_test = |w, b| count_feature(phalanx_pawns, w, b);
+        execute_test(under_test, board!(A2, H3, C4, D5), EMPTY, 0)
+    }
+
+    #[test]
+    fn connected_case_0() {
+        let under_test = |w, b| count_feature(connected_pawns, w, b);
+        execute_test(under_test, board!(B2, C3, D4, F2, G3), EMPTY, 3)
+    }
+
+    #[test]
+    fn connected_case_1() {
+        let under_test = |w, b| count_feature(connected_pawns, w, b);
+        execute_test(under_test, board!(H2, A3), EMPTY, 0)
+    }
+
+    #[test]
+    fn connected_case_2() {
+        let under_test = |w, b| count_feature(connected_pawns, w, b);
+        execute_test(under_test, board!(B2, C3), board!(F7, G6, H5), -1)
+    }
+
+    #[test]
+    fn candidate_case_0() {
+        let under_test = |w, b| count_feature(candidate_passers, w, b);
+        execute_test(under_test, board!(C2, D4), board!(E6), 1)
+    }
+
+    #[test]
+    fn candidate_case_1() {
+        let under_test = |w, b| count_feature(candidate_passers, w, b);
+        execute_test(under_test, board!(D4), board!(C6, E6), 0)
+    }
+
+    #[test]
+    fn candidate_case_2() {
+        let under_test = |w, b| count_feature(candidate_passers, w, b);
+        execute_test(under_test, board!(C3, D4, E3), board!(C6, E6), 1)
+    }
+
+    #[test]
+    fn islands_case_0() {
+        execute_test(count_pawn_islands, EMPTY, EMPTY, 0)
+    }
+
+    #[test]
+    fn islands_case_1() {
+        execute_test(count_pawn_islands, board!(A2, B2, D2, F2, G2, H2), board!(A7 => H7), 2)
+    }
+
+    #[test]
+    fn islands_case_2() {
+        execute_test(count_pawn_islands, board!(A2, C3, E4), board!(H7, G6, A7), 1)
+    }
+}
+
+#[cfg(test)]
+mod king_test {
+    use crate::constants::{class, create_piece, side};
+    use crate::eval::pawns::{find_passed_pawns, king_passer_proximity, unstoppable_passers};
+    use crate::position::Position;
+    use crate::{Board, Side, Symmetric};
+
+    fn count(under_test: fn(&Position, Side, Board) -> i32, board: &Position) -> i32 {
+        let whites = board.piece_boards[create_piece(side::W, class::P)];
+        let blacks = board.piece_boards[create_piece(side::B, class::P)];
+        let (w_passers, b_passers) = find_passed_pawns(whites, blacks);
+        under_test(board, side::W, w_passers) - under_test(board, side::B, b_passers)
+    }
+
+    fn execute_test(under_test: fn(&Position, Side, Board) -> i32, fen: &str, expected: i32) {
+        let board = fen.parse::<Position>().unwrap();
+        assert_eq!(expected, count(under_test, &board));
+        assert_eq!(-expected, count(under_test, &board.reflect()));
+    }
+
+    fn unstoppable(board: &Position, side: Side, passers: Board) -> i32 {
+        unstoppable_passers(board, side, passers).count_ones() as i32
+    }
+
+    #[test]
+    fn unstoppable_case_0() {
+        execute_test(unstoppable, "8/8/8/P7/8/8/7k/K7 w - - 0 1", 1);
+        execute_test(unstoppable, "8/8/8/P7/8/8/7k/K7 b - - 0 1", 1);
+    }
+
+    #[test]
+    fn unstoppable_case_1() {
+        execute_test(unstoppable, "8/8/3k4/P7/8/8/8/K7 w - - 0 1", 0)
+    }
+
+    #[test]
+    fn unstoppable_tempo() {
+        execute_test(unstoppable, "8/8/4k3/P7/8/8/8/K7 w - - 0 1", 1);
+        execute_test(unstoppable, "8/8/4k3/P7/8/8/8/K7 b - - 0 1", 0);
+    }
+
+    #[test]
+    fn unstoppable_enemy_pieces() {
+        execute_test(unstoppable, "8/8/4k3/P7/8/8/n7/K7 w - - 0 1", 0)
+    }
+
+    #[test]
+    fn unstoppable_blocked() {
+        execute_test(unstoppable, "8/K7/4k3/P7/8/8/8/8 w - - 0 1", 0)
+    }
+
+    #[test]
+    fn unstoppable_double_step() {
+        execute_test(unstoppable, "8/8/8/8/5k2/8/P7/K7 w - - 0 1", 0);
+        execute_test(unstoppable, "8/8/8/8/6k1/8/P7/K7 w - - 0 1", 1);
+    }
+
+    #[test]
+    fn proximity_case_0() {
+        execute_test(king_passer_proximity, "7k/8/8/3P4/3K4/8/8/8 w - - 0 1", 2)
+    }
+
+    #[test]
+    fn proximity_case_1() {
+        execute_test(king_passer_proximity, "7k/8/8/3P4/3K3p/8/8/8 w - - 0 1", 3)
+    }
+}
@@ -4,6 +4,7 @@ use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
 use crate::position::Position;
 use crate::{union_boards, Side};
+use serde_derive::{Deserialize, Serialize};
 use std::cmp::min;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -12,6 +13,28 @@ struct SafetyCounts {
     attacker_count: usize,
 }
 
+/// Each square next to the king controlled by an enemy piece is penalised,
+/// multiplied according to the number of attacking pieces. The endgame
+/// penalty is a fraction of the midgame one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafetyParams {
+    pub control_bonus: usize,
+    pub piece_count_multipliers: [f64; 3],
+    pub endgame_multiplier: f64,
+}
+
+impl Default for SafetyParams {
+    fn default() -> Self {
+        SafetyParams {
+            control_bonus: 10,
+            endgame_multiplier: 0.1,
+            piece_count_multipliers: [1.0, 1.5, 3.0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct SafetyFacet {
     control_bonus: usize,
     piece_count_multipliers: [f64; 3],
@@ -20,10 +43,16 @@ pub struct SafetyFacet {
 
 impl Default for SafetyFacet {
     fn default() -> Self {
+        SafetyFacet::from(&SafetyParams::default())
+    }
+}
+
+impl From<&SafetyParams> for SafetyFacet {
+    fn from(params: &SafetyParams) -> Self {
         SafetyFacet {
-            control_bonus: 10,
-            endgame_multiplier: 0.1,
-            piece_count_multipliers: [1.0, 1.5, 3.0],
+            control_bonus: params.control_bonus,
+            endgame_multiplier: params.endgame_multiplier,
+            piece_count_multipliers: params.piece_count_multipliers,
         }
     }
 }
@@ -77,6 +106,15 @@ impl EvalFacet for SafetyFacet {
         }
     }
 
+    fn name(&self) -> &'static str {
+        "Safety"
+    }
+
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+        let mid = -self.compute_king_danger(board, side);
+        Evaluation::Phased { mid, end: (mid as f64 * self.endgame_multiplier).round() as i32 }
+    }
+
     fn make(&mut self, _: &Move, _: &Position) {}
 
     fn unmake(&mut self, _: &Move) {}
@@ -1,12 +1,13 @@
 use crate::constants::{
     class, corner_side, create_piece, piece_class, piece_side, reflect_piece, reflect_square,
-    square_file, square_rank,
+    side_parity, square_file, square_rank,
 };
-use crate::{ClassMap, Piece, SideMap, Square, SquareMap, Symmetric};
+use crate::{ClassMap, Piece, Side, SideMap, Square, SquareMap, Symmetric};
 
 use crate::moves::Move;
 use crate::node::{EvalFacet, Evaluation};
-use crate::position::{Position, CASTLING_DETAILS};
+use crate::position::{castle_targets, Position};
+use serde_derive::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct PieceSquareTablesFacet {
@@ -23,16 +24,24 @@ impl Default for PieceSquareTablesFacet {
 
 impl<'a> From<&'a Position> for PieceSquareTablesFacet {
     fn from(value: &Position) -> Self {
-        let mut facet = PieceSquareTablesFacet::default();
-        facet.mid_eval = facet.compute_midgame_eval(value);
-        facet.end_eval = facet.compute_endgame_eval(value);
-        facet
+        PieceSquareTablesFacet::new(&TableParams::default(), value)
     }
 }
 
 type UpdateFn = fn(&mut PieceSquareTablesFacet, Piece, Square) -> ();
 
 impl PieceSquareTablesFacet {
+    pub fn new(params: &TableParams, position: &Position) -> PieceSquareTablesFacet {
+        let mut facet = PieceSquareTablesFacet {
+            tables: PositionTables::from(params),
+            mid_eval: 0,
+            end_eval: 0,
+        };
+        facet.mid_eval = facet.compute_midgame_eval(position);
+        facet.end_eval = facet.compute_endgame_eval(position);
+        facet
+    }
+
     pub fn compute_midgame_eval(&self, board: &Position) -> i32 {
         (0..64)
             .flat_map(|square| board.piece_locs[square].map(|p| (p, square)))
@@ -60,15 +69,15 @@ impl PieceSquareTablesFacet {
     fn make_impl(&mut self, mv: &Move, add: UpdateFn, remove: UpdateFn) {
         match mv {
             Move::Null => {}
-            &Move::Castle { corner } => {
-                let details = &CASTLING_DETAILS[corner];
+            &Move::Castle { corner, king: k_source, rook: r_source } => {
+                let (k_target, r_target) = castle_targets(corner);
                 let side = corner_side(corner);
                 let rook = create_piece(side, class::R);
                 let king = create_piece(side, class::K);
-                remove(self, rook, details.rook_line.0);
-                add(self, rook, details.rook_line.1);
-                remove(self, king, details.king_line.0);
-                add(self, king, details.king_line.1);
+                remove(self, rook, r_source);
+                add(self, rook, r_target);
+                remove(self, king, k_source);
+                add(self, king, k_target);
             }
             &Move::Normal { moving, from, dest, capture } => {
                 remove(self, moving, from);
@@ -99,6 +108,22 @@ impl EvalFacet for PieceSquareTablesFacet {
         Evaluation::Phased { mid: self.mid_eval, end: self.end_eval }
     }
 
+    fn name(&self) -> &'static str {
+        "PieceSquareTables"
+    }
+
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+        let parity = side_parity(side);
+        let pieces = (0..64)
+            .flat_map(|square| board.piece_locs[square].map(|p| (p, square)))
+            .filter(|&(p, _)| piece_side(p) == side)
+            .collect::<Vec<_>>();
+        Evaluation::Phased {
+            mid: parity * pieces.iter().map(|&(p, sq)| self.tables.midgame(p, sq)).sum::<i32>(),
+            end: parity * pieces.iter().map(|&(p, sq)| self.tables.endgame(p, sq)).sum::<i32>(),
+        }
+    }
+
     fn make(&mut self, mv: &Move, _: &Position) {
         self.make_impl(mv, PieceSquareTablesFacet::add, PieceSquareTablesFacet::remove);
     }
@@ -128,29 +153,56 @@ impl PositionTables {
 
 impl Default for PositionTables {
     fn default() -> Self {
-        PositionTables {
-            tables: [
-                [
-                    parse_full(PAWN),
-                    parse_symmetric(KNIGHT),
-                    parse_symmetric(BISHOP),
-                    parse_symmetric(ROOK),
-                    parse_symmetric(QUEEN),
-                    parse_symmetric(KING),
-                ],
-                [
-                    parse_full(PAWN).reflect(),
-                    parse_symmetric(KNIGHT).reflect(),
-                    parse_symmetric(BISHOP).reflect(),
-                    parse_symmetric(ROOK).reflect(),
-                    parse_symmetric(QUEEN).reflect(),
-                    parse_symmetric(KING).reflect(),
-                ],
-            ],
+        PositionTables::from(&TableParams::default())
+    }
+}
+
+impl From<&TableParams> for PositionTables {
+    fn from(params: &TableParams) -> Self {
+        let white = [
+            parse_full(&params.pawn),
+            parse_symmetric(&params.knight),
+            parse_symmetric(&params.bishop),
+            parse_symmetric(&params.rook),
+            parse_symmetric(&params.queen),
+            parse_symmetric(&params.king),
+        ];
+        let black = std::array::from_fn(|class| white[class].reflect());
+        PositionTables { tables: [white, black] }
+    }
+}
+
+/// The (mid, end) values of each piece class for the white side, listed by
+/// rank starting from the first. The tables of every class except the pawn
+/// are symmetric and so only give the files H - E.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TableParams {
+    pub pawn: [[(i32, i32); 8]; 8],
+    pub knight: [[(i32, i32); 4]; 8],
+    pub bishop: [[(i32, i32); 4]; 8],
+    pub rook: [[(i32, i32); 4]; 8],
+    pub queen: [[(i32, i32); 4]; 8],
+    pub king: [[(i32, i32); 4]; 8],
+}
+
+impl Default for TableParams {
+    fn default() -> Self {
+        TableParams {
+            pawn: by_rank(PAWN),
+            knight: by_rank(KNIGHT),
+            bishop: by_rank(BISHOP),
+            rook: by_rank(ROOK),
+            queen: by_rank(QUEEN),
+            king: by_rank(KING),
         }
     }
 }
 
+fn by_rank<const N: usize, const M: usize>(raw: [(i32, i32); M]) -> [[(i32, i32); N]; 8] {
+    std::array::from_fn(|rank| std::array::from_fn(|file| raw[N * rank + file]))
+}
+
 #[derive(Debug, Clone, PartialOrd, PartialEq, Eq)]
 struct SquareTable(SquareMap<(i32, i32)>);
 
@@ -166,16 +218,16 @@ impl Symmetric for SquareTable {
 type SymmetricTable = [(i32, i32); 32];
 type CompleteTable = [(i32, i32); 64];
 
-fn parse_symmetric(raw: SymmetricTable) -> SquareTable {
+fn parse_symmetric(raw: &[[(i32, i32); 4]; 8]) -> SquareTable {
     SquareTable(std::array::from_fn(|sq| {
         let (rank, file) = (square_rank(sq), square_file(sq));
         let column = if file < 4 { file } else { 7 - file };
-        raw[4 * rank + column]
+        raw[rank][column]
     }))
 }
 
-fn parse_full(raw: CompleteTable) -> SquareTable {
-    SquareTable(std::array::from_fn(|sq| raw[8 * square_rank(sq) + square_file(sq)]))
+fn parse_full(raw: &[[(i32, i32); 8]; 8]) -> SquareTable {
+    SquareTable(std::array::from_fn(|sq| raw[square_rank(sq)][square_file(sq)]))
 }
 
 /// Tables lifted from stockfish here: https://github.com/official-stockfish/Stockfish/blob/master/src/psqt.cpp
@@ -1,8 +1,12 @@
-use crate::constants::{piece_class, side};
-use crate::moves::Move;
-use crate::parse::StringIndexMap;
+use crate::constants::{
+    class, corner, corner_side, create_piece, piece_class, side, square_file, square_rank,
+};
+use crate::moves::{Move, Moves, Notation};
+use crate::parse::{StringIndexMap, FILE_CHARS, RANK_CHARS};
 use crate::position::{Position, CASTLING_DETAILS};
+use crate::{Class, Corner, Square};
 
+use anyhow::{anyhow, Result};
 use lazy_static::lazy_static;
 use std::fmt::{Display, Formatter};
 
@@ -31,32 +35,192 @@ impl Display for Position {
     }
 }
 
+/// Castling moves are written as the king moving to its target square when
+/// the king and rook start on their standard squares and as the king taking
+/// its own rook otherwise.
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_uci(false))
+    }
+}
+
+impl Move {
+    /// Format this move in UCI notation, in Chess960 mode castling moves are
+    /// always written as the king taking its own rook.
+    pub fn to_uci(&self, chess960: bool) -> String {
         lazy_static! {
             static ref SQUARES: StringIndexMap = StringIndexMap::squares();
             static ref PIECES: StringIndexMap = StringIndexMap::uci_pieces();
         }
-        write!(
-            f,
-            "{}",
-            match self {
-                Move::Null => "null".to_owned(),
-                &Move::Normal { from, dest, .. } =>
-                    format!("{}{}", SQUARES.format(from), SQUARES.format(dest)),
-                &Move::Enpassant { from, dest, .. } =>
-                    format!("{}{}", SQUARES.format(from), SQUARES.format(dest)),
-                &Move::Castle { corner, .. } => {
-                    let details = &CASTLING_DETAILS[corner];
-                    let (from, dest) = details.king_line;
-                    format!("{}{}", SQUARES.format(from), SQUARES.format(dest))
+        match self {
+            Move::Null => "null".to_owned(),
+            &Move::Normal { from, dest, .. } => {
+                format!("{}{}", SQUARES.format(from), SQUARES.format(dest))
+            }
+            &Move::Enpassant { from, dest, .. } => {
+                format!("{}{}", SQUARES.format(from), SQUARES.format(dest))
+            }
+            &Move::Castle { corner, king, rook } => {
+                let standard = &CASTLING_DETAILS[corner];
+                let is_standard = (king, rook) == (standard.king_line.0, standard.rook_line.0);
+                let dest = if is_standard && !chess960 { standard.king_line.1 } else { rook };
+                format!("{}{}", SQUARES.format(king), SQUARES.format(dest))
+            }
+            &Move::Promote { from, dest, promoted, .. } => {
+                let promote_class = PIECES.format(piece_class(promoted));
+                format!("{}{}{}", SQUARES.format(from), SQUARES.format(dest), promote_class)
+            }
+        }
+    }
+}
+
+impl Position {
+    /// Format a move which is legal in this position in the given notation.
+    pub fn format_move(&self, m: &Move, notation: Notation) -> Result<String> {
+        let legal = self.moves(&Moves::All);
+        if !legal.contains(m) {
+            return Err(anyhow!("{} is not legal in {}", m, self));
+        }
+        Ok(match notation {
+            Notation::Uci => m.to_uci(false),
+            Notation::San => format!("{}{}", self.san_body(m, &legal), self.check_suffix(m)?),
+            Notation::Lan => format!("{}{}", lan_body(m), self.check_suffix(m)?),
+        })
+    }
+
+    pub fn to_san(&self, m: &Move) -> Result<String> {
+        self.format_move(m, Notation::San)
+    }
+
+    /// Format a sequence of moves played consecutively from this position.
+    pub fn format_line(&self, moves: &[Move], notation: Notation) -> Result<Vec<String>> {
+        let mut position = self.clone();
+        let mut result = Vec::with_capacity(moves.len());
+        for m in moves {
+            result.push(position.format_move(m, notation)?);
+            position.make(m.clone())?;
+        }
+        Ok(result)
+    }
+
+    /// Rewrite a legal move given in one notation into another.
+    pub fn convert_move(&self, input: &str, from: Notation, to: Notation) -> Result<String> {
+        self.format_move(&self.parse_move(input, from)?, to)
+    }
+
+    fn san_body(&self, m: &Move, legal: &[Move]) -> String {
+        match m {
+            Move::Null => "--".to_owned(),
+            &Move::Castle { corner, .. } => castle_str(corner).to_owned(),
+            &Move::Enpassant { from, dest, .. } => {
+                format!("{}x{}", FILE_CHARS[square_file(from)], square_str(dest))
+            }
+            &Move::Promote { from, dest, promoted, capture } => {
+                let promoted = piece_char(piece_class(promoted));
+                match capture {
+                    None => format!("{}={}", square_str(dest), promoted),
+                    Some(_) => {
+                        format!(
+                            "{}x{}={}",
+                            FILE_CHARS[square_file(from)],
+                            square_str(dest),
+                            promoted
+                        )
+                    }
                 }
-                &Move::Promote { from, dest, promoted, .. } => {
-                    let promote_class = PIECES.format(piece_class(promoted));
-                    format!("{}{}{}", SQUARES.format(from), SQUARES.format(dest), promote_class)
+            }
+            &Move::Normal { moving, from, dest, capture } => {
+                let capture = if capture.is_some() { "x" } else { "" };
+                if piece_class(moving) == class::P {
+                    let file = if capture.is_empty() {
+                        "".to_owned()
+                    } else {
+                        FILE_CHARS[square_file(from)].to_string()
+                    };
+                    format!("{}{}{}", file, capture, square_str(dest))
+                } else {
+                    let others: Vec<Square> = legal
+                        .iter()
+                        .filter_map(|other| match other {
+                            &Move::Normal { moving: m, from: f, dest: d, .. } => {
+                                Some(f).filter(|&f| m == moving && d == dest && f != from)
+                            }
+                            _ => None,
+                        })
+                        .collect();
+                    let same_file = others.iter().any(|&sq| square_file(sq) == square_file(from));
+                    let same_rank = others.iter().any(|&sq| square_rank(sq) == square_rank(from));
+                    let disambiguation = if others.is_empty() {
+                        "".to_owned()
+                    } else if !same_file {
+                        FILE_CHARS[square_file(from)].to_string()
+                    } else if !same_rank {
+                        RANK_CHARS[square_rank(from)].to_string()
+                    } else {
+                        square_str(from)
+                    };
+                    let piece = piece_char(piece_class(moving));
+                    format!("{}{}{}{}", piece, disambiguation, capture, square_str(dest))
                 }
             }
-        )
+        }
+    }
+
+    fn check_suffix(&self, m: &Move) -> Result<&str> {
+        let mut next = self.clone();
+        next.make(m.clone())?;
+        Ok(if !next.in_check() {
+            ""
+        } else if next.moves(&Moves::All).is_empty() {
+            "#"
+        } else {
+            "+"
+        })
+    }
+}
+
+fn lan_body(m: &Move) -> String {
+    let (piece, from, dest, capture, promoted) = match m {
+        Move::Null => return "--".to_owned(),
+        &Move::Castle { corner, .. } => return castle_str(corner).to_owned(),
+        &Move::Enpassant { from, dest, .. } => (class::P, from, dest, true, None),
+        &Move::Normal { moving, from, dest, capture } => {
+            (piece_class(moving), from, dest, capture.is_some(), None)
+        }
+        &Move::Promote { from, dest, promoted, capture } => {
+            (class::P, from, dest, capture.is_some(), Some(piece_class(promoted)))
+        }
+    };
+    format!(
+        "{}{}{}{}{}",
+        if piece == class::P { "".to_owned() } else { piece_char(piece).to_string() },
+        square_str(from),
+        if capture { "x" } else { "-" },
+        square_str(dest),
+        promoted.map(|p| format!("={}", piece_char(p))).unwrap_or_default(),
+    )
+}
+
+fn castle_str(corner: Corner) -> &'static str {
+    if matches!(corner, corner::WK | corner::BK) {
+        "O-O"
+    } else {
+        "O-O-O"
+    }
+}
+
+fn square_str(square: Square) -> String {
+    format!("{}{}", FILE_CHARS[square_file(square)], RANK_CHARS[square_rank(square)])
+}
+
+fn piece_char(class: Class) -> char {
+    match class {
+        class::N => 'N',
+        class::B => 'B',
+        class::R => 'R',
+        class::Q => 'Q',
+        class::K => 'K',
+        _ => 'P',
     }
 }
 
@@ -112,9 +276,28 @@ fn to_fen_side(board: &Position) -> String {
     if board.active == side::W { "w" } else { "b" }.to_string()
 }
 
+/// Uses X-FEN, the rook file is only written out when the castling rook is
+/// not the outermost rook on that side of the king.
 fn to_fen_castling_rights(board: &Position) -> String {
-    let rights =
-        (0..4).filter(|c| board.castling_rights[*c]).map(|c| CORNERS[c]).collect::<String>();
+    let rights = (0..4)
+        .filter(|c| board.castling_rights[*c])
+        .map(|c| {
+            let (king, rook) =
+                (board.castling_details[c].king_line.0, board.castling_details[c].rook_line.0);
+            let rook_piece = create_piece(corner_side(c), class::R);
+            let outer = if rook < king {
+                square_rank(rook) * 8..rook
+            } else {
+                rook + 1..square_rank(rook) * 8 + 8
+            };
+            if outer.into_iter().any(|sq| board.piece_locs[sq] == Some(rook_piece)) {
+                let file = FILE_CHARS[square_file(rook)];
+                if corner_side(c) == side::W { file.to_ascii_uppercase() } else { file }.to_string()
+            } else {
+                CORNERS[c].to_string()
+            }
+        })
+        .collect::<String>();
     if rights.is_empty() {
         format!("-")
     } else {
@@ -142,7 +325,10 @@ const PIECES: [&'static str; 12] = ["P", "N", "B", "R", "Q", "K", "p", "n", "b",
 #[cfg(test)]
 mod test {
     use super::to_fen_impl;
+    use crate::constants::corner;
+    use crate::constants::square::*;
     use crate::format::FenPart;
+    use crate::moves::Move;
     use crate::position::Position;
     use std::iter::once;
 
@@ -283,4 +469,113 @@ mod test {
         let expected = "rnbq1br1/pppkppp1/5n1p/3pP3/8/5N2/PPPPKPPP/RNBQ1B1R b - - 3 6";
         assert_eq!(expected, position_2().to_string());
     }
+
+    #[test]
+    fn chess960_castling_rights() {
+        let position = "rk2r3/8/8/8/8/8/8/RR2K2R w HBa - 0 1".parse::<Position>().unwrap();
+        assert_eq!("KBq", to_fen_impl(&position, once(FenPart::CastlingRights)));
+        assert_eq!(position, position.to_string().parse::<Position>().unwrap());
+    }
+
+    #[test]
+    fn chess960_castle_uci() {
+        let standard = Move::Castle { corner: corner::WK, king: E1, rook: H1 };
+        assert_eq!("e1g1", standard.to_string());
+        assert_eq!("e1h1", standard.to_uci(true));
+        let chess960 = Move::Castle { corner: corner::BQ, king: B8, rook: A8 };
+        assert_eq!("b8a8", chess960.to_string());
+        assert_eq!("b8a8", chess960.to_uci(true));
+    }
+}
+
+#[cfg(test)]
+mod test_san {
+    use crate::moves::{Move, Notation};
+    use crate::position::Position;
+    use anyhow::Result;
+
+    fn execute_test(fen: &str, uci: &str, san: &str, lan: &str) -> Result<()> {
+        let position = fen.parse::<Position>()?;
+        let m = position.parse_move(uci, Notation::Uci)?;
+        assert_eq!(san, position.format_move(&m, Notation::San)?);
+        assert_eq!(lan, position.format_move(&m, Notation::Lan)?);
+        assert_eq!(m, position.parse_move(san, Notation::San)?);
+        assert_eq!(m, position.parse_move(lan, Notation::Lan)?);
+        assert_eq!(uci, position.convert_move(san, Notation::San, Notation::Uci)?);
+        assert_eq!(san, position.convert_move(lan, Notation::Lan, Notation::San)?);
+        assert_eq!(lan, position.convert_move(uci, Notation::Uci, Notation::Lan)?);
+        Ok(())
+    }
+
+    #[test]
+    fn knight_move() -> Result<()> {
+        execute_test("startpos", "g1f3", "Nf3", "Ng1-f3")
+    }
+
+    #[test]
+    fn pawn_push() -> Result<()> {
+        execute_test("startpos", "e2e4", "e4", "e2-e4")
+    }
+
+    #[test]
+    fn disambiguate_by_file() -> Result<()> {
+        execute_test("4k3/8/8/8/8/8/8/1N3N1K w - - 0 1", "b1d2", "Nbd2", "Nb1-d2")
+    }
+
+    #[test]
+    fn disambiguate_by_rank() -> Result<()> {
+        execute_test("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3", "Ra1-a3")
+    }
+
+    #[test]
+    fn disambiguate_by_square() -> Result<()> {
+        execute_test("4k3/8/8/8/8/Q7/8/Q1Q1K3 w - - 0 1", "a1b2", "Qa1b2", "Qa1-b2")
+    }
+
+    #[test]
+    fn no_disambiguation_for_pinned_piece() -> Result<()> {
+        execute_test("4k3/8/8/8/8/8/8/1N2KN1r w - - 0 1", "b1d2", "Nd2", "Nb1-d2")
+    }
+
+    #[test]
+    fn capture_promotion_with_check() -> Result<()> {
+        execute_test("3r3k/4P3/8/8/8/8/8/K7 w - - 0 1", "e7d8q", "exd8=Q+", "e7xd8=Q+")
+    }
+
+    #[test]
+    fn enpassant() -> Result<()> {
+        execute_test("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6", "e5xd6")
+    }
+
+    #[test]
+    fn castling() -> Result<()> {
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1";
+        execute_test(fen, "e8g8", "O-O", "O-O")?;
+        execute_test(fen, "e8c8", "O-O-O", "O-O-O")
+    }
+
+    #[test]
+    fn checkmate() -> Result<()> {
+        let fen = "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2";
+        execute_test(fen, "d8h4", "Qh4#", "Qd8-h4#")
+    }
+
+    #[test]
+    fn format_line() -> Result<()> {
+        let mut position = Position::default();
+        let moves = position.clone().play("e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 e1g1")?;
+        let expected = vec!["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "O-O"];
+        assert_eq!(expected, position.format_line(&moves, Notation::San)?);
+        position.play("e2e4")?;
+        assert!(position.format_line(&moves, Notation::San).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn illegal_move() {
+        let position = Position::default();
+        let m =
+            Move::Normal { moving: crate::constants::piece::WN, from: 1, dest: 11, capture: None };
+        assert!(position.to_san(&m).is_err());
+    }
 }
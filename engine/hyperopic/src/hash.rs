@@ -44,12 +44,17 @@ struct Features {
 }
 
 // https://github.com/official-stockfish/Stockfish/blob/master/src/misc.h#L122
-struct PRNG {
+pub(crate) struct PRNG {
     s: u64,
 }
 
 impl PRNG {
-    fn rand64(&mut self) -> u64 {
+    /// The seed must be non-zero
+    pub(crate) fn new(seed: u64) -> PRNG {
+        PRNG { s: seed }
+    }
+
+    pub(crate) fn rand64(&mut self) -> u64 {
         self.s ^= self.s.wrapping_shr(12);
         self.s ^= self.s.wrapping_shl(25);
         self.s ^= self.s.wrapping_shr(27);
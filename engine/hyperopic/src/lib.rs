@@ -1,23 +1,37 @@
 use crate::moves::Move;
-use crate::node::TreeNode;
+use crate::node::TreeNodeBuilder;
 use crate::position::Position;
-use crate::search::{SearchOutcome, SearchParameters, TranspositionsImpl};
+use crate::search::end::SearchEnd;
+use crate::search::observer::SearchObserver;
+use crate::search::{
+    ConcurrentTranspositions, MultiPv, ParallelSearchParameters, SearchControl, SearchHandle,
+    SearchOutcome,
+};
+use crate::syzygy::Tablebase;
 use crate::timing::TimeAllocator;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 pub use board::union_boards;
+pub use eval::EvalParams;
+use std::cmp::max;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 mod board;
+pub mod epd;
 mod eval;
 mod format;
 mod hash;
 pub mod moves;
 pub mod node;
 mod parse;
+pub mod perft;
+pub mod pgn;
 mod phase;
+pub mod polyglot;
 pub mod position;
 pub mod search;
 mod see;
+pub mod syzygy;
 #[cfg(test)]
 mod test;
 mod timing;
@@ -96,6 +110,8 @@ pub trait LookupMoveService: Send + Sync {
     fn lookup(&mut self, position: Position) -> Result<Option<Move>>;
 }
 
+type Lookups = Arc<Mutex<Vec<Box<dyn LookupMoveService>>>>;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ComputeMoveInput {
     pub position: Position,
@@ -110,54 +126,159 @@ pub struct ComputeMoveOutput {
 }
 
 pub struct Engine {
-    transpositions: TranspositionsImpl,
-    lookups: Vec<Box<dyn LookupMoveService>>,
+    transpositions: Arc<ConcurrentTranspositions>,
+    lookups: Lookups,
     timing: TimeAllocator,
+    threads: usize,
+    observer: Arc<Mutex<Box<dyn SearchObserver + Send>>>,
+    multi_pv: MultiPv,
+    tablebase: Option<Arc<dyn Tablebase>>,
+    node_builder: TreeNodeBuilder,
 }
 
 impl Engine {
     pub fn new(table_size: usize, lookups: Vec<Box<dyn LookupMoveService>>) -> Engine {
         Engine {
-            transpositions: TranspositionsImpl::new(table_size),
-            lookups,
+            transpositions: Arc::new(ConcurrentTranspositions::new(table_size)),
+            lookups: Arc::new(Mutex::new(lookups)),
             timing: TimeAllocator::default(),
+            threads: 1,
+            observer: Arc::new(Mutex::new(Box::new(()))),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
+            node_builder: TreeNodeBuilder::default(),
         }
     }
 
+    /// Set the number of threads used by the search, any more than one will
+    /// run a Lazy SMP search sharing the transposition table.
+    pub fn with_threads(mut self, threads: usize) -> Engine {
+        self.threads = max(1, threads);
+        self
+    }
+
+    /// Set an observer which will be notified of the progress of every search
+    pub fn with_observer(mut self, observer: Box<dyn SearchObserver + Send>) -> Engine {
+        self.observer = Arc::new(Mutex::new(observer));
+        self
+    }
+
+    /// Set the number of lines the search computes, the best move is always
+    /// taken from the first but the search details will contain all of them.
+    pub fn with_multi_pv(mut self, multi_pv: MultiPv) -> Engine {
+        self.multi_pv = multi_pv;
+        self
+    }
+
+    /// Set an endgame tablebase which the search will probe once few enough
+    /// pieces remain. To also choose root moves from the tablebase pass a
+    /// [syzygy::TablebaseLookup] as one of the lookups.
+    pub fn with_tablebase(mut self, tablebase: Arc<dyn Tablebase>) -> Engine {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Set the weights used to evaluate positions in the search
+    pub fn with_eval_params(mut self, params: EvalParams) -> Engine {
+        self.node_builder = self.node_builder.with_params(params);
+        self
+    }
+
+    /// Set the builder used to create the root node of every search, which
+    /// determines the facets of the evaluation and their weights.
+    pub fn with_node_builder(mut self, builder: TreeNodeBuilder) -> Engine {
+        self.node_builder = builder;
+        self
+    }
+
     pub fn compute_move(&mut self, input: ComputeMoveInput) -> Result<ComputeMoveOutput> {
         let start = Instant::now();
-        let node: TreeNode = input.position.into();
-        match self.perform_lookups(node.position().clone()) {
-            Some(mv) => Ok(ComputeMoveOutput { best_move: mv, search_details: None }),
-            None => {
-                let position_count = node.position().history.len();
-                search::search(
-                    node,
-                    SearchParameters {
-                        table: &mut self.transpositions,
-                        end: self.timing.allocate(
-                            position_count,
-                            input.remaining - start.elapsed(),
-                            input.increment,
-                        ),
-                    },
-                )
-                .map(|outcome| ComputeMoveOutput {
-                    best_move: outcome.best_move.clone(),
-                    search_details: Some(outcome),
-                })
-            }
+        if let Some(mv) = perform_lookups(&self.lookups, &input.position) {
+            return Ok(ComputeMoveOutput { best_move: mv, search_details: None });
         }
+        // Time spent in the lookups has already come off the clock
+        let remaining = input.remaining.saturating_sub(start.elapsed());
+        let position_count = input.position.history.len();
+        let deadline = self.timing.allocate(position_count, remaining, input.increment);
+        self.spawn(input.position, Some(deadline), (), None).join()
+    }
+
+    /// Compute a move where the search is bounded by the given end condition rather
+    /// than by time allocated from a game clock, e.g. a fixed depth or move time.
+    pub fn compute_move_until<E: SearchEnd + Send + Sync + 'static>(
+        &mut self,
+        position: Position,
+        end: E,
+    ) -> Result<ComputeMoveOutput> {
+        self.spawn_compute_move(position, None, end).join()
+    }
+
+    /// The time we would spend searching for a move given the state of the clock
+    pub fn allocate_time(&self, input: &ComputeMoveInput) -> Duration {
+        let position_count = input.position.history.len();
+        self.timing.allocate(position_count, input.remaining, input.increment)
     }
 
-    fn perform_lookups(&mut self, position: Position) -> Option<Move> {
-        for service in self.lookups.iter_mut() {
-            if let Ok(Some(m)) = service.lookup(position.clone()) {
-                return Some(m);
+    /// Compute a move on a background thread, the lookups are tried first and
+    /// then a search is run until the deadline passes, the end condition is
+    /// met or the returned handle is used to stop it.
+    pub fn spawn_compute_move<E: SearchEnd + Send + Sync + 'static>(
+        &self,
+        position: Position,
+        deadline: Option<Duration>,
+        end: E,
+    ) -> SearchHandle<ComputeMoveOutput> {
+        self.spawn(position, deadline, end, Some(self.lookups.clone()))
+    }
+
+    /// As [Engine::spawn_compute_move] but the lookups are only tried if given
+    fn spawn<E: SearchEnd + Send + Sync + 'static>(
+        &self,
+        position: Position,
+        deadline: Option<Duration>,
+        end: E,
+        lookups: Option<Lookups>,
+    ) -> SearchHandle<ComputeMoveOutput> {
+        let control = Arc::new(SearchControl::new(deadline));
+        let (cloned_control, helpers, multi_pv) =
+            (control.clone(), self.threads - 1, self.multi_pv);
+        let (table, observer) = (self.transpositions.clone(), self.observer.clone());
+        let (tablebase, node_builder) = (self.tablebase.clone(), self.node_builder.clone());
+        SearchHandle::spawn(control, move || {
+            if let Some(mv) = lookups.and_then(|lookups| perform_lookups(&lookups, &position)) {
+                return Ok(ComputeMoveOutput { best_move: mv, search_details: None });
             }
+            let mut observer = observer.lock().map_err(|_| anyhow!("Observer poisoned"))?;
+            search::search_parallel(
+                node_builder.build(position),
+                ParallelSearchParameters {
+                    table: table.as_ref(),
+                    end: (end, cloned_control),
+                    helpers,
+                    observer: observer.as_mut(),
+                    multi_pv,
+                    tablebase: tablebase.as_deref(),
+                },
+            )
+            .map(|outcome| ComputeMoveOutput {
+                best_move: outcome.best_move.clone(),
+                search_details: Some(outcome),
+            })
+        })
+    }
+}
+
+fn perform_lookups(
+    lookups: &Mutex<Vec<Box<dyn LookupMoveService>>>,
+    position: &Position,
+) -> Option<Move> {
+    let mut lookups = lookups.lock().ok()?;
+    for service in lookups.iter_mut() {
+        if let Ok(Some(m)) = service.lookup(position.clone()) {
+            return Some(m);
         }
-        None
     }
+    None
 }
 
 #[cfg(test)]
@@ -7,7 +7,7 @@ pub enum Move {
     Normal { moving: Piece, from: Square, dest: Square, capture: Option<Piece> },
     Enpassant { side: Side, from: Square, dest: Square, capture: Square },
     Promote { from: Square, dest: Square, promoted: Piece, capture: Option<Piece> },
-    Castle { corner: Corner },
+    Castle { corner: Corner, king: Square, rook: Square },
     Null,
 }
 
@@ -33,3 +33,14 @@ pub enum MoveFacet {
     Attacking,
     Promoting,
 }
+
+/// The textual notations a move can be written in relative to a position.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, PartialOrd, Ord, Hash)]
+pub enum Notation {
+    /// Coordinate notation used by the UCI protocol, e.g. g1f3
+    Uci,
+    /// Standard algebraic notation used in PGN, e.g. Nf3
+    San,
+    /// Long algebraic notation, e.g. Ng1-f3
+    Lan,
+}
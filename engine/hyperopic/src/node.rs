@@ -1,15 +1,17 @@
-use crate::constants::side_parity;
-use crate::position::{Position, TerminalState};
+use crate::constants::{side, side_parity};
+use crate::position::Position;
 
 use crate::eval::material::{MaterialFacet, PieceValues};
 use crate::eval::{
-    CastlingFacet, DevelopmentFacet, KnightRimFacet, PawnStructureFacet, PieceSquareTablesFacet,
-    SafetyFacet,
+    CastlingFacet, DevelopmentFacet, EvalParams, KnightRimFacet, PawnStructureFacet,
+    PieceSquareTablesFacet, SafetyFacet,
 };
 use crate::moves::Move;
 use crate::phase::Phase;
-use crate::{see, Square};
+use crate::{see, Side, Square};
 use anyhow::Result;
+use serde_derive::Serialize;
+use std::sync::Arc;
 
 /// The evaluation upper/lower bound definition
 pub const INFTY: i32 = 500_000i32;
@@ -23,6 +25,39 @@ pub const LOSS_VALUE: i32 = -WIN_VALUE;
 /// The evaluation assigned to a drawn position.
 pub const DRAW_VALUE: i32 = 0;
 
+/// Mate scores are reduced by the distance in ply from the root of the search
+/// so that nearer mates are preferred, no mate can be further than this.
+pub const MAX_MATE_PLY: i32 = 1000;
+
+/// The evaluation of a position where the side to move mates in the given ply.
+pub fn mate_in(ply: usize) -> i32 {
+    WIN_VALUE - ply as i32
+}
+
+/// The evaluation of a position where the side to move is mated in the given ply.
+pub fn mated_in(ply: usize) -> i32 {
+    LOSS_VALUE + ply as i32
+}
+
+/// Wins found in the tablebase are valued below any forced mate but above any
+/// positional evaluation, reduced by distance from the root like mates.
+pub const TABLEBASE_WIN_VALUE: i32 = WIN_VALUE - 2 * MAX_MATE_PLY;
+
+/// The evaluation of a tablebase win for the side to move at the given ply.
+pub fn tablebase_win_at(ply: usize) -> i32 {
+    TABLEBASE_WIN_VALUE - ply as i32
+}
+
+/// Whether the evaluation represents a forced mate for either side.
+pub fn is_mate(eval: i32) -> bool {
+    eval.abs() > WIN_VALUE - MAX_MATE_PLY
+}
+
+/// Whether the evaluation represents a tablebase win for either side.
+pub fn is_tablebase_win(eval: i32) -> bool {
+    !is_mate(eval) && eval.abs() > TABLEBASE_WIN_VALUE - MAX_MATE_PLY
+}
+
 /// The different types of evaluation that can be generated by a facet.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Evaluation {
@@ -32,9 +67,48 @@ pub enum Evaluation {
     Phased { mid: i32, end: i32 },
 }
 
+impl Evaluation {
+    /// The (midgame, endgame) pair, a single evaluation applies to both
+    pub fn phased(&self) -> (i32, i32) {
+        match self {
+            Evaluation::Single(eval) => (*eval, *eval),
+            Evaluation::Phased { mid, end } => (*mid, *end),
+        }
+    }
+}
+
+/// The contribution of one facet to the evaluation of a position
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FacetBreakdown {
+    pub name: &'static str,
+    /// The (mid, end) evaluation of the white pieces from white's perspective
+    pub white: (i32, i32),
+    /// The (mid, end) evaluation of the black pieces from black's perspective
+    pub black: (i32, i32),
+    /// The (mid, end) evaluation of the facet from white's perspective
+    pub total: (i32, i32),
+    /// The total interpolated by the game phase
+    pub tapered: i32,
+}
+
+/// The evaluation of a position split by facet, all totals are from white's
+/// perspective.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalBreakdown {
+    pub facets: Vec<FacetBreakdown>,
+    /// How far the game has progressed from 0, the start, to 256, bare kings
+    pub phase: i32,
+    /// The sum of the tapered facet evaluations
+    pub total: i32,
+    /// The evaluation used by the search, relative to the side to move and
+    /// accounting for terminal positions
+    pub relative: i32,
+}
+
 /// Represents some (possibly stateful) feature of a position which can be
-/// evaluated.
-pub trait EvalFacet {
+/// evaluated. Facets must be cloneable and sendable so that a node can be
+/// copied across the threads of a parallel search.
+pub trait EvalFacet: CloneFacet + Send {
     /// Return the static evaluation of the given position. Implementors are
     /// guaranteed that exactly the same move sequence will have been passed to
     /// this component and the given board position. I.e the internal states
@@ -42,6 +116,14 @@ pub trait EvalFacet {
     /// WHITE, LARGER -VE SCORE BETTER FOR BLACK'.
     fn static_eval(&self, board: &Position) -> Evaluation;
 
+    /// A short name identifying this facet in an evaluation breakdown
+    fn name(&self) -> &'static str;
+
+    /// The part of the static evaluation arising from the pieces of the given
+    /// side, from the perspective of that side. The static evaluation is the
+    /// white part minus the black part, up to rounding.
+    fn side_eval(&self, board: &Position, side: Side) -> Evaluation;
+
     /// Update internal state by making the given move FROM the given position
     fn make(&mut self, mv: &Move, board: &Position);
 
@@ -50,9 +132,27 @@ pub trait EvalFacet {
     fn unmake(&mut self, mv: &Move);
 }
 
+/// Allows a boxed facet to be cloned, implemented for any facet which is Clone.
+pub trait CloneFacet {
+    fn clone_facet(&self) -> Box<dyn EvalFacet>;
+}
+
+impl<F: EvalFacet + Clone + 'static> CloneFacet for F {
+    fn clone_facet(&self) -> Box<dyn EvalFacet> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn EvalFacet> {
+    fn clone(&self) -> Self {
+        self.clone_facet()
+    }
+}
+
 /// Wrapper around a chess board which adds position evaluation capabilities.
 /// The evaluation function is decomposed into orthogonal "facets". The minimal
 /// evaluator looks only at material.
+#[derive(Clone)]
 pub struct TreeNode {
     position: Position,
     phase: Phase,
@@ -61,6 +161,18 @@ pub struct TreeNode {
 }
 
 impl TreeNode {
+    /// Create a node evaluating the given position with the default facets
+    /// using the given weights.
+    pub fn new(board: Position, params: &EvalParams) -> TreeNode {
+        TreeNodeBuilder::default().with_params(params.clone()).build(board)
+    }
+
+    /// Start building a node with a chosen set of facets, initially the
+    /// default facets with the default weights.
+    pub fn builder() -> TreeNodeBuilder {
+        TreeNodeBuilder::default()
+    }
+
     /// Get an immutable reference to the underlying position
     pub fn position(&self) -> &Position {
         &self.position
@@ -103,8 +215,8 @@ impl TreeNode {
     /// termination.
     pub fn relative_eval(&self) -> i32 {
         match self.position.compute_terminal_state() {
-            Some(TerminalState::Draw) => DRAW_VALUE,
-            Some(TerminalState::Loss) => LOSS_VALUE,
+            Some(state) if state.is_loss() => LOSS_VALUE,
+            Some(_) => DRAW_VALUE,
             None => {
                 let parity = side_parity(self.position.active);
                 let material = self.phase.unwrap(self.material.static_eval(&self.position));
@@ -118,6 +230,30 @@ impl TreeNode {
         }
     }
 
+    /// The evaluation of the position split into the contribution of each
+    /// facet, with the material facet first.
+    pub fn eval_breakdown(&self) -> EvalBreakdown {
+        let facets: Vec<_> = std::iter::once(&self.material as &dyn EvalFacet)
+            .chain(self.facets.iter().map(|f| f.as_ref()))
+            .map(|facet| {
+                let total = facet.static_eval(&self.position);
+                FacetBreakdown {
+                    name: facet.name(),
+                    white: facet.side_eval(&self.position, side::W).phased(),
+                    black: facet.side_eval(&self.position, side::B).phased(),
+                    total: total.phased(),
+                    tapered: self.phase.unwrap(total),
+                }
+            })
+            .collect();
+        EvalBreakdown {
+            phase: self.phase.value(),
+            total: facets.iter().map(|f| f.tapered).sum(),
+            relative: self.relative_eval(),
+            facets,
+        }
+    }
+
     /// API function for determining whether an exchange is good on this
     /// board. The board must have a piece at both the source and target square
     /// otherwise this function will panic. The pieces must be on opposing
@@ -139,47 +275,238 @@ impl TreeNode {
     }
 }
 
-impl From<Position> for TreeNode {
-    fn from(board: Position) -> Self {
-        let mut board_clone = board.clone();
+/// Creates a facet with the given weights for the given position, which is the
+/// start of the history of the position the node is built for.
+type FacetFactory = Arc<dyn Fn(&EvalParams, &Position) -> Box<dyn EvalFacet> + Send + Sync>;
+
+/// Assembles tree nodes from a chosen set of facets. The material facet is
+/// always included as the search relies on it, the other facets are evaluated
+/// in the order they were added. Builders are cheap to clone and can build any
+/// number of nodes.
+#[derive(Clone)]
+pub struct TreeNodeBuilder {
+    params: Arc<EvalParams>,
+    facets: Vec<(&'static str, FacetFactory)>,
+}
+
+impl Default for TreeNodeBuilder {
+    fn default() -> Self {
+        TreeNodeBuilder { params: Arc::new(EvalParams::default()), facets: vec![] }
+            .with_facet("PieceSquareTables", |params, position| {
+                PieceSquareTablesFacet::new(&params.tables, position)
+            })
+            .with_facet("Castling", |params, position| {
+                CastlingFacet::new(&params.castling, position)
+            })
+            .with_facet("Development", |params, position| {
+                DevelopmentFacet::new(&params.development, position)
+            })
+            .with_facet("KnightRim", |params, position| {
+                KnightRimFacet::new(&params.knight_rim, position)
+            })
+            .with_facet("PawnStructure", |params, _| PawnStructureFacet::from(&params.pawns))
+            .with_facet("Safety", |params, _| SafetyFacet::from(&params.safety))
+    }
+}
+
+impl TreeNodeBuilder {
+    /// Set the weights passed to every facet when a node is built
+    pub fn with_params(mut self, params: EvalParams) -> Self {
+        self.params = Arc::new(params);
+        self
+    }
+
+    /// Add a facet created by the given factory under the name the facet
+    /// reports, any facet already added with the same name is replaced in place.
+    pub fn with_facet<F, E>(mut self, name: &'static str, factory: F) -> Self
+    where
+        F: Fn(&EvalParams, &Position) -> E + Send + Sync + 'static,
+        E: EvalFacet + 'static,
+    {
+        let factory: FacetFactory =
+            Arc::new(move |params, position| Box::new(factory(params, position)));
+        match self.facets.iter_mut().find(|(existing, _)| *existing == name) {
+            Some(entry) => entry.1 = factory,
+            None => self.facets.push((name, factory)),
+        }
+        self
+    }
+
+    /// Remove the facet with the given name if it was added
+    pub fn without_facet(mut self, name: &str) -> Self {
+        self.facets.retain(|(existing, _)| *existing != name);
+        self
+    }
+
+    /// Remove every facet, leaving only material in the evaluation
+    pub fn without_facets(mut self) -> Self {
+        self.facets.clear();
+        self
+    }
+
+    /// The names of the facets which will be built, excluding material
+    pub fn facet_names(&self) -> Vec<&'static str> {
+        self.facets.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// Build a node for the given position. The facets are initialised from the
+    /// position at the start of its history and then follow the moves which
+    /// were played from it.
+    pub fn build(&self, position: Position) -> TreeNode {
+        let mut root = position;
         let mut moves = vec![];
-        while let Ok(m) = board_clone.unmake() {
+        while let Ok(m) = root.unmake() {
             moves.push(m)
         }
+        let mut node = TreeNode {
+            phase: Phase::from(&root),
+            material: MaterialFacet::new(&self.params.material, &root),
+            facets: self
+                .facets
+                .iter()
+                .map(|(name, factory)| {
+                    let facet = factory(&self.params, &root);
+                    debug_assert_eq!(*name, facet.name(), "Facet added under the wrong name");
+                    facet
+                })
+                .collect(),
+            position: root,
+        };
+        moves.into_iter().rev().for_each(|m| node.make(m).unwrap());
+        node
+    }
+}
 
-        if board_clone == Position::default() {
-            let mut eval = TreeNode {
-                position: Position::default(),
-                phase: Default::default(),
-                material: Default::default(),
-                facets: vec![
-                    Box::new(PieceSquareTablesFacet::default()),
-                    Box::new(CastlingFacet::default()),
-                    Box::new(DevelopmentFacet::default()),
-                    Box::new(KnightRimFacet::default()),
-                    Box::new(PawnStructureFacet::default()),
-                    Box::new(SafetyFacet::default()),
-                ],
-            };
-            moves.into_iter().rev().for_each(|m| eval.make(m).unwrap());
-            eval
-        } else {
-            TreeNode {
-                material: MaterialFacet::from(&board),
-                phase: Phase::from(&board),
-                facets: vec![
-                    Box::new(PieceSquareTablesFacet::from(&board)),
-                    Box::new(PawnStructureFacet::default()),
-                    Box::new(SafetyFacet::default()),
-                ],
-                position: board,
-            }
-        }
+impl From<Position> for TreeNode {
+    fn from(board: Position) -> Self {
+        TreeNode::new(board, &EvalParams::default())
     }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::constants::{side, side_parity};
+    use crate::moves::Move;
+    use crate::node::{EvalFacet, Evaluation, TreeNode};
+    use crate::position::Position;
+    use crate::Side;
+
+    /// Favours the side to move by a fixed amount
+    #[derive(Clone)]
+    struct Tempo(i32);
+
+    impl EvalFacet for Tempo {
+        fn static_eval(&self, board: &Position) -> Evaluation {
+            Evaluation::Single(side_parity(board.active) * self.0)
+        }
+
+        fn name(&self) -> &'static str {
+            "Tempo"
+        }
+
+        fn side_eval(&self, board: &Position, side: Side) -> Evaluation {
+            Evaluation::Single(if board.active == side { self.0 } else { 0 })
+        }
+
+        fn make(&mut self, _: &Move, _: &Position) {}
+
+        fn unmake(&mut self, _: &Move) {}
+    }
+
+    fn assert_consistent(position: Position) {
+        let node = TreeNode::from(position.clone());
+        let breakdown = node.eval_breakdown();
+        assert_eq!(7, breakdown.facets.len());
+        assert_eq!("Material", breakdown.facets[0].name);
+        for facet in breakdown.facets.iter() {
+            let (mid, end) = facet.total;
+            // Only the safety endgame value is rounded separately for each side
+            assert_eq!(mid, facet.white.0 - facet.black.0, "{}", facet.name);
+            assert!((end - (facet.white.1 - facet.black.1)).abs() <= 1, "{}", facet.name);
+        }
+        let parity = if position.active == side::W { 1 } else { -1 };
+        assert_eq!(node.relative_eval(), parity * breakdown.total);
+        assert_eq!(node.relative_eval(), breakdown.relative);
+    }
+
+    #[test]
+    fn breakdown_from_start() {
+        assert_consistent(Position::default());
+        assert_consistent(
+            "1. e4 c5 2. Nc3 Nc6 3. Nf3 e6 4. Bc4 d6 5. d4 cxd4 6. Nxd4 Nxd4 7. Qxd4 Ne7 \
+            8. Bg5 Nc6 9. Qd2 f6 10. Be3 Be7 11. O-O-O Ne5 12. Be2 Bd7 13. Nb5 Bxb5 14. Bxb5+ Nc6"
+                .parse()
+                .unwrap(),
+        );
+    }
+
+    #[test]
+    fn breakdown_from_fen() {
+        assert_consistent(
+            "r5k1/pb4pp/1pn1pq2/5B2/2Pr4/B7/PP3RPP/R4QK1 b - - 0 23".parse().unwrap(),
+        );
+        assert_consistent("8/2p5/1pP5/1P1k1p2/5P2/4K3/6P1/8 w - - 3 50".parse().unwrap());
+    }
+
+    #[test]
+    fn fen_matches_moves() {
+        // Every move other than castling develops a new piece so the number of
+        // moves played can be recovered exactly from the fen
+        let played: Position = "1. e4 Nh6 2. Nf3 e5 3. Bc4 Nc6 4. O-O Bc5".parse().unwrap();
+        let from_fen: Position = played.to_string().parse().unwrap();
+        assert!(from_fen.history.is_empty());
+        let (played, from_fen) = (TreeNode::from(played), TreeNode::from(from_fen));
+        let (played, from_fen) = (played.eval_breakdown(), from_fen.eval_breakdown());
+        for (expected, actual) in played.facets.iter().zip(from_fen.facets.iter()) {
+            assert_eq!(expected.total, actual.total, "{}", expected.name);
+        }
+        assert_eq!(played.relative, from_fen.relative);
+    }
+
+    #[test]
+    fn builder_default_facets() {
+        let position: Position = "1. e4 e5 2. Ke2 Nf6 3. Nh3 Bc5".parse().unwrap();
+        let built = TreeNode::builder().build(position.clone());
+        let names: Vec<_> = built.eval_breakdown().facets.iter().map(|f| f.name).collect();
+        assert_eq!(names[1..], TreeNode::builder().facet_names());
+        assert_eq!(TreeNode::from(position).relative_eval(), built.relative_eval());
+    }
+
+    #[test]
+    fn builder_without_facet() {
+        let position: Position = "1. e4 e5 2. Ke2 Nf6 3. Nh3 Bc5".parse().unwrap();
+        let full = TreeNode::from(position.clone());
+        let builder = TreeNode::builder().without_facet("Castling").without_facet("KnightRim");
+        assert_eq!(
+            vec!["PieceSquareTables", "Development", "PawnStructure", "Safety"],
+            builder.facet_names()
+        );
+        let ablated = builder.build(position.clone());
+        let removed = full
+            .eval_breakdown()
+            .facets
+            .iter()
+            .filter(|f| f.name == "Castling" || f.name == "KnightRim")
+            .map(|f| f.tapered)
+            .sum::<i32>();
+        assert_ne!(0, removed);
+        let parity = side_parity(position.active);
+        assert_eq!(full.relative_eval() - parity * removed, ablated.relative_eval());
+    }
+
+    #[test]
+    fn builder_custom_facet() {
+        let position: Position = "1. d4 d5 2. c4".parse().unwrap();
+        let material = TreeNode::builder().without_facets().build(position.clone());
+        assert_eq!(1, material.eval_breakdown().facets.len());
+        let builder = TreeNode::builder().without_facets().with_facet("Tempo", |_, _| Tempo(15));
+        assert_eq!(material.relative_eval() + 15, builder.build(position.clone()).relative_eval());
+        // Adding a facet with the same name replaces the existing one
+        let builder = builder.with_facet("Tempo", |_, _| Tempo(20));
+        assert_eq!(vec!["Tempo"], builder.facet_names());
+        assert_eq!(material.relative_eval() + 20, builder.build(position).relative_eval());
+    }
+
     //#[test]
     //fn sanity() {
     //    assert_eq!(crate::START_FEN, crate::START_FEN.parse::<>().unwrap().to_fen())
@@ -6,11 +6,12 @@ use regex::Regex;
 use Move::{Castle, Enpassant, Normal, Null, Promote};
 
 use crate::board::iter;
-use crate::constants::{class, lift, piece_class, square_file, square_rank};
-use crate::moves::{Move, Moves};
+use crate::constants::boards::RANKS;
+use crate::constants::{class, create_piece, lift, piece_class, square_file, square_rank};
+use crate::moves::{Move, Moves, Notation};
 use crate::{Board, Class, Piece, PieceMap, Square};
 
-use crate::position::{Position, CASTLING_DETAILS};
+use crate::position::{castle_targets, CastlingDetails, Position};
 
 impl FromStr for Position {
     type Err = Error;
@@ -25,6 +26,21 @@ impl FromStr for Position {
 }
 
 impl Position {
+    /// Parse a position from FEN only, unlike [`FromStr`] this does not fall
+    /// back to interpreting the input as a sequence of moves.
+    pub fn from_fen(fen: &str) -> Result<Position> {
+        parse_fen(fen)
+    }
+
+    /// Parse a single legal move written in the given notation.
+    pub fn parse_move(&self, input: &str, notation: Notation) -> Result<Move> {
+        match notation {
+            Notation::Uci => parse_uci_move(self, input),
+            Notation::San => parse_pgn_move(self, input),
+            Notation::Lan => parse_lan_move(self, input),
+        }
+    }
+
     pub fn play<S: AsRef<str>>(&mut self, moves: S) -> Result<Vec<Move>> {
         let moves = moves.as_ref();
         let pgn_count = PGN_MOVE.find_iter(moves).count();
@@ -46,8 +62,8 @@ pub struct StringIndexMap {
     content: Vec<String>,
 }
 
-const FILE_CHARS: [char; 8] = ['h', 'g', 'f', 'e', 'd', 'c', 'b', 'a'];
-const RANK_CHARS: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
+pub(crate) const FILE_CHARS: [char; 8] = ['h', 'g', 'f', 'e', 'd', 'c', 'b', 'a'];
+pub(crate) const RANK_CHARS: [char; 8] = ['1', '2', '3', '4', '5', '6', '7', '8'];
 
 impl StringIndexMap {
     pub fn squares() -> StringIndexMap {
@@ -123,7 +139,7 @@ lazy_static! {
     static ref FEN_RANK: Regex = r"([pnbrqkPNBRQK1-8]{1,8})".parse().unwrap();
 
     static ref FEN: Regex = format!(
-        r"{}(/{}){{7}}\s+(w|b)\s+(-|[kqKQ]{{1,4}})\s+(-|{})\s+\d+\s+\d+",
+        r"{}(/{}){{7}}\s+(w|b)\s+(-|[kqKQa-hA-H]{{1,4}})\s+(-|{})\s+\d+\s+\d+",
         FEN_RANK.as_str(),
         FEN_RANK.as_str(),
         SQUARE.as_str(),
@@ -155,22 +171,32 @@ lazy_static! {
     static ref UCI_MOVE: Regex = r"(([a-h][1-8]){2}[nbrq]?)".parse().unwrap();
 }
 
+/// Castling moves are accepted both as the king moving to its target square
+/// and as the king taking its own rook. In Chess960 the first form can clash
+/// with a normal king move, in which case the normal move is chosen.
 pub fn parse_uci_move(position: &Position, input: &str) -> Result<Move> {
     let (f, d, promoting) = extract_uci_component(input)?;
-    position
-        .moves(&Moves::All)
-        .into_iter()
-        .find(|m| match m {
-            Null => false,
-            &Normal { from, dest, .. } => from == f && dest == d,
-            &Enpassant { from, dest, .. } => from == f && dest == d,
-            &Castle { corner, .. } => CASTLING_DETAILS[corner].king_line == (f, d),
-            &Promote { from, dest, promoted, .. } => {
-                from == f
-                    && dest == d
-                    && promoting.map(|c| class_char(piece_class(promoted)) == c).unwrap_or(false)
-            }
+    let moves = position.moves(&Moves::All);
+    let non_castle = moves.iter().find(|m| match m {
+        Null | Castle { .. } => false,
+        &&Normal { from, dest, .. } => from == f && dest == d,
+        &&Enpassant { from, dest, .. } => from == f && dest == d,
+        &&Promote { from, dest, promoted, .. } => {
+            from == f
+                && dest == d
+                && promoting.map(|c| class_char(piece_class(promoted)) == c).unwrap_or(false)
+        }
+    });
+    non_castle
+        .or_else(|| {
+            moves.iter().find(|m| match m {
+                &&Castle { corner, king, rook } => {
+                    king == f && (rook == d || castle_targets(corner).0 == d)
+                }
+                _ => false,
+            })
         })
+        .cloned()
         .ok_or(anyhow!("No moves matching {}", input))
 }
 
@@ -186,6 +212,9 @@ fn class_char(piece: Class) -> char {
 
 fn extract_uci_component(m: &str) -> Result<(Square, Square, Option<char>)> {
     let squares: Vec<_> = SQUARE.find_iter(m).map(|m| m.as_str()).collect();
+    if squares.len() < 2 {
+        return Err(anyhow!("{} is not a uci move", m));
+    }
     let from = SQUARE_MAP.index(squares[0]);
     let dest = SQUARE_MAP.index(squares[1]);
     Ok((from, dest, m.chars().skip(4).next()))
@@ -198,7 +227,7 @@ pub fn parse_pgn_move(position: &Position, input: &str) -> Result<Move> {
         return moves
             .iter()
             .find(|&m| {
-                if let Castle { corner } = m {
+                if let Castle { corner, .. } = m {
                     *corner % 2 == if input == "O-O" { 0 } else { 1 }
                 } else {
                     false
@@ -243,6 +272,16 @@ pub fn parse_pgn_move(position: &Position, input: &str) -> Result<Move> {
         .ok_or(anyhow!("No move matching {}", input))
 }
 
+/// Long algebraic moves are the same as SAN moves with the source square fully
+/// disambiguated, so once the separator is removed we can reuse the SAN parser.
+pub fn parse_lan_move(position: &Position, input: &str) -> Result<Move> {
+    if PGN_CASTLE.is_match(input) {
+        parse_pgn_move(position, input)
+    } else {
+        parse_pgn_move(position, input.replace('-', "").as_str())
+    }
+}
+
 fn matches_square(file: Option<char>, rank: Option<char>, square: Square) -> bool {
     let sq_file = FILE_CHARS[square_file(square)];
     let sq_rank = RANK_CHARS[square_rank(square)];
@@ -293,9 +332,33 @@ fn parse_fen(fen: &str) -> Result<Position> {
     let piece_boards = parse_fen_pieces(parts[0]);
     let mut piece_locs = [None; 64];
     (0..12).for_each(|p| iter(piece_boards[p]).for_each(|s| piece_locs[s] = Some(p)));
-    let rights_fn = |s: &str| parts[2].contains(s);
-    let castling_rights = [rights_fn("K"), rights_fn("Q"), rights_fn("k"), rights_fn("q")];
-    Ok(Position::new(active, enpassant, clock, castling_rights, piece_locs))
+    let mut castling_rights = [false; 4];
+    let mut castling_rooks = vec![];
+    for c in parts[2].chars().filter(|&c| c != '-') {
+        let side = if c.is_ascii_uppercase() { side::W } else { side::B };
+        match c.to_ascii_lowercase() {
+            'k' => castling_rights[2 * side] = true,
+            'q' => castling_rights[2 * side + 1] = true,
+            file => {
+                // Shredder-FEN style rights name the file of the castling rook
+                let king = piece_boards[create_piece(side, class::K)];
+                let king = iter(king & RANKS[7 * side]).next().ok_or(anyhow!(
+                    "No king on the back rank for castling rights {} in {}",
+                    c,
+                    fen
+                ))?;
+                let rook = 56 * side + FILE_CHARS.iter().position(|&f| f == file).unwrap();
+                let corner = 2 * side + if rook < king { 0 } else { 1 };
+                castling_rights[corner] = true;
+                castling_rooks.push((corner, king, rook));
+            }
+        }
+    }
+    let mut position = Position::new(active, enpassant, clock, castling_rights, piece_locs);
+    for (corner, king, rook) in castling_rooks {
+        position.castling_details[corner] = CastlingDetails::new(corner, king, rook);
+    }
+    Ok(position)
 }
 
 fn parse_fen_pieces(fen: &str) -> PieceMap<Board> {
@@ -686,4 +749,29 @@ mod test_single_uci_move {
             "e8c8",
         )
     }
+
+    #[test]
+    fn king_takes_rook_castle() {
+        execute_success_test(
+            "cwk",
+            "r3k2r/pp1q1ppp/n1p2n2/4p3/3pP2P/3P1QP1/PPPN1PB1/R3K2R w KQkq - 1 13",
+            "e1h1",
+        )
+    }
+
+    #[test]
+    fn chess960_castle() {
+        use crate::constants::{corner, square::*};
+        let position = "1r1k4/8/8/8/8/8/8/1R1K2R1 w GB - 0 1".parse::<Position>().unwrap();
+        let queenside = Move::Castle { corner: corner::WQ, king: D1, rook: B1 };
+        assert_eq!(queenside, parse_uci_move(&position, "d1b1").unwrap());
+        let kingside = Move::Castle { corner: corner::WK, king: D1, rook: G1 };
+        assert_eq!(kingside, parse_uci_move(&position, "d1g1").unwrap());
+    }
+
+    #[test]
+    fn chess960_king_move_preferred_over_castle() {
+        let position = "1r1k4/8/8/8/8/8/8/1R1K2R1 w GB - 0 1".parse::<Position>().unwrap();
+        assert!(matches!(parse_uci_move(&position, "d1c1").unwrap(), Normal { .. }));
+    }
 }
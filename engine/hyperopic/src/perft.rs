@@ -0,0 +1,41 @@
+use anyhow::Result;
+
+use crate::moves::{Move, Moves};
+use crate::position::Position;
+
+/// Count the number of leaf nodes in the tree of legal moves rooted at the
+/// given position and extending to the given depth. Used to validate the
+/// move generation against known results, the position is left unchanged.
+pub fn perft(position: &mut Position, depth: usize) -> Result<u64> {
+    if depth == 0 {
+        return Ok(1);
+    }
+    let moves = position.moves(&Moves::All);
+    if depth == 1 {
+        return Ok(moves.len() as u64);
+    }
+    let mut count = 0;
+    for m in moves {
+        position.make(m)?;
+        count += perft(position, depth - 1)?;
+        position.unmake()?;
+    }
+    Ok(count)
+}
+
+/// Split the perft count of the given position by each legal move available
+/// at the root, useful for finding the line in which move generation diverges
+/// from a reference engine.
+pub fn divide(position: &mut Position, depth: usize) -> Result<Vec<(Move, u64)>> {
+    if depth == 0 {
+        return Ok(vec![]);
+    }
+    let mut result = vec![];
+    for m in position.moves(&Moves::All) {
+        position.make(m.clone())?;
+        let count = perft(position, depth - 1)?;
+        position.unmake()?;
+        result.push((m, count));
+    }
+    Ok(result)
+}
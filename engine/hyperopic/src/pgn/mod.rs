@@ -0,0 +1,5 @@
+pub use crate::pgn::reader::{PgnError, PgnMove, PgnReader, PgnRecord};
+pub use crate::pgn::writer::{MoveAnnotation, PgnGame, PgnTags, DEFAULT_LINE_WIDTH};
+
+mod reader;
+mod writer;
@@ -0,0 +1,626 @@
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, Lines};
+
+use anyhow::Result;
+
+use crate::moves::{Move, Notation};
+use crate::pgn::{PgnGame, PgnTags};
+use crate::position::Position;
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// An error encountered while reading a single game. The reader skips to the
+/// start of the next game so that the remaining games can still be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnError {
+    /// The line of the input the error was found on, starting from 1
+    pub line: usize,
+    pub message: String,
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// A single game read from PGN.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnRecord {
+    /// Every tag in the order it was given
+    pub tags: Vec<(String, String)>,
+    /// The position given by the FEN tag if present, otherwise the standard start
+    pub start: Position,
+    /// Comments preceding the first move
+    pub comments: Vec<String>,
+    pub moves: Vec<PgnMove>,
+    /// The game termination marker, absent if the movetext was cut short
+    pub result: Option<String>,
+}
+
+/// A move in the movetext along with everything attached to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnMove {
+    pub mv: Move,
+    /// Numeric annotation glyphs, suffixes like "!?" are converted to their NAG
+    pub nags: Vec<u8>,
+    pub comments: Vec<String>,
+    /// Alternatives to this move, each played from the position before it
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+impl PgnRecord {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    pub fn mainline(&self) -> impl Iterator<Item = &Move> {
+        self.moves.iter().map(|m| &m.mv)
+    }
+
+    pub fn final_position(&self) -> Result<Position> {
+        let mut position = self.start.clone();
+        for m in self.mainline() {
+            position.make(m.clone())?;
+        }
+        Ok(position)
+    }
+
+    /// Convert the mainline of this game into a form which can be written back
+    /// out, comments, NAGs and variations are dropped.
+    pub fn to_game(&self) -> PgnGame {
+        let tag = |name: &str| self.tag(name).map(|v| v.to_owned());
+        let defaults = PgnTags::default();
+        let tags = PgnTags {
+            event: tag("Event").unwrap_or(defaults.event),
+            site: tag("Site").unwrap_or(defaults.site),
+            date: tag("Date").unwrap_or(defaults.date),
+            round: tag("Round").unwrap_or(defaults.round),
+            white: tag("White").unwrap_or(defaults.white),
+            black: tag("Black").unwrap_or(defaults.black),
+            result: self.result.clone().or(tag("Result")),
+        };
+        let mut game = PgnGame::new(tags, self.start.clone(), self.mainline().cloned().collect());
+        game.extra_tags = self
+            .tags
+            .iter()
+            .filter(|(n, _)| !SEVEN_TAG_ROSTER.contains(&n.as_str()) && n != "SetUp" && n != "FEN")
+            .cloned()
+            .collect();
+        game
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    TagOpen,
+    TagClose,
+    Str(String),
+    Symbol(String),
+    Period,
+    Nag(u8),
+    Comment(String),
+    VariationOpen,
+    VariationClose,
+    Result(String),
+}
+
+/// Splits the input into tokens, tracking the line each one starts on.
+struct Lexer<R: BufRead> {
+    lines: Lines<R>,
+    chars: Vec<char>,
+    index: usize,
+    line: usize,
+    peeked: Option<(usize, Token)>,
+    /// Set once the underlying input fails, nothing more can be read after
+    failed: bool,
+}
+
+impl<R: BufRead> Lexer<R> {
+    fn new(input: R) -> Lexer<R> {
+        Lexer {
+            lines: input.lines(),
+            chars: vec![],
+            index: 0,
+            line: 0,
+            peeked: None,
+            failed: false,
+        }
+    }
+
+    fn error<T>(&self, line: usize, message: String) -> Result<T, PgnError> {
+        Err(PgnError { line, message })
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, PgnError> {
+        while self.index == self.chars.len() {
+            match self.lines.next() {
+                None => return Ok(None),
+                Some(Err(e)) => {
+                    self.failed = true;
+                    return self.error(self.line + 1, format!("Failed to read input: {}", e));
+                }
+                Some(Ok(line)) => {
+                    self.line += 1;
+                    // Lines starting with a percent sign are escaped from parsing
+                    if !line.starts_with('%') {
+                        self.chars = line.chars().chain(std::iter::once('\n')).collect();
+                        self.index = 0;
+                    }
+                }
+            }
+        }
+        Ok(Some(self.chars[self.index]))
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, PgnError> {
+        let c = self.peek_char()?;
+        self.index += c.is_some() as usize;
+        Ok(c)
+    }
+
+    fn push_back(&mut self, line: usize, token: Token) {
+        self.peeked = Some((line, token))
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&(usize, Token)>, PgnError> {
+        if self.peeked.is_none() {
+            self.peeked = self.lex()?;
+        }
+        Ok(self.peeked.as_ref())
+    }
+
+    fn next_token(&mut self) -> Result<Option<(usize, Token)>, PgnError> {
+        match self.peeked.take() {
+            Some(token) => Ok(Some(token)),
+            None => self.lex(),
+        }
+    }
+
+    fn lex(&mut self) -> Result<Option<(usize, Token)>, PgnError> {
+        while let Some(c) = self.peek_char()? {
+            if c.is_whitespace() {
+                self.index += 1;
+            } else {
+                break;
+            }
+        }
+        let line = self.line;
+        let c = match self.next_char()? {
+            None => return Ok(None),
+            Some(c) => c,
+        };
+        let token = match c {
+            '[' => Token::TagOpen,
+            ']' => Token::TagClose,
+            '(' => Token::VariationOpen,
+            ')' => Token::VariationClose,
+            '.' => Token::Period,
+            '*' => Token::Result("*".to_owned()),
+            '"' => Token::Str(self.lex_string(line)?),
+            '{' => Token::Comment(self.lex_comment(line)?),
+            ';' => {
+                let mut comment = String::new();
+                while let Some(c) = self.next_char()? {
+                    if c == '\n' {
+                        break;
+                    }
+                    comment.push(c);
+                }
+                Token::Comment(comment.trim().to_owned())
+            }
+            '$' => {
+                let digits = self.take_while(|c| c.is_ascii_digit())?;
+                match digits.parse::<u8>() {
+                    Ok(nag) => Token::Nag(nag),
+                    Err(_) => return self.error(line, format!("Bad NAG ${}", digits)),
+                }
+            }
+            '!' | '?' => {
+                let suffix = format!("{}{}", c, self.take_while(|c| c == '!' || c == '?')?);
+                match suffix.as_str() {
+                    "!" => Token::Nag(1),
+                    "?" => Token::Nag(2),
+                    "!!" => Token::Nag(3),
+                    "??" => Token::Nag(4),
+                    "!?" => Token::Nag(5),
+                    "?!" => Token::Nag(6),
+                    _ => return self.error(line, format!("Bad move suffix {}", suffix)),
+                }
+            }
+            c if is_symbol_char(c) => {
+                let symbol = format!("{}{}", c, self.take_while(is_symbol_char)?);
+                if RESULTS.contains(&symbol.as_str()) {
+                    Token::Result(symbol)
+                } else {
+                    Token::Symbol(symbol)
+                }
+            }
+            c => return self.error(line, format!("Unexpected character {}", c)),
+        };
+        Ok(Some((line, token)))
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> Result<String, PgnError> {
+        let mut dest = String::new();
+        while let Some(c) = self.peek_char()? {
+            if c == '\n' || !pred(c) {
+                break;
+            }
+            dest.push(c);
+            self.index += 1;
+        }
+        Ok(dest)
+    }
+
+    fn lex_string(&mut self, line: usize) -> Result<String, PgnError> {
+        let mut dest = String::new();
+        loop {
+            match self.next_char()? {
+                None | Some('\n') => return self.error(line, "Unterminated string".to_owned()),
+                Some('"') => return Ok(dest),
+                Some('\\') => match self.next_char()? {
+                    Some(c @ ('"' | '\\')) => dest.push(c),
+                    _ => return self.error(self.line, "Bad escape in string".to_owned()),
+                },
+                Some(c) => dest.push(c),
+            }
+        }
+    }
+
+    fn lex_comment(&mut self, line: usize) -> Result<String, PgnError> {
+        let mut dest = String::new();
+        loop {
+            match self.next_char()? {
+                None => return self.error(line, "Unterminated comment".to_owned()),
+                Some('}') => return Ok(dest.split_whitespace().collect::<Vec<_>>().join(" ")),
+                Some(c) => dest.push(c),
+            }
+        }
+    }
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)
+}
+
+/// Moves played in sequence, either a whole game or a variation within one.
+struct MoveSequence {
+    moves: Vec<PgnMove>,
+    /// Comments preceding the first move
+    leading_comments: Vec<String>,
+    /// The game termination marker if the sequence was ended by one
+    result: Option<String>,
+}
+
+/// Streams games from PGN input. Each game is yielded as it is read, a game
+/// which cannot be parsed is yielded as an error and reading continues from
+/// the game after it.
+pub struct PgnReader<R: BufRead> {
+    lexer: Lexer<R>,
+    in_movetext: bool,
+    finished: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(input: R) -> PgnReader<R> {
+        PgnReader { lexer: Lexer::new(input), in_movetext: false, finished: false }
+    }
+
+    fn read_game(&mut self) -> Result<Option<PgnRecord>, PgnError> {
+        self.in_movetext = false;
+        if self.lexer.peek_token()?.is_none() {
+            return Ok(None);
+        }
+        let mut tags = vec![];
+        let mut start = Position::default();
+        while let Some((line, Token::TagOpen)) = self.lexer.peek_token()?.cloned() {
+            self.lexer.next_token()?;
+            let (name, value) = self.read_tag(line)?;
+            if name == "FEN" {
+                start = Position::from_fen(value.as_str())
+                    .map_err(|e| PgnError { line, message: format!("Bad FEN {}: {}", value, e) })?;
+            }
+            tags.push((name, value));
+        }
+        let mut position = start.clone();
+        let sequence = self.read_moves(&mut position, 0)?;
+        Ok(Some(PgnRecord {
+            tags,
+            start,
+            comments: sequence.leading_comments,
+            moves: sequence.moves,
+            result: sequence.result,
+        }))
+    }
+
+    fn read_tag(&mut self, line: usize) -> Result<(String, String), PgnError> {
+        let name = self.lexer.next_token()?.map(|(_, t)| t);
+        let value = self.lexer.next_token()?.map(|(_, t)| t);
+        let close = self.lexer.next_token()?.map(|(_, t)| t);
+        match (name, value, close) {
+            (Some(Token::Symbol(name)), Some(Token::Str(value)), Some(Token::TagClose)) => {
+                Ok((name, value))
+            }
+            _ => self.lexer.error(line, "Malformed tag".to_owned()),
+        }
+    }
+
+    /// Read a sequence of moves played from the given position.
+    fn read_moves(
+        &mut self,
+        position: &mut Position,
+        depth: usize,
+    ) -> Result<MoveSequence, PgnError> {
+        let mut moves: Vec<PgnMove> = vec![];
+        let mut leading_comments = vec![];
+        loop {
+            let (line, token) = match self.lexer.next_token()? {
+                Some(next) => next,
+                None if depth == 0 => {
+                    return Ok(MoveSequence { moves, leading_comments, result: None })
+                }
+                None => {
+                    return self.lexer.error(self.lexer.line, "Unterminated variation".to_owned())
+                }
+            };
+            let error = |message: String| Err(PgnError { line, message });
+            self.in_movetext |= token != Token::TagOpen;
+            match token {
+                Token::Period => {}
+                Token::Symbol(s) if s.chars().all(|c| c.is_ascii_digit()) => {}
+                Token::Symbol(s) => {
+                    let mv = if s == "--" {
+                        Move::Null
+                    } else {
+                        // Castling is sometimes written with zeros rather than letters
+                        let san =
+                            if s.starts_with("0-0") { s.replace('0', "O") } else { s.clone() };
+                        match position.parse_move(san.as_str(), Notation::San) {
+                            Ok(mv) => mv,
+                            Err(e) => return error(format!("Illegal move {}: {}", s, e)),
+                        }
+                    };
+                    if let Err(e) = position.make(mv.clone()) {
+                        return error(format!("Cannot play {}: {}", s, e));
+                    }
+                    moves.push(PgnMove { mv, nags: vec![], comments: vec![], variations: vec![] })
+                }
+                Token::Nag(nag) => match moves.last_mut() {
+                    Some(m) => m.nags.push(nag),
+                    None => return error(format!("Annotation ${} precedes any move", nag)),
+                },
+                Token::Comment(comment) => match moves.last_mut() {
+                    Some(m) => m.comments.push(comment),
+                    None => leading_comments.push(comment),
+                },
+                Token::VariationOpen => {
+                    let last = match moves.last_mut() {
+                        Some(m) => m,
+                        None => return error("Variation precedes any move".to_owned()),
+                    };
+                    let mut variation_start = position.clone();
+                    if let Err(e) = variation_start.unmake() {
+                        return error(format!("Cannot start variation: {}", e));
+                    }
+                    let mut variation = self.read_moves(&mut variation_start, depth + 1)?;
+                    // Comments leading a variation are attached to its first move
+                    if let Some(first) = variation.moves.first_mut() {
+                        first.comments.splice(0..0, variation.leading_comments);
+                    }
+                    last.variations.push(variation.moves);
+                }
+                Token::VariationClose if depth > 0 => {
+                    return Ok(MoveSequence { moves, leading_comments, result: None })
+                }
+                Token::Result(result) if depth == 0 => {
+                    return Ok(MoveSequence { moves, leading_comments, result: Some(result) })
+                }
+                // The tags of the next game, this one was missing its result
+                Token::TagOpen if depth == 0 => {
+                    self.lexer.push_back(line, Token::TagOpen);
+                    return Ok(MoveSequence { moves, leading_comments, result: None });
+                }
+                token => return error(format!("Unexpected {:?}", token)),
+            }
+        }
+    }
+
+    /// Discard the remainder of a game which failed to parse, stopping after its
+    /// result or before the tags of the following game.
+    fn skip_game(&mut self) {
+        let mut in_movetext = self.in_movetext;
+        loop {
+            match self.lexer.next_token() {
+                Ok(None) => return,
+                Ok(Some((_, Token::Result(_)))) if in_movetext => return,
+                Ok(Some((line, Token::TagOpen))) if in_movetext => {
+                    return self.lexer.push_back(line, Token::TagOpen)
+                }
+                Ok(Some((_, Token::TagOpen | Token::TagClose | Token::Str(_)))) => {}
+                Ok(Some(_)) => in_movetext = true,
+                Err(_) if self.lexer.failed => return,
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnRecord, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        match self.read_game() {
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Ok(Some(game)) => Some(Ok(game)),
+            Err(e) => {
+                self.finished = self.lexer.failed;
+                if !self.finished {
+                    self.skip_game();
+                }
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::constants::corner;
+    use crate::constants::square::*;
+
+    fn read_all(pgn: &str) -> Vec<Result<PgnRecord, PgnError>> {
+        PgnReader::new(pgn.as_bytes()).collect()
+    }
+
+    fn mainline_uci(game: &PgnRecord) -> Vec<String> {
+        game.mainline().map(|m| m.to_string()).collect()
+    }
+
+    #[test]
+    fn tags_moves_and_result() {
+        let pgn = r#"[Event "F/S Return Match"]
+[Site "Belgrade, Serbia JUG"]
+[White "Fischer, Robert J."]
+[Black "Spassky, Boris V."]
+[Result "1/2-1/2"]
+
+1. e4 e5 2. Nf3 Nc6 3. Bb5 a6 4. Ba4 Nf6 5. O-O 1/2-1/2
+"#;
+        let games = read_all(pgn);
+        assert_eq!(1, games.len());
+        let game = games[0].as_ref().unwrap();
+        assert_eq!(Some("Belgrade, Serbia JUG"), game.tag("Site"));
+        assert_eq!(Some("Fischer, Robert J."), game.tag("White"));
+        assert_eq!(5, game.tags.len());
+        assert_eq!(Some("1/2-1/2".to_owned()), game.result);
+        assert_eq!(
+            vec!["e2e4", "e7e5", "g1f3", "b8c6", "f1b5", "a7a6", "b5a4", "g8f6", "e1g1"],
+            mainline_uci(game)
+        );
+        assert_eq!(
+            &Move::Castle { corner: corner::WK, king: E1, rook: H1 },
+            game.mainline().last().unwrap()
+        );
+    }
+
+    #[test]
+    fn comments_nags_and_variations() {
+        let pgn = r#"{Opening comment}
+1. e4 $1 {Best by test} e5!? (1... c5 {Sicilian} 2. Nf3 (2. c3) d6) (; the French
+1... e6) 2. Nf3?! *"#;
+        let games = read_all(pgn);
+        let game = games[0].as_ref().unwrap();
+        assert!(game.tags.is_empty());
+        assert_eq!(vec!["Opening comment".to_owned()], game.comments);
+        assert_eq!(Some("*".to_owned()), game.result);
+        assert_eq!(vec!["e2e4", "e7e5", "g1f3"], mainline_uci(game));
+        let (e4, e5, nf3) = (&game.moves[0], &game.moves[1], &game.moves[2]);
+        assert_eq!(vec![1], e4.nags);
+        assert_eq!(vec!["Best by test".to_owned()], e4.comments);
+        assert_eq!(vec![5], e5.nags);
+        assert_eq!(vec![6], nf3.nags);
+        assert_eq!(2, e5.variations.len());
+        let sicilian = &e5.variations[0];
+        assert_eq!(
+            vec!["c7c5", "g1f3", "d7d6"],
+            sicilian.iter().map(|m| m.mv.to_string()).collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["Sicilian".to_owned()], sicilian[0].comments);
+        assert_eq!("c2c3", sicilian[1].variations[0][0].mv.to_string());
+        let french = &e5.variations[1];
+        assert_eq!("e7e6", french[0].mv.to_string());
+        assert_eq!(vec!["the French".to_owned()], french[0].comments);
+    }
+
+    #[test]
+    fn fen_tag_sets_start() {
+        let pgn = r#"[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+
+1... Kd7 2. e4 *"#;
+        let game = read_all(pgn).remove(0).unwrap();
+        assert_eq!(Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap(), game.start);
+        assert_eq!(vec!["e8d7", "e2e4"], mainline_uci(&game));
+    }
+
+    #[test]
+    fn malformed_game_is_skipped() {
+        let pgn = r#"[Event "First"]
+
+1. e4 e5 2. Nf3 Nc6 1-0
+
+[Event "Second"]
+
+1. e4 e5 2. Ke3 Nc6 0-1
+
+[Event "Third"]
+
+1. d4 d5
+% an escaped line
+2. c4 1/2-1/2
+"#;
+        let games = read_all(pgn);
+        assert_eq!(3, games.len());
+        assert_eq!(Some("First"), games[0].as_ref().unwrap().tag("Event"));
+        let error = games[1].as_ref().unwrap_err();
+        assert_eq!(7, error.line);
+        assert!(error.message.contains("Ke3"));
+        let third = games[2].as_ref().unwrap();
+        assert_eq!(Some("Third"), third.tag("Event"));
+        assert_eq!(vec!["d2d4", "d7d5", "c2c4"], mainline_uci(third));
+    }
+
+    #[test]
+    fn missing_result_ends_at_next_game() {
+        let pgn = r#"[Event "First"]
+
+1. e4 e5
+
+[Event "Second"]
+
+1. d4 0-1
+"#;
+        let games: Vec<_> = read_all(pgn).into_iter().map(|g| g.unwrap()).collect();
+        assert_eq!(2, games.len());
+        assert_eq!(None, games[0].result);
+        assert_eq!(vec!["e2e4", "e7e5"], mainline_uci(&games[0]));
+        assert_eq!(Some("0-1".to_owned()), games[1].result);
+    }
+
+    #[test]
+    fn malformed_tag_reported() {
+        let pgn = "[Event \"Unterminated]\n\n1. e4 *\n\n[Event \"Next\"]\n1. d4 *";
+        let games = read_all(pgn);
+        assert_eq!(2, games.len());
+        assert_eq!(1, games[0].as_ref().unwrap_err().line);
+        assert_eq!(Some("Next"), games[1].as_ref().unwrap().tag("Event"));
+    }
+
+    #[test]
+    fn round_trip_through_writer() {
+        let pgn = r#"[Event "Casual game"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "a"]
+[Black "b"]
+[Result "0-1"]
+[Variant "Standard"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#;
+        let game = read_all(pgn).remove(0).unwrap();
+        assert_eq!(pgn, game.to_game().to_pgn().unwrap());
+    }
+}
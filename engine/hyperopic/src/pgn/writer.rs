@@ -0,0 +1,310 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+
+use crate::constants::side;
+use crate::moves::{Move, Notation};
+use crate::position::Position;
+use crate::search::Score;
+
+/// The export line length recommended by the PGN standard
+pub const DEFAULT_LINE_WIDTH: usize = 80;
+
+const UNKNOWN_RESULT: &str = "*";
+
+/// The seven tag roster which the PGN standard requires every game to have,
+/// unknown values are written as question marks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    /// Formatted as YYYY.MM.DD
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+    /// If absent the result is inferred from the terminal state of the final
+    /// position, a game which is still in progress is written with "*".
+    pub result: Option<String>,
+}
+
+impl Default for PgnTags {
+    fn default() -> Self {
+        PgnTags {
+            event: "?".to_owned(),
+            site: "?".to_owned(),
+            date: "????.??.??".to_owned(),
+            round: "?".to_owned(),
+            white: "?".to_owned(),
+            black: "?".to_owned(),
+            result: None,
+        }
+    }
+}
+
+/// Optional commentary attached to a single move.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveAnnotation {
+    /// The evaluation of the position after the move from white's perspective
+    pub eval: Option<Score>,
+    /// The time remaining on the clock of the side which made the move
+    pub clock: Option<Duration>,
+}
+
+/// A game which can be exported as PGN, consisting of a starting position and
+/// the moves played from it.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: PgnTags,
+    /// Tags written after the seven tag roster, e.g. ("Variant", "Chess960")
+    pub extra_tags: Vec<(String, String)>,
+    pub start: Position,
+    pub moves: Vec<Move>,
+    /// Annotations paired with the moves by index, may be shorter than the moves
+    pub annotations: Vec<MoveAnnotation>,
+    pub line_width: usize,
+}
+
+impl PgnGame {
+    pub fn new(tags: PgnTags, start: Position, moves: Vec<Move>) -> PgnGame {
+        PgnGame {
+            tags,
+            extra_tags: vec![],
+            start,
+            moves,
+            annotations: vec![],
+            line_width: DEFAULT_LINE_WIDTH,
+        }
+    }
+
+    /// Create a game from the history of the given position, the start is
+    /// recovered by unmaking every move in the history.
+    pub fn from_history(tags: PgnTags, position: &Position) -> Result<PgnGame> {
+        let mut start = position.clone();
+        let mut moves = Vec::with_capacity(position.history.len());
+        while !start.history.is_empty() {
+            moves.push(start.unmake()?);
+        }
+        moves.reverse();
+        Ok(PgnGame::new(tags, start, moves))
+    }
+
+    /// The result written in the Result tag and at the end of the movetext.
+    pub fn result(&self) -> Result<String> {
+        if let Some(result) = self.tags.result.as_ref() {
+            return Ok(result.clone());
+        }
+        let mut end = self.start.clone();
+        for m in self.moves.iter() {
+            end.make(m.clone())?;
+        }
+        Ok(end
+            .compute_terminal_state()
+            .filter(|state| state.is_automatic() || state.is_loss())
+            .map(|state| state.result(end.active).to_owned())
+            .unwrap_or(UNKNOWN_RESULT.to_owned()))
+    }
+
+    pub fn to_pgn(&self) -> Result<String> {
+        let result = self.result()?;
+        let mut pgn = String::new();
+        let tags = &self.tags;
+        for (name, value) in [
+            ("Event", tags.event.as_str()),
+            ("Site", tags.site.as_str()),
+            ("Date", tags.date.as_str()),
+            ("Round", tags.round.as_str()),
+            ("White", tags.white.as_str()),
+            ("Black", tags.black.as_str()),
+            ("Result", result.as_str()),
+        ] {
+            pgn.push_str(format_tag(name, value).as_str());
+        }
+        let start_fen = self.start.to_string();
+        if start_fen != Position::default().to_string() {
+            pgn.push_str(format_tag("SetUp", "1").as_str());
+            pgn.push_str(format_tag("FEN", start_fen.as_str()).as_str());
+        }
+        for (name, value) in self.extra_tags.iter() {
+            pgn.push_str(format_tag(name, value).as_str());
+        }
+        pgn.push('\n');
+        let mut tokens = self.movetext_tokens()?;
+        tokens.push(result);
+        pgn.push_str(wrap(tokens, self.line_width).as_str());
+        pgn.push('\n');
+        Ok(pgn)
+    }
+
+    fn movetext_tokens(&self) -> Result<Vec<String>> {
+        let mut tokens = vec![];
+        let mut position = self.start.clone();
+        let mut move_number = 1;
+        // Black moves need their number if they start the game or follow a comment
+        let mut number_black = true;
+        for (i, m) in self.moves.iter().enumerate() {
+            let san = position.format_move(m, Notation::San)?;
+            if position.active == side::W {
+                tokens.push(format!("{}.", move_number));
+            } else if number_black {
+                tokens.push(format!("{}...", move_number));
+            }
+            tokens.push(san);
+            number_black = false;
+            if let Some(comment) = self.annotations.get(i).and_then(format_annotation) {
+                tokens.extend(comment.split(' ').map(|s| s.to_owned()));
+                number_black = true;
+            }
+            if position.active == side::B {
+                move_number += 1;
+            }
+            position.make(m.clone()).map_err(|e| anyhow!("Cannot play {}: {}", m, e))?;
+        }
+        Ok(tokens)
+    }
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn format_annotation(annotation: &MoveAnnotation) -> Option<String> {
+    let mut commands = vec![];
+    if let Some(eval) = annotation.eval {
+        commands.push(match eval {
+            Score::Centipawns(cp) => format!("[%eval {:.2}]", cp as f64 / 100.0),
+            Score::Mate(moves) => format!("[%eval #{}]", moves),
+        })
+    }
+    if let Some(clock) = annotation.clock {
+        let secs = clock.as_secs();
+        commands.push(format!("[%clk {}:{:02}:{:02}]", secs / 3600, (secs / 60) % 60, secs % 60))
+    }
+    if commands.is_empty() {
+        None
+    } else {
+        Some(format!("{{{}}}", commands.join(" ")))
+    }
+}
+
+/// Join the tokens with single spaces, starting a new line whenever the
+/// next token would take the current one beyond the given width.
+fn wrap(tokens: Vec<String>, width: usize) -> String {
+    let mut text = String::new();
+    let mut line_len = 0;
+    for token in tokens {
+        if line_len > 0 && line_len + 1 + token.len() > width {
+            text.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            text.push(' ');
+            line_len += 1;
+        }
+        line_len += token.len();
+        text.push_str(token.as_str());
+    }
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tags() -> PgnTags {
+        PgnTags {
+            event: "Casual game".to_owned(),
+            site: "https://lichess.org/abcdefgh".to_owned(),
+            date: "2023.05.01".to_owned(),
+            round: "-".to_owned(),
+            white: "hyperopic".to_owned(),
+            black: "opponent".to_owned(),
+            result: None,
+        }
+    }
+
+    #[test]
+    fn checkmate_result_inferred() -> Result<()> {
+        let mut position = Position::default();
+        position.play("f2f3 e7e5 g2g4 d8h4")?;
+        let pgn = PgnGame::from_history(tags(), &position)?.to_pgn()?;
+        let expected = r#"[Event "Casual game"]
+[Site "https://lichess.org/abcdefgh"]
+[Date "2023.05.01"]
+[Round "-"]
+[White "hyperopic"]
+[Black "opponent"]
+[Result "0-1"]
+
+1. f3 e5 2. g4 Qh4# 0-1
+"#;
+        assert_eq!(expected, pgn);
+        Ok(())
+    }
+
+    #[test]
+    fn unfinished_game_from_position() -> Result<()> {
+        let mut position = Position::from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1")?;
+        position.play("e8d7 e2e4 d7e6")?;
+        let mut game = PgnGame::from_history(PgnTags::default(), &position)?;
+        game.extra_tags.push(("Variant".to_owned(), "From Position".to_owned()));
+        let expected = r#"[Event "?"]
+[Site "?"]
+[Date "????.??.??"]
+[Round "?"]
+[White "?"]
+[Black "?"]
+[Result "*"]
+[SetUp "1"]
+[FEN "4k3/8/8/8/8/8/4P3/4K3 b - - 0 1"]
+[Variant "From Position"]
+
+1... Kd7 2. e4 Ke6 *
+"#;
+        assert_eq!(expected, game.to_pgn()?);
+        Ok(())
+    }
+
+    #[test]
+    fn annotations() -> Result<()> {
+        let mut position = Position::default();
+        let moves = position.play("e2e4 e7e5 g1f3")?;
+        let mut game = PgnGame::new(tags(), Position::default(), moves);
+        game.tags.result = Some("1-0".to_owned());
+        game.annotations = vec![
+            MoveAnnotation {
+                eval: Some(Score::Centipawns(35)),
+                clock: Some(Duration::from_secs(180)),
+            },
+            MoveAnnotation { eval: Some(Score::Mate(-3)), clock: None },
+        ];
+        let pgn = game.to_pgn()?;
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        assert_eq!(
+            "1. e4 {[%eval 0.35] [%clk 0:03:00]} 1... e5 {[%eval #-3]} 2. Nf3 1-0\n",
+            movetext
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn line_wrapping() -> Result<()> {
+        let mut position = Position::default();
+        position.play("g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1 f6g8")?;
+        let mut game = PgnGame::from_history(tags(), &position)?;
+        game.line_width = 20;
+        let pgn = game.to_pgn()?;
+        let movetext = pgn.split("\n\n").nth(1).unwrap();
+        let expected = "1. Nf3 Nf6 2. Ng1\nNg8 3. Nf3 Nf6 4.\nNg1 Ng8 5. Nf3 Nf6\n6. Ng1 Ng8 *\n";
+        assert_eq!(expected, movetext);
+        assert!(movetext.lines().all(|line| line.len() <= 20));
+        Ok(())
+    }
+
+    #[test]
+    fn illegal_move_rejected() {
+        let moves = Position::default().play("e2e4").unwrap();
+        let mut start = Position::default();
+        start.play("d2d4").unwrap();
+        assert!(PgnGame::new(tags(), start, moves).to_pgn().is_err());
+    }
+}
@@ -49,6 +49,11 @@ impl Phase {
         }
     }
 
+    /// The phase between 0 at the start of the game and 256 with bare kings
+    pub fn value(&self) -> i32 {
+        self.phase
+    }
+
     pub fn phase_progression(&self) -> f32 {
         (self.phase as f32) / (MAX_PHASE as f32)
     }
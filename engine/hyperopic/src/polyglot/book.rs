@@ -0,0 +1,390 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Result};
+
+use crate::constants::{piece_class, square_rank};
+use crate::hash::PRNG;
+use crate::moves::{Move, Moves};
+use crate::polyglot::keys::{polyglot_file, polyglot_key};
+use crate::position::Position;
+use crate::{LookupMoveService, Square};
+
+/// The number of bytes taken by each entry in a book file
+pub const ENTRY_SIZE: usize = 16;
+
+/// How a move is chosen when the book contains several for a position
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum BookSelection {
+    /// Choose randomly with probability proportional to the move weights
+    #[default]
+    WeightedRandom,
+    /// Always choose the move with the largest weight
+    BestWeight,
+}
+
+/// A single record of a book file, the move is left in its encoded form
+/// as it can only be decoded relative to a position.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct BookEntry {
+    pub key: u64,
+    pub raw_move: u16,
+    pub weight: u16,
+    pub learn: u32,
+}
+
+impl BookEntry {
+    pub fn to_bytes(&self) -> [u8; ENTRY_SIZE] {
+        let mut bytes = [0u8; ENTRY_SIZE];
+        bytes[0..8].copy_from_slice(&self.key.to_be_bytes());
+        bytes[8..10].copy_from_slice(&self.raw_move.to_be_bytes());
+        bytes[10..12].copy_from_slice(&self.weight.to_be_bytes());
+        bytes[12..16].copy_from_slice(&self.learn.to_be_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> BookEntry {
+        BookEntry {
+            key: u64::from_be_bytes(bytes[0..8].try_into().unwrap()),
+            raw_move: u16::from_be_bytes(bytes[8..10].try_into().unwrap()),
+            weight: u16::from_be_bytes(bytes[10..12].try_into().unwrap()),
+            learn: u32::from_be_bytes(bytes[12..16].try_into().unwrap()),
+        }
+    }
+}
+
+/// An opening book in the Polyglot format held in memory, entries are
+/// sorted by key so all moves for a position can be found by binary search.
+pub struct PolyglotBook {
+    entries: Vec<BookEntry>,
+    selection: BookSelection,
+    max_ply: usize,
+    prng: PRNG,
+}
+
+impl PolyglotBook {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<PolyglotBook> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| anyhow!("Cannot read {}: {}", path.display(), e))?;
+        PolyglotBook::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<PolyglotBook> {
+        if !bytes.len().is_multiple_of(ENTRY_SIZE) {
+            return Err(anyhow!("Book size {} is not a multiple of {}", bytes.len(), ENTRY_SIZE));
+        }
+        let mut entries: Vec<_> =
+            bytes.chunks_exact(ENTRY_SIZE).map(BookEntry::from_bytes).collect();
+        // Books are written sorted but be lenient, the sort is stable so the
+        // order of moves within a position is preserved
+        entries.sort_by_key(|e| e.key);
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64);
+        Ok(PolyglotBook {
+            entries,
+            selection: BookSelection::default(),
+            max_ply: usize::MAX,
+            prng: PRNG::new(seed | 1),
+        })
+    }
+
+    pub fn with_selection(mut self, selection: BookSelection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Positions with more than this many moves played will not be looked up
+    pub fn with_max_ply(mut self, max_ply: usize) -> Self {
+        self.max_ply = max_ply;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// All entries for the given position key in the order they appear in the book
+    pub fn entries(&self, key: u64) -> &[BookEntry] {
+        let start = self.entries.partition_point(|e| e.key < key);
+        let end = start + self.entries[start..].partition_point(|e| e.key == key);
+        &self.entries[start..end]
+    }
+
+    /// The legal moves the book gives for this position paired with their weights
+    pub fn moves(&self, position: &Position) -> Vec<(Move, u16)> {
+        let entries = self.entries(polyglot_key(position));
+        if entries.is_empty() {
+            return vec![];
+        }
+        let legal = position.moves(&Moves::All);
+        entries
+            .iter()
+            .filter_map(|e| decode_move(&legal, e.raw_move).map(|m| (m, e.weight)))
+            .collect()
+    }
+
+    fn choose(&mut self, moves: Vec<(Move, u16)>) -> Option<Move> {
+        match self.selection {
+            BookSelection::BestWeight => moves
+                .into_iter()
+                .filter(|(_, weight)| *weight > 0)
+                .fold(None, |best: Option<(Move, u16)>, (m, weight)| match best {
+                    Some((_, best_weight)) if best_weight >= weight => best,
+                    _ => Some((m, weight)),
+                })
+                .map(|(m, _)| m),
+            BookSelection::WeightedRandom => {
+                let total = moves.iter().map(|(_, weight)| *weight as u64).sum::<u64>();
+                if total == 0 {
+                    return None;
+                }
+                choose_weighted(moves, self.prng.rand64() % total)
+            }
+        }
+    }
+}
+
+impl LookupMoveService for PolyglotBook {
+    fn lookup(&mut self, position: Position) -> Result<Option<Move>> {
+        if position.history.len() > self.max_ply {
+            return Ok(None);
+        }
+        let moves = self.moves(&position);
+        Ok(self.choose(moves))
+    }
+}
+
+/// Write the given entries as a book file, they are sorted by key as the
+/// format requires and then by descending weight which is the convention.
+pub fn write_book<W: Write>(mut entries: Vec<BookEntry>, writer: &mut W) -> Result<()> {
+    entries.sort_by(|a, b| a.key.cmp(&b.key).then(b.weight.cmp(&a.weight)));
+    for entry in entries {
+        writer.write_all(&entry.to_bytes())?;
+    }
+    Ok(())
+}
+
+/// Encode a move in the book format, the inverse of decoding it against a
+/// position so castling is written as the king moving onto its rook.
+pub fn encode_move(m: &Move) -> u16 {
+    let (from, dest, promoted) = match m {
+        Move::Normal { from, dest, .. } | Move::Enpassant { from, dest, .. } => (*from, *dest, 0),
+        Move::Promote { from, dest, promoted, .. } => (*from, *dest, piece_class(*promoted)),
+        Move::Castle { king, rook, .. } => (*king, *rook, 0),
+        Move::Null => return 0,
+    };
+    ((promoted as u16) << 12) | (encode_square(from) << 6) | encode_square(dest)
+}
+
+fn encode_square(square: Square) -> u16 {
+    (8 * square_rank(square) + polyglot_file(square)) as u16
+}
+
+/// Choose the move whose cumulative weight range contains the given value
+fn choose_weighted(moves: Vec<(Move, u16)>, value: u64) -> Option<Move> {
+    let mut sum = 0u64;
+    for (m, weight) in moves {
+        sum += weight as u64;
+        if value < sum {
+            return Some(m);
+        }
+    }
+    None
+}
+
+/// Squares are encoded as 3 bits of file from a to h then 3 bits of rank
+fn decode_square(bits: u16) -> Square {
+    let file = (bits & 7) as usize;
+    let rank = ((bits >> 3) & 7) as usize;
+    8 * rank + 7 - file
+}
+
+/// Decode a book move by matching it against the legal moves in the position.
+/// Castling is encoded as the king moving to the square of its rook and the
+/// promotion codes 1 to 4 coincide with our knight to queen classes.
+fn decode_move(legal: &[Move], raw: u16) -> Option<Move> {
+    let dest = decode_square(raw);
+    let from = decode_square(raw >> 6);
+    let promoted = ((raw >> 12) & 7) as usize;
+    legal
+        .iter()
+        .find(|m| match m {
+            Move::Normal { from: f, dest: d, .. } | Move::Enpassant { from: f, dest: d, .. } => {
+                promoted == 0 && *f == from && *d == dest
+            }
+            Move::Promote { from: f, dest: d, promoted: p, .. } => {
+                *f == from && *d == dest && piece_class(*p) == promoted
+            }
+            Move::Castle { king, rook, .. } => *king == from && *rook == dest,
+            Move::Null => false,
+        })
+        .cloned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{encode_move, write_book, BookEntry, BookSelection, PolyglotBook};
+    use crate::constants::square::*;
+    use crate::constants::{corner, piece};
+    use crate::moves::Move;
+    use crate::polyglot::keys::polyglot_key;
+    use crate::position::Position;
+    use crate::LookupMoveService;
+
+    const START_KEY: u64 = 0x463b96181691fc9c;
+
+    fn encode(from: &str, dest: &str, promoted: u16) -> u16 {
+        let square = |s: &str| {
+            let file = s.as_bytes()[0] - b'a';
+            let rank = s.as_bytes()[1] - b'1';
+            (8 * rank + file) as u16
+        };
+        (promoted << 12) | (square(from) << 6) | square(dest)
+    }
+
+    fn book(entries: &[(u64, u16, u16)]) -> PolyglotBook {
+        let bytes: Vec<u8> = entries
+            .iter()
+            .flat_map(|(key, raw_move, weight)| {
+                let mut bytes = key.to_be_bytes().to_vec();
+                bytes.extend(raw_move.to_be_bytes());
+                bytes.extend(weight.to_be_bytes());
+                bytes.extend(0u32.to_be_bytes());
+                bytes
+            })
+            .collect();
+        PolyglotBook::from_bytes(&bytes).unwrap()
+    }
+
+    fn e4() -> Move {
+        Move::Normal { moving: piece::WP, from: E2, dest: E4, capture: None }
+    }
+
+    fn d4() -> Move {
+        Move::Normal { moving: piece::WP, from: D2, dest: D4, capture: None }
+    }
+
+    fn start_book() -> PolyglotBook {
+        book(&[
+            (START_KEY + 1, encode("g1", "f3", 0), 100),
+            (START_KEY, encode("d2", "d4", 0), 3),
+            (START_KEY, encode("e2", "e4", 0), 7),
+            (START_KEY - 1, encode("c2", "c4", 0), 100),
+            // Illegal moves are ignored
+            (START_KEY, encode("e2", "e5", 0), 100),
+        ])
+    }
+
+    #[test]
+    fn invalid_size() {
+        assert!(PolyglotBook::from_bytes(&[0u8; 17]).is_err());
+    }
+
+    #[test]
+    fn entries_for_key() {
+        let book = start_book();
+        assert_eq!(5, book.len());
+        assert_eq!(3, book.entries(START_KEY).len());
+        assert_eq!(0, book.entries(START_KEY + 2).len());
+        assert_eq!(vec![(d4(), 3), (e4(), 7)], book.moves(&Position::default()));
+    }
+
+    #[test]
+    fn best_weight() {
+        let mut book = start_book().with_selection(BookSelection::BestWeight);
+        assert_eq!(Some(e4()), book.lookup(Position::default()).unwrap());
+    }
+
+    #[test]
+    fn weighted_random() {
+        let mut book = start_book();
+        for _ in 0..20 {
+            let chosen = book.lookup(Position::default()).unwrap();
+            assert!(chosen == Some(e4()) || chosen == Some(d4()));
+        }
+        let moves = vec![(d4(), 3), (e4(), 7)];
+        assert_eq!(Some(d4()), super::choose_weighted(moves.clone(), 2));
+        assert_eq!(Some(e4()), super::choose_weighted(moves.clone(), 3));
+        assert_eq!(Some(e4()), super::choose_weighted(moves, 9));
+    }
+
+    #[test]
+    fn zero_weights_not_chosen() {
+        let entries = [(START_KEY, encode("e2", "e4", 0), 0)];
+        assert_eq!(1, book(&entries).moves(&Position::default()).len());
+        for selection in [BookSelection::WeightedRandom, BookSelection::BestWeight] {
+            let mut book = book(&entries).with_selection(selection);
+            assert_eq!(None, book.lookup(Position::default()).unwrap());
+        }
+    }
+
+    #[test]
+    fn max_ply() {
+        let position: Position = "e2e4 e7e5".parse().unwrap();
+        let key = polyglot_key(&position);
+        let entries = [(key, encode("g1", "f3", 0), 1)];
+        let nf3 = Move::Normal { moving: piece::WN, from: G1, dest: F3, capture: None };
+        assert_eq!(Some(nf3), book(&entries).with_max_ply(2).lookup(position.clone()).unwrap());
+        assert_eq!(None, book(&entries).with_max_ply(1).lookup(position).unwrap());
+    }
+
+    #[test]
+    fn castling_decoded() {
+        let position = Position::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let key = polyglot_key(&position);
+        let mut book = book(&[(key, encode("e1", "h1", 0), 1), (key, encode("e1", "a1", 0), 1)]);
+        assert_eq!(
+            vec![
+                (Move::Castle { corner: corner::WK, king: E1, rook: H1 }, 1),
+                (Move::Castle { corner: corner::WQ, king: E1, rook: A1 }, 1)
+            ],
+            book.moves(&position)
+        );
+        assert!(book.lookup(position).unwrap().is_some());
+    }
+
+    #[test]
+    fn promotion_decoded() {
+        let position = Position::from_fen("7k/1P6/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let key = polyglot_key(&position);
+        let book = book(&[(key, encode("b7", "b8", 1), 1), (key, encode("b7", "b8", 4), 1)]);
+        assert_eq!(
+            vec![
+                (Move::Promote { from: B7, dest: B8, promoted: piece::WN, capture: None }, 1),
+                (Move::Promote { from: B7, dest: B8, promoted: piece::WQ, capture: None }, 1)
+            ],
+            book.moves(&position)
+        );
+    }
+
+    #[test]
+    fn encoding_round_trip() {
+        let position = Position::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let legal = position.moves(&crate::moves::Moves::All);
+        for m in legal.iter() {
+            assert_eq!(Some(m.clone()), super::decode_move(&legal, encode_move(m)), "{}", m);
+        }
+        let castle = Move::Castle { corner: corner::WQ, king: E1, rook: A1 };
+        assert_eq!(encode("e1", "a1", 0), encode_move(&castle));
+    }
+
+    #[test]
+    fn written_book_readable() {
+        let entries = vec![
+            BookEntry { key: START_KEY, raw_move: encode("d2", "d4", 0), weight: 3, learn: 0 },
+            BookEntry { key: START_KEY + 1, raw_move: encode("g1", "f3", 0), weight: 1, learn: 0 },
+            BookEntry { key: START_KEY, raw_move: encode("e2", "e4", 0), weight: 7, learn: 9 },
+        ];
+        let mut bytes = vec![];
+        write_book(entries.clone(), &mut bytes).unwrap();
+        assert_eq!(48, bytes.len());
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(&[entries[2], entries[0]], book.entries(START_KEY));
+        assert_eq!(vec![(e4(), 7), (d4(), 3)], book.moves(&Position::default()));
+    }
+}
@@ -0,0 +1,7 @@
+pub use crate::polyglot::book::{
+    encode_move, write_book, BookEntry, BookSelection, PolyglotBook, ENTRY_SIZE,
+};
+pub use crate::polyglot::keys::polyglot_key;
+
+mod book;
+mod keys;
@@ -5,13 +5,12 @@ use crate::{
 use std::cmp::{max, min};
 
 use crate::board::{board_moves, control, cord, iter, union_boards};
-use crate::constants::boards::{ADJACENT_FILES, RANKS};
+use crate::constants::boards::{ADJACENT_FILES, LIGHT_SQUARES, RANKS};
 use crate::constants::piece::*;
 use crate::constants::side::*;
-use crate::constants::square::*;
 use crate::constants::{
-    class, corner, create_piece, first_square, in_board, intersects, is_superset, lift,
-    piece_class, piece_side, reflect_piece, reflect_side, side, square_file, square_rank,
+    class, corner, corner_side, create_piece, first_square, in_board, intersects, is_superset,
+    lift, piece_class, piece_side, reflect_piece, reflect_side, side, square_file, square_rank,
 };
 use anyhow::{anyhow, Result};
 use rustc_hash::FxHashMap;
@@ -24,8 +23,50 @@ use rustc_hash::FxHashMap;
 /// drawn.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
 pub enum TerminalState {
-    Draw,
-    Loss,
+    Checkmate,
+    Stalemate,
+    Repetition(DrawRule),
+    FiftyMoves(DrawRule),
+    InsufficientMaterial,
+}
+
+/// Distinguishes the draw conditions which a player must claim (threefold
+/// repetition, fifty moves) from those which end the game automatically
+/// (fivefold repetition, seventy five moves).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
+pub enum DrawRule {
+    Claimable,
+    Automatic,
+}
+
+impl TerminalState {
+    /// Is the game lost for the side to move.
+    pub fn is_loss(&self) -> bool {
+        matches!(self, TerminalState::Checkmate)
+    }
+
+    pub fn is_draw(&self) -> bool {
+        !self.is_loss()
+    }
+
+    /// Does this state end the game without either player needing to claim it.
+    pub fn is_automatic(&self) -> bool {
+        !matches!(
+            self,
+            TerminalState::Repetition(DrawRule::Claimable)
+                | TerminalState::FiftyMoves(DrawRule::Claimable)
+        )
+    }
+
+    /// The PGN result tag value for a game ending in this state with the
+    /// given side to move.
+    pub fn result(&self, active: Side) -> &str {
+        match (self.is_loss(), active) {
+            (false, _) => "1/2-1/2",
+            (true, W) => "0-1",
+            (true, _) => "1-0",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -34,6 +75,7 @@ pub struct Position {
     pub piece_locs: SquareMap<Option<Piece>>,
     pub side_boards: SideMap<Board>,
     pub castling_rights: CornerMap<bool>,
+    pub castling_details: CornerMap<CastlingDetails>,
     pub active: Side,
     pub enpassant: Option<Square>,
     pub clock: usize,
@@ -99,6 +141,12 @@ impl Position {
             clock,
             piece_locs,
             castling_rights,
+            castling_details: std::array::from_fn(|corner| {
+                castling_rights[corner]
+                    .then(|| infer_castling_details(corner, &piece_locs))
+                    .flatten()
+                    .unwrap_or(CASTLING_DETAILS[corner])
+            }),
             key: 0,
             history: vec![],
             passive_control: 0,
@@ -139,8 +187,8 @@ impl Position {
                 capture.map(|p| self.unset_piece(p, dest));
                 self.unset_piece(moving, from);
                 self.set_piece(moving, dest);
-                self.remove_rights(rights_removed(from));
-                self.remove_rights(rights_removed(dest));
+                self.remove_rights(from);
+                self.remove_rights(dest);
                 let is_pawn = piece_class(moving) == class::P;
                 self.clock = if capture.is_some() || is_pawn { 0 } else { self.clock + 1 };
                 if is_pawn && max(from, dest) - min(from, dest) == 16 {
@@ -152,7 +200,7 @@ impl Position {
             Promote { from, dest, promoted, capture } => {
                 capture.map(|p| self.unset_piece(p, dest));
                 let moved = create_piece(piece_side(promoted), class::P);
-                self.remove_rights(rights_removed(dest));
+                self.remove_rights(dest);
                 self.unset_piece(moved, from);
                 self.set_piece(promoted, dest);
                 self.clock = 0;
@@ -165,11 +213,9 @@ impl Position {
                 self.set_piece(moving, dest);
                 self.clock = 0;
             }
-            Castle { corner } => {
-                let details = &CASTLING_DETAILS[corner];
-                let (r_source, r_target) = details.rook_line;
-                let (k_source, k_target) = details.king_line;
-                self.remove_rights(rights_removed(k_source));
+            Castle { corner, king: k_source, rook: r_source } => {
+                let (k_target, r_target) = castle_targets(corner);
+                self.remove_rights(k_source);
                 let side = corner / 2;
                 let rook = create_piece(side, class::R);
                 let king = create_piece(side, class::K);
@@ -219,17 +265,16 @@ impl Position {
                 self.set_piece(taken, capture);
                 self.set_piece(moving, from);
             }
-            &Castle { corner } => {
-                let details = &CASTLING_DETAILS[corner];
-                let (r_source, r_target) = details.rook_line;
-                let (k_source, k_target) = details.king_line;
+            &Castle { corner, king: k_source, rook: r_source } => {
+                let (k_target, r_target) = castle_targets(corner);
                 let side = corner / 2;
                 let rook = create_piece(side, class::R);
                 let king = create_piece(side, class::K);
-                self.set_piece(rook, r_source);
-                self.set_piece(king, k_source);
+                // In Chess960 the source and target squares can overlap so clear the targets first
                 self.unset_piece(rook, r_target);
                 self.unset_piece(king, k_target);
+                self.set_piece(rook, r_source);
+                self.set_piece(king, k_source);
             }
         };
         self.castling_rights = state.castling_rights;
@@ -264,13 +309,16 @@ impl Position {
         self.piece_locs[square] = None;
     }
 
-    fn remove_rights(&mut self, corners: &[Corner]) {
-        corners.iter().for_each(|&c| {
-            if self.castling_rights[c] {
+    /// Remove the rights for every corner whose king or rook starts on the given square
+    fn remove_rights(&mut self, square: Square) {
+        for c in 0..4 {
+            let details = &self.castling_details[c];
+            let affected = details.king_line.0 == square || details.rook_line.0 == square;
+            if affected && self.castling_rights[c] {
                 self.castling_rights[c] = false;
                 self.key ^= hash::corner(c);
             }
-        })
+        }
     }
 
     pub fn create_discards(&self) -> Discards {
@@ -298,11 +346,18 @@ impl Position {
     }
 
     pub fn compute_terminal_state(&self) -> Option<TerminalState> {
+        self.check_no_legal_moves()
+            .or_else(|| self.check_insufficient_material())
+            .or_else(|| self.check_clock_limit())
+            .or_else(|| self.check_repetitions())
+    }
+
+    fn check_no_legal_moves(&self) -> Option<TerminalState> {
         let king = create_piece(self.active, class::K);
         let king_loc = self.piece_boards[king].trailing_zeros() as usize;
         if king_loc == 64 {
             // Treat king not on the board as a loss
-            return Some(TerminalState::Loss);
+            return Some(TerminalState::Checkmate);
         }
         let passive_control = self.passive_control;
         let friendly = self.side_boards[self.active];
@@ -313,7 +368,7 @@ impl Position {
             None
         } else if in_board(passive_control, king_loc) {
             // If in check delegate to move gen
-            Some(TerminalState::Loss).filter(|_| self.moves(&Moves::All).is_empty())
+            Some(TerminalState::Checkmate).filter(|_| self.moves(&Moves::All).is_empty())
         } else {
             // In most positions where king can't move but not in check there will be a piece
             // definitely not pinned which can move
@@ -328,10 +383,25 @@ impl Position {
                 }
             }
             // Otherwise delegate to move gen to be sure
-            Some(TerminalState::Draw).filter(|_| self.moves(&Moves::All).is_empty())
+            Some(TerminalState::Stalemate).filter(|_| self.moves(&Moves::All).is_empty())
+        }
+    }
+
+    /// Neither side can deliver mate by any sequence of legal moves, i.e. only
+    /// kings remain with at most a single minor piece or only bishops all
+    /// standing on squares of the same colour.
+    fn check_insufficient_material(&self) -> Option<TerminalState> {
+        let heavy = [WP, WR, WQ, BP, BR, BQ].iter().fold(0u64, |a, &p| a | self.piece_boards[p]);
+        if heavy != 0 {
+            return None;
         }
-        .or(self.check_clock_limit())
-        .or(self.check_repetitions())
+        let knights = self.piece_boards[WN] | self.piece_boards[BN];
+        let bishops = self.piece_boards[WB] | self.piece_boards[BB];
+        let minor_count = (knights | bishops).count_ones();
+        let same_colour_bishops = knights == 0
+            && (is_superset(LIGHT_SQUARES, bishops) || !intersects(LIGHT_SQUARES, bishops));
+        Some(TerminalState::InsufficientMaterial)
+            .filter(|_| minor_count <= 1 || same_colour_bishops)
     }
 
     fn check_repetitions(&self) -> Option<TerminalState> {
@@ -346,16 +416,23 @@ impl Position {
             .take_while(|(_, m)| m.is_repeatable())
             .map(|(discards, _)| discards.key);
 
+        let mut max_count = 1;
         for p in positions {
-            if 3 == *key_counts.entry(p).and_modify(|v| *v += 1).or_insert(1) {
-                return Some(TerminalState::Draw);
+            let count = key_counts.entry(p).and_modify(|v| *v += 1).or_insert(1);
+            max_count = max(max_count, *count);
+            if max_count == 5 {
+                return Some(TerminalState::Repetition(DrawRule::Automatic));
             }
         }
-        None
+        Some(TerminalState::Repetition(DrawRule::Claimable)).filter(|_| max_count >= 3)
     }
 
     fn check_clock_limit(&self) -> Option<TerminalState> {
-        Some(TerminalState::Draw).filter(|_| self.clock >= 100)
+        match self.clock {
+            0..=99 => None,
+            100..=149 => Some(TerminalState::FiftyMoves(DrawRule::Claimable)),
+            _ => Some(TerminalState::FiftyMoves(DrawRule::Automatic)),
+        }
     }
 
     pub fn compute_discoveries_on(&self, square: Square) -> Result<ConstrainedPieces> {
@@ -586,7 +663,7 @@ impl Position {
     ) -> impl Iterator<Item = Move> + 'a {
         self.castling_rights.iter().enumerate().filter(|(_, &allowed)| allowed).filter_map(
             move |(corner, _)| {
-                let details = &CASTLING_DETAILS[corner];
+                let details = &self.castling_details[corner];
                 let king = create_piece(self.active, class::K);
                 let rook = create_piece(self.active, class::R);
                 let occupied = union_boards(&self.side_boards);
@@ -594,16 +671,24 @@ impl Position {
                     && !intersects(occupied, details.no_piece)
                     && self.piece_locs[details.king_line.0] == Some(king)
                     && self.piece_locs[details.rook_line.0] == Some(rook)
+                    && !self.castle_discovers_attack(details, occupied)
                     && match mode {
                         CastlingMoveMode::All => true,
                         CastlingMoveMode::None => false,
-                        CastlingMoveMode::Checking => intersects(
-                            control(rook, details.rook_line.1, occupied),
-                            self.piece_boards[reflect_piece(king)],
-                        ),
+                        CastlingMoveMode::Checking => {
+                            let (k_source, k_target) = details.king_line;
+                            let (r_source, r_target) = details.rook_line;
+                            let after = (occupied & !lift(k_source) & !lift(r_source))
+                                | lift(k_target)
+                                | lift(r_target);
+                            intersects(
+                                control(rook, r_target, after),
+                                self.piece_boards[reflect_piece(king)],
+                            )
+                        }
                     }
                 {
-                    Some(Castle { corner })
+                    Some(Castle { corner, king: details.king_line.0, rook: details.rook_line.0 })
                 } else {
                     None
                 }
@@ -648,7 +733,10 @@ impl Position {
             .chain(iter(enpassant).filter_map(move |from| {
                 let dest = self.enpassant.unwrap();
                 let capture = if is_white { dest - 8 } else { dest + 8 };
-                if in_board(constraints[from], capture)
+                // The pawn may either block or capture a checking piece, or be pinned along
+                // a diagonal which the destination stays on
+                let constraint = constraints[from];
+                if (in_board(constraint, capture) || in_board(constraint, dest))
                     && self.enpassant_doesnt_discover_attack(from, capture)
                 {
                     Some(Enpassant { side: active, from, dest, capture })
@@ -676,6 +764,20 @@ impl Position {
         })
     }
 
+    /// In Chess960 the castling rook can shield the king target from an enemy
+    /// rook or queen on the back rank, which only becomes visible once it moves.
+    fn castle_discovers_attack(&self, details: &CastlingDetails, occupied: Board) -> bool {
+        let (k_source, k_target) = details.king_line;
+        let (r_source, r_target) = details.rook_line;
+        let passive = reflect_side(self.active);
+        let rook = create_piece(passive, class::R);
+        let attackers = (self.piece_boards[rook]
+            | self.piece_boards[create_piece(passive, class::Q)])
+            & RANKS[square_rank(k_target)];
+        let occupied = (occupied & !lift(k_source) & !lift(r_source)) | lift(r_target);
+        iter(attackers).any(|sq| in_board(control(rook, sq, occupied), k_target))
+    }
+
     fn create_normal_moves(
         &self,
         moving: Piece,
@@ -702,16 +804,31 @@ impl Position {
     }
 }
 
-fn rights_removed<'a>(square: Square) -> &'a [Corner] {
-    match square {
-        H1 => &[corner::WK],
-        E1 => &[corner::WK, corner::WQ],
-        A1 => &[corner::WQ],
-        H8 => &[corner::BK],
-        E8 => &[corner::BK, corner::BQ],
-        A8 => &[corner::BQ],
-        _ => &[],
-    }
+/// The squares the king and rook finish on after castling through the given
+/// corner, these are the same in standard chess and Chess960.
+pub fn castle_targets(corner: Corner) -> (Square, Square) {
+    let details = &CASTLING_DETAILS[corner];
+    (details.king_line.1, details.rook_line.1)
+}
+
+/// Find the castling details for a corner from the king and the outermost
+/// rook on that side of it, as implied by the KQkq castling rights notation.
+fn infer_castling_details(
+    corner: Corner,
+    piece_locs: &SquareMap<Option<Piece>>,
+) -> Option<CastlingDetails> {
+    let side = corner_side(corner);
+    let back_rank = if side == W { 0..8 } else { 56..64 };
+    let king = create_piece(side, class::K);
+    let rook = create_piece(side, class::R);
+    let king_loc = back_rank.clone().find(|&sq| piece_locs[sq] == Some(king))?;
+    let is_kingside = matches!(corner, corner::WK | corner::BK);
+    let rook_loc = if is_kingside {
+        (back_rank.start..king_loc).find(|&sq| piece_locs[sq] == Some(rook))
+    } else {
+        (king_loc + 1..back_rank.end).rev().find(|&sq| piece_locs[sq] == Some(rook))
+    }?;
+    Some(CastlingDetails::new(corner, king_loc, rook_loc))
 }
 
 #[rustfmt::skip]
@@ -745,9 +862,31 @@ pub const CASTLING_DETAILS: CornerMap<CastlingDetails> = {
     ]
 };
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct CastlingDetails {
     pub king_line: (Square, Square),
     pub rook_line: (Square, Square),
     pub no_piece: Board,
     pub no_control: Board,
 }
+
+impl CastlingDetails {
+    /// Compute the details for castling through the given corner with the king
+    /// and rook starting on the given squares of the back rank.
+    pub fn new(corner: Corner, king: Square, rook: Square) -> CastlingDetails {
+        let (king_target, rook_target) = castle_targets(corner);
+        let travelled = cord(king, king_target) | cord(rook, rook_target);
+        CastlingDetails {
+            king_line: (king, king_target),
+            rook_line: (rook, rook_target),
+            no_piece: travelled & !lift(king) & !lift(rook),
+            no_control: cord(king, king_target),
+        }
+    }
+
+    /// Whether the king and rook start on their standard chess squares
+    pub fn is_standard(&self, corner: Corner) -> bool {
+        let standard = &CASTLING_DETAILS[corner];
+        self.king_line == standard.king_line && self.rook_line == standard.rook_line
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use crate::search::search::Context;
@@ -5,8 +7,9 @@ use crate::search::search::Context;
 /// Represents some object which can determine whether a search should be
 /// terminated given certain context about the current state. Implementations
 /// are provided for Duration (caps the search based on time elapsed), for
-/// usize which represents a maximum search depth and for a pair (Duration, usize)
-/// which combines both checks.
+/// usize which represents a maximum search depth, for a node limit, for a flag which can be raised
+/// from another thread to stop the search, for the unit type which never ends the
+/// search and for any pair of ends which combines both checks.
 pub trait SearchEnd {
     fn should_end(&self, ctx: &Context) -> bool;
 }
@@ -23,8 +26,77 @@ impl SearchEnd for usize {
     }
 }
 
-impl SearchEnd for (Duration, usize) {
+/// Ends the search once the given number of nodes have been visited, with a
+/// single thread this gives a reproducible search independent of hardware.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct NodeLimit(pub u64);
+
+impl SearchEnd for NodeLimit {
+    fn should_end(&self, ctx: &Context) -> bool {
+        ctx.nodes >= self.0
+    }
+}
+
+impl SearchEnd for () {
+    fn should_end(&self, _ctx: &Context) -> bool {
+        false
+    }
+}
+
+impl SearchEnd for AtomicBool {
+    fn should_end(&self, _ctx: &Context) -> bool {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl<E: SearchEnd + ?Sized> SearchEnd for Arc<E> {
+    fn should_end(&self, ctx: &Context) -> bool {
+        self.as_ref().should_end(ctx)
+    }
+}
+
+impl<E: SearchEnd + ?Sized> SearchEnd for &E {
+    fn should_end(&self, ctx: &Context) -> bool {
+        (*self).should_end(ctx)
+    }
+}
+
+impl<A: SearchEnd, B: SearchEnd> SearchEnd for (A, B) {
     fn should_end(&self, ctx: &Context) -> bool {
         self.0.should_end(ctx) || self.1.should_end(ctx)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::NodeLimit;
+    use crate::position::Position;
+    use crate::search::{search, MultiPv, SearchOutcome, SearchParameters, TranspositionsImpl};
+
+    fn search_nodes(position: &Position, limit: u64) -> SearchOutcome {
+        search(
+            position.clone().into(),
+            SearchParameters {
+                end: NodeLimit(limit),
+                table: &mut TranspositionsImpl::new(10_000),
+                observer: &mut (),
+                multi_pv: MultiPv::default(),
+                tablebase: None,
+            },
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn node_limit_is_reproducible() {
+        let position: Position = "1. e4 c5 2. Nf3 d6 3. d4 cxd4".parse().unwrap();
+        let first = search_nodes(&position, 20_000);
+        let second = search_nodes(&position, 20_000);
+        assert_eq!(first.statistics, second.statistics);
+        assert_eq!(first.optimal_path, second.optimal_path);
+        assert!(first.depth > 1);
+        // The limit is only checked on entering the main search so quiescence may overshoot
+        assert!(first.statistics.nodes >= 20_000);
+        assert!(search_nodes(&position, 5_000).statistics.nodes < first.statistics.nodes);
+    }
+}
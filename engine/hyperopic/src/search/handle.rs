@@ -0,0 +1,193 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+
+use crate::node::TreeNode;
+use crate::search::end::SearchEnd;
+use crate::search::observer::SearchObserver;
+use crate::search::search::Context;
+use crate::search::{
+    search_parallel, ConcurrentTranspositions, MultiPv, ParallelSearchParameters, SearchOutcome,
+};
+use crate::syzygy::Tablebase;
+
+/// Search end shared between a background search and its handle, the search
+/// can be stopped or have its deadline moved while it is running.
+#[derive(Debug)]
+pub struct SearchControl {
+    origin: Instant,
+    stop: AtomicBool,
+    /// Milliseconds since the origin, no deadline is represented by the max value
+    deadline: AtomicU64,
+}
+
+impl SearchControl {
+    pub fn new(deadline: Option<Duration>) -> SearchControl {
+        let control = SearchControl {
+            origin: Instant::now(),
+            stop: AtomicBool::new(false),
+            deadline: AtomicU64::new(u64::MAX),
+        };
+        if let Some(deadline) = deadline {
+            control.set_deadline(deadline)
+        }
+        control
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed)
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn has_deadline(&self) -> bool {
+        self.deadline.load(Ordering::Relaxed) != u64::MAX
+    }
+
+    /// Set the deadline to be the given duration from now
+    pub fn set_deadline(&self, deadline: Duration) {
+        // Saturate below the max value so a huge deadline still counts as being set
+        let millis = self.origin.elapsed().saturating_add(deadline).as_millis();
+        self.deadline.store(millis.min(u64::MAX as u128 - 1) as u64, Ordering::Relaxed)
+    }
+}
+
+impl SearchEnd for SearchControl {
+    fn should_end(&self, _ctx: &Context) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self.origin.elapsed().as_millis() as u64 > self.deadline.load(Ordering::Relaxed)
+    }
+}
+
+/// Handle on a search running on its own thread. Stopping the search or
+/// reaching its deadline ends it at the next node visited and the outcome of
+/// the deepest completed iteration is then available by joining.
+pub struct SearchHandle<T = SearchOutcome> {
+    control: Arc<SearchControl>,
+    thread: JoinHandle<Result<T>>,
+}
+
+impl<T: Send + 'static> SearchHandle<T> {
+    /// Run the given computation on a new thread, it should terminate its
+    /// search when the given control signals it should end.
+    pub fn spawn(
+        control: Arc<SearchControl>,
+        compute: impl FnOnce() -> Result<T> + Send + 'static,
+    ) -> SearchHandle<T> {
+        SearchHandle { control, thread: std::thread::spawn(compute) }
+    }
+
+    /// Signal the search to end as soon as possible
+    pub fn stop(&self) {
+        self.control.stop()
+    }
+
+    /// The move we were pondering on was played so the search now has the
+    /// given amount of time from this point to complete.
+    pub fn ponderhit(&self, new_deadline: Duration) {
+        self.control.set_deadline(new_deadline)
+    }
+
+    /// The shared control, useful for stopping the search after the handle
+    /// has been given up to a thread waiting to join.
+    pub fn control(&self) -> Arc<SearchControl> {
+        self.control.clone()
+    }
+
+    /// Wait for the search to end and retrieve the result
+    pub fn join(self) -> Result<T> {
+        self.thread.join().map_err(|_| anyhow!("Search thread panicked"))?
+    }
+}
+
+pub struct BackgroundSearchParameters<E: SearchEnd> {
+    pub end: E,
+    /// Time from now after which the search will end, none implies the search
+    /// runs until stopped, the end condition is met or the handle is given a
+    /// deadline via ponderhit.
+    pub deadline: Option<Duration>,
+    pub table: Arc<ConcurrentTranspositions>,
+    pub helpers: usize,
+    pub observer: Box<dyn SearchObserver + Send>,
+    pub multi_pv: MultiPv,
+    pub tablebase: Option<Arc<dyn Tablebase>>,
+}
+
+/// API function for executing search on a background thread, a handle on
+/// the running search is returned immediately.
+pub fn spawn<E: SearchEnd + Send + Sync + 'static>(
+    node: TreeNode,
+    parameters: BackgroundSearchParameters<E>,
+) -> SearchHandle {
+    let BackgroundSearchParameters {
+        end,
+        deadline,
+        table,
+        helpers,
+        mut observer,
+        multi_pv,
+        tablebase,
+    } = parameters;
+    let control = Arc::new(SearchControl::new(deadline));
+    let cloned_control = control.clone();
+    SearchHandle::spawn(control, move || {
+        search_parallel(
+            node,
+            ParallelSearchParameters {
+                end: (end, cloned_control),
+                table: table.as_ref(),
+                helpers,
+                observer: observer.as_mut(),
+                multi_pv,
+                tablebase: tablebase.as_deref(),
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{spawn, BackgroundSearchParameters};
+    use crate::position::Position;
+    use crate::search::{ConcurrentTranspositions, MultiPv};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn parameters(deadline: Option<Duration>) -> BackgroundSearchParameters<()> {
+        BackgroundSearchParameters {
+            end: (),
+            deadline,
+            table: Arc::new(ConcurrentTranspositions::new(10_000)),
+            helpers: 1,
+            observer: Box::new(()),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
+        }
+    }
+
+    #[test]
+    fn stop_returns_completed_iteration() {
+        let position: Position = "1. e4 e5 2. Nf3 Nc6".parse().unwrap();
+        let handle = spawn(position.into(), parameters(None));
+        handle.stop();
+        let outcome = handle.join().unwrap();
+        assert!(outcome.depth >= 1);
+        assert!(!outcome.optimal_path.is_empty());
+    }
+
+    #[test]
+    fn ponderhit_sets_deadline() {
+        let position: Position = "1. d4 d5 2. c4 e6".parse().unwrap();
+        let handle = spawn(position.into(), parameters(None));
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(!handle.control().has_deadline());
+        handle.ponderhit(Duration::from_millis(50));
+        assert!(handle.control().has_deadline());
+        assert!(handle.join().unwrap().depth >= 1);
+    }
+}
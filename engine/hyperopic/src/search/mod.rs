@@ -1,3 +1,4 @@
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 
 use serde::ser::SerializeStruct;
@@ -6,18 +7,27 @@ use serde::Serializer;
 use anyhow::{anyhow, Result};
 use end::SearchEnd;
 
-use crate::moves::Move;
+use crate::moves::{Move, Moves};
 use crate::node;
 use crate::node::TreeNode;
+pub use crate::search::handle::{spawn, BackgroundSearchParameters, SearchControl, SearchHandle};
 use crate::search::moves::MoveGenerator;
+use crate::search::observer::{IterationSummary, SearchObserver};
 use crate::search::pv::PrincipleVariation;
+pub use crate::search::score::Score;
 use crate::search::search::{Context, SearchResponse, TreeSearcher};
-pub use crate::search::table::{NodeType, TableEntry, Transpositions, TranspositionsImpl};
+pub use crate::search::table::{
+    ConcurrentTranspositions, NodeType, TableEntry, Transpositions, TranspositionsImpl,
+};
+use crate::syzygy::Tablebase;
 
 pub mod end;
+pub mod handle;
 mod moves;
+pub mod observer;
 mod pv;
 pub mod quiescent;
+pub mod score;
 pub mod search;
 mod table;
 
@@ -30,12 +40,125 @@ pub fn search<E: SearchEnd, T: Transpositions>(
     node: TreeNode,
     parameters: SearchParameters<E, T>,
 ) -> Result<SearchOutcome> {
-    Search { node, end: parameters.end, transpositions: parameters.table }.search()
+    Search {
+        node,
+        end: parameters.end,
+        transpositions: parameters.table,
+        observer: parameters.observer,
+        start_depth: 1,
+        stats: SearchStatistics::default(),
+        multi_pv: parameters.multi_pv,
+        tablebase: parameters.tablebase,
+    }
+    .search()
 }
 
 pub struct SearchParameters<'a, E: SearchEnd, T: Transpositions> {
     pub end: E,
     pub table: &'a mut T,
+    pub observer: &'a mut dyn SearchObserver,
+    pub multi_pv: MultiPv,
+    /// Probed for the result of positions with few enough pieces
+    pub tablebase: Option<&'a dyn Tablebase>,
+}
+
+/// The number of lines from the root which the search ranks and scores in
+/// each iteration. Each line after the first is found by searching the root
+/// again with the moves of the previous lines excluded, so every extra line
+/// costs roughly as much as the first.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MultiPv {
+    Top(usize),
+    /// Score every legal move in the root position
+    All,
+}
+
+impl Default for MultiPv {
+    fn default() -> Self {
+        MultiPv::Top(1)
+    }
+}
+
+impl MultiPv {
+    fn line_count(&self, node: &TreeNode) -> usize {
+        let legal_moves = node.position().moves(&Moves::All).len();
+        match self {
+            MultiPv::Top(n) => (*n).clamp(1, legal_moves.max(1)),
+            MultiPv::All => legal_moves.max(1),
+        }
+    }
+}
+
+/// API function for executing a Lazy SMP search. The calling thread and the
+/// requested number of helper threads all run iterative deepening from a copy
+/// of the root node, communicating only through the shared table. The helpers
+/// are stopped once the calling thread finishes and the deepest completed
+/// result across all threads is returned.
+pub fn search_parallel<E: SearchEnd + Sync>(
+    node: TreeNode,
+    parameters: ParallelSearchParameters<E>,
+) -> Result<SearchOutcome> {
+    let ParallelSearchParameters { end, table, helpers, observer, multi_pv, tablebase } =
+        parameters;
+    let main_finished = AtomicBool::new(false);
+    std::thread::scope(|scope| {
+        let helper_handles = (0..helpers)
+            .map(|i| {
+                let (node, end, main_finished) = (node.clone(), &end, &main_finished);
+                scope.spawn(move || {
+                    Search {
+                        node,
+                        end: (end, main_finished),
+                        transpositions: &mut &*table,
+                        observer: &mut (),
+                        // Stagger the helpers so they are not all searching the same depth
+                        start_depth: 1 + (i % 2) as u8,
+                        stats: SearchStatistics::default(),
+                        multi_pv,
+                        tablebase,
+                    }
+                    .search()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let main_outcome = Search {
+            node,
+            end: &end,
+            transpositions: &mut &*table,
+            observer,
+            start_depth: 1,
+            stats: SearchStatistics::default(),
+            multi_pv,
+            tablebase,
+        }
+        .search();
+        main_finished.store(true, Ordering::Relaxed);
+
+        let mut best = main_outcome?;
+        let mut statistics = best.statistics.clone();
+        for handle in helper_handles {
+            if let Ok(Ok(outcome)) = handle.join() {
+                statistics.merge(&outcome.statistics);
+                if outcome.depth > best.depth {
+                    best = SearchOutcome { time: best.time, ..outcome };
+                }
+            }
+        }
+        // The work done by every thread contributes to the result
+        Ok(SearchOutcome { statistics, ..best })
+    })
+}
+
+pub struct ParallelSearchParameters<'a, E: SearchEnd> {
+    pub end: E,
+    pub table: &'a ConcurrentTranspositions,
+    /// The number of threads to run alongside the calling thread
+    pub helpers: usize,
+    /// Only notified of the progress of the calling thread
+    pub observer: &'a mut dyn SearchObserver,
+    pub multi_pv: MultiPv,
+    pub tablebase: Option<&'a dyn Tablebase>,
 }
 
 /// Data class composing information/result about/of a best move search.
@@ -44,9 +167,96 @@ pub struct SearchOutcome {
     pub best_move: Move,
     /// Larger +ve score better for side to move
     pub relative_eval: i32,
+    /// The relative eval distinguishing forced mates from positional estimates
+    pub score: Score,
     pub depth: u8,
     pub time: Duration,
     pub optimal_path: Vec<Move>,
+    /// Lines from the root ranked best first, the first matches the fields above
+    pub lines: Vec<SearchLine>,
+    pub statistics: SearchStatistics,
+}
+
+/// A scored line of play starting with some move in the root position.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SearchLine {
+    pub root_move: Move,
+    /// Larger +ve score better for side to move
+    pub relative_eval: i32,
+    pub score: Score,
+    pub path: Vec<Move>,
+}
+
+impl serde::Serialize for SearchLine {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SearchLine", 4)?;
+        state.serialize_field("move", &self.root_move.to_string())?;
+        state.serialize_field("positionEval", &self.relative_eval)?;
+        state.serialize_field("score", &self.score)?;
+        state.serialize_field(
+            "path",
+            &self.path.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
+        )?;
+        state.end()
+    }
+}
+
+/// Counters describing the work done during a search, summed over every
+/// iteration and, for a parallel search, every thread.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SearchStatistics {
+    /// All nodes visited including those in the quiescent search
+    pub nodes: u64,
+    /// Nodes visited in the quiescent search
+    pub qnodes: u64,
+    /// The maximum distance from the root reached by any line
+    pub seldepth: u8,
+    pub tt_hits: u64,
+    pub tt_misses: u64,
+    /// Nodes where a move caused a beta cutoff
+    pub cutoffs: u64,
+    /// Nodes where the first move searched caused a beta cutoff
+    pub first_move_cutoffs: u64,
+    /// Nodes where the result was found by probing the tablebase
+    pub tb_hits: u64,
+    /// Permille of the transposition table in use when the search ended
+    pub hashfull: u16,
+}
+
+impl SearchStatistics {
+    pub fn merge(&mut self, other: &SearchStatistics) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        self.seldepth = self.seldepth.max(other.seldepth);
+        self.tt_hits += other.tt_hits;
+        self.tt_misses += other.tt_misses;
+        self.cutoffs += other.cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.tb_hits += other.tb_hits;
+        self.hashfull = self.hashfull.max(other.hashfull);
+    }
+}
+
+impl serde::Serialize for SearchStatistics {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("SearchStatistics", 9)?;
+        state.serialize_field("nodes", &self.nodes)?;
+        state.serialize_field("qnodes", &self.qnodes)?;
+        state.serialize_field("seldepth", &self.seldepth)?;
+        state.serialize_field("ttHits", &self.tt_hits)?;
+        state.serialize_field("ttMisses", &self.tt_misses)?;
+        state.serialize_field("cutoffs", &self.cutoffs)?;
+        state.serialize_field("firstMoveCutoffs", &self.first_move_cutoffs)?;
+        state.serialize_field("tbHits", &self.tb_hits)?;
+        state.serialize_field("hashfull", &self.hashfull)?;
+        state.end()
+    }
 }
 
 impl serde::Serialize for SearchOutcome {
@@ -54,15 +264,18 @@ impl serde::Serialize for SearchOutcome {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("SearchOutcome", 4)?;
+        let mut state = serializer.serialize_struct("SearchOutcome", 8)?;
         state.serialize_field("bestMove", &self.best_move.to_string())?;
         state.serialize_field("positionEval", &self.relative_eval)?;
+        state.serialize_field("score", &self.score)?;
         state.serialize_field("depthSearched", &self.depth)?;
         state.serialize_field("searchDurationMillis", &self.time.as_millis())?;
         state.serialize_field(
             "optimalPath",
             &self.optimal_path.iter().map(|m| m.to_string()).collect::<Vec<_>>(),
         )?;
+        state.serialize_field("lines", &self.lines)?;
+        state.serialize_field("statistics", &self.statistics)?;
         state.end()
     }
 }
@@ -77,27 +290,46 @@ mod searchoutcome_serialize_test {
     use crate::constants::{class, corner, side, square};
     use crate::moves::Move;
 
-    use super::SearchOutcome;
+    use super::{Score, SearchLine, SearchOutcome, SearchStatistics};
 
     #[test]
     fn test_json_serialize() {
+        let optimal_path = vec![
+            Move::Castle { corner: corner::WK, king: square::E1, rook: square::H1 },
+            Move::Normal {
+                moving: create_piece(side::B, class::P),
+                from: square::D7,
+                dest: square::D5,
+                capture: None,
+            },
+        ];
         let search_outcome = SearchOutcome {
-            best_move: Move::Castle { corner: corner::WK },
+            best_move: Move::Castle { corner: corner::WK, king: square::E1, rook: square::H1 },
             relative_eval: -125,
+            score: Score::Centipawns(-125),
             depth: 2,
             time: Duration::from_millis(3000),
-            optimal_path: vec![
-                Move::Castle { corner: corner::WK },
-                Move::Normal {
-                    moving: create_piece(side::B, class::P),
-                    from: square::D7,
-                    dest: square::D5,
-                    capture: None,
-                },
-            ],
+            optimal_path: optimal_path.clone(),
+            lines: vec![SearchLine {
+                root_move: Move::Castle { corner: corner::WK, king: square::E1, rook: square::H1 },
+                relative_eval: -125,
+                score: Score::Centipawns(-125),
+                path: optimal_path,
+            }],
+            statistics: SearchStatistics {
+                nodes: 100,
+                qnodes: 60,
+                seldepth: 7,
+                tt_hits: 10,
+                tt_misses: 20,
+                cutoffs: 5,
+                first_move_cutoffs: 4,
+                tb_hits: 2,
+                hashfull: 3,
+            },
         };
         assert_eq!(
-            r#"{"bestMove":"e1g1","positionEval":-125,"depthSearched":2,"searchDurationMillis":3000,"optimalPath":["e1g1","d7d5"]}"#,
+            r#"{"bestMove":"e1g1","positionEval":-125,"score":{"cp":-125},"depthSearched":2,"searchDurationMillis":3000,"optimalPath":["e1g1","d7d5"],"lines":[{"move":"e1g1","positionEval":-125,"score":{"cp":-125},"path":["e1g1","d7d5"]}],"statistics":{"nodes":100,"qnodes":60,"seldepth":7,"ttHits":10,"ttMisses":20,"cutoffs":5,"firstMoveCutoffs":4,"tbHits":2,"hashfull":3}}"#,
             serde_json::to_string(&search_outcome).expect("Serialization failed")
         );
     }
@@ -107,64 +339,125 @@ struct Search<'a, E: SearchEnd, T: Transpositions> {
     node: TreeNode,
     end: E,
     transpositions: &'a mut T,
+    observer: &'a mut dyn SearchObserver,
+    start_depth: u8,
+    stats: SearchStatistics,
+    multi_pv: MultiPv,
+    tablebase: Option<&'a dyn Tablebase>,
+}
+
+/// Wraps the end condition of the search so it can be disabled for an iteration
+struct IterationEnd<'a, E: SearchEnd> {
+    end: &'a E,
+    can_end: bool,
 }
 
-struct BestMoveResponse {
-    eval: i32,
-    best_move: Move,
-    path: Vec<Move>,
-    depth: u8,
+impl<E: SearchEnd> SearchEnd for IterationEnd<'_, E> {
+    fn should_end(&self, ctx: &Context) -> bool {
+        self.can_end && self.end.should_end(ctx)
+    }
 }
 
 impl<E: SearchEnd, T: Transpositions> Search<'_, E, T> {
     pub fn search(&mut self) -> Result<SearchOutcome> {
         let search_start = Instant::now();
         let mut break_err = anyhow!("Terminated before search began");
-        let mut pv = PrincipleVariation::default();
-        let mut best_response = None;
-        for i in 1..DEPTH_UPPER_BOUND {
-            match self.best_move(i as u8, search_start, &pv) {
+        let mut pvs: Vec<PrincipleVariation> = vec![];
+        let mut best_lines = None;
+        for i in self.start_depth as usize..DEPTH_UPPER_BOUND {
+            // The first iteration is always completed so there is a move to return
+            let can_end = best_lines.is_some();
+            match self.best_lines(i as u8, search_start, &pvs, can_end) {
                 Err(message) => {
                     break_err = anyhow!("{}", message);
                     break;
                 }
-                Ok(response) => {
-                    pv.set(response.path.as_slice());
-                    let eval = response.eval;
-                    best_response = Some(response);
-                    // Inevitable checkmate detected, don't search any deeper
-                    if eval.abs() == node::WIN_VALUE {
+                Ok(lines) => {
+                    pvs = lines
+                        .iter()
+                        .map(|line| {
+                            let mut pv = PrincipleVariation::default();
+                            pv.set(line.path.as_slice());
+                            pv
+                        })
+                        .collect();
+                    self.observer.on_iteration_complete(&IterationSummary {
+                        depth: i as u8,
+                        relative_eval: lines[0].relative_eval,
+                        optimal_path: lines[0].path.clone(),
+                        lines: lines.clone(),
+                        elapsed: search_start.elapsed(),
+                        nodes: self.stats.nodes,
+                    });
+                    // Checkmate found within the full width of the search so no
+                    // nearer mate exists, don't search any deeper
+                    let all_mates = lines.iter().all(|line| {
+                        let eval = line.relative_eval;
+                        node::is_mate(eval) && node::WIN_VALUE - eval.abs() <= i as i32
+                    });
+                    best_lines = Some((i as u8, lines));
+                    if all_mates {
                         break;
                     }
                 }
             }
         }
 
-        best_response.ok_or(break_err).map(|response| SearchOutcome {
-            best_move: response.best_move,
-            relative_eval: response.eval,
-            depth: response.depth,
+        self.stats.hashfull = self.transpositions.hashfull();
+        best_lines.ok_or(break_err).map(|(depth, lines)| SearchOutcome {
+            best_move: lines[0].root_move.clone(),
+            relative_eval: lines[0].relative_eval,
+            score: lines[0].score,
+            depth,
             time: search_start.elapsed(),
-            optimal_path: response.path,
+            optimal_path: lines[0].path.clone(),
+            lines,
+            statistics: self.stats.clone(),
         })
     }
 
+    /// Complete one iteration for every requested line, ranked best first
+    fn best_lines(
+        &mut self,
+        depth: u8,
+        search_start: Instant,
+        pvs: &[PrincipleVariation],
+        can_end: bool,
+    ) -> Result<Vec<SearchLine>> {
+        let mut lines: Vec<SearchLine> = vec![];
+        for i in 0..self.multi_pv.line_count(&self.node) {
+            let pv = pvs.get(i).cloned().unwrap_or_default();
+            let excluded = lines.iter().map(|line| line.root_move.clone()).collect::<Vec<_>>();
+            lines.push(self.best_move(depth, search_start, &pv, can_end, &excluded)?);
+        }
+        // Lines found later are not guaranteed to be worse due to search instability
+        lines.sort_by_key(|line| -line.relative_eval);
+        Ok(lines)
+    }
+
     fn best_move(
         &mut self,
         depth: u8,
         search_start: Instant,
         pv: &PrincipleVariation,
-    ) -> Result<BestMoveResponse> {
+        can_end: bool,
+        excluded: &[Move],
+    ) -> Result<SearchLine> {
         if depth < 1 {
             return Err(anyhow!("Cannot iteratively deepen with depth 0"));
         }
 
         let root_index = self.node.position().history.len() as u16;
+        let nodes = self.stats.nodes;
         let SearchResponse { eval, path } = TreeSearcher {
-            end: &self.end,
+            end: &IterationEnd { end: &self.end, can_end },
             table: self.transpositions,
             moves: MoveGenerator::default(),
             pv,
+            observer: self.observer,
+            stats: &mut self.stats,
+            excluded,
+            tablebase: self.tablebase,
         }
         .search(
             &mut self.node,
@@ -176,14 +469,21 @@ impl<E: SearchEnd, T: Transpositions> Search<'_, E, T> {
                 precursors: vec![],
                 known_raise_alpha: None,
                 root_index,
+                nodes,
             },
         )?;
 
         // If the path returned is empty then there must be no legal moves in this position
-        if path.is_empty() {
-            Err(anyhow!("No moves for position {} at depth {}", self.node.position(), depth))
-        } else {
-            Ok(BestMoveResponse { best_move: path.get(0).unwrap().clone(), eval, path, depth })
+        match path.first() {
+            None => {
+                Err(anyhow!("No moves for position {} at depth {}", self.node.position(), depth))
+            }
+            Some(m) => Ok(SearchLine {
+                root_move: m.clone(),
+                relative_eval: eval,
+                score: eval.into(),
+                path,
+            }),
         }
     }
 }
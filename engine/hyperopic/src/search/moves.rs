@@ -8,7 +8,7 @@ use crate::eval::tables::PositionTables;
 use crate::moves::Move::{Castle, Enpassant, Normal, Null, Promote};
 use crate::moves::{Move, Moves};
 use crate::node::TreeNode;
-use crate::position::{ConstrainedPieces, Position, CASTLING_DETAILS};
+use crate::position::{castle_targets, ConstrainedPieces, Position};
 use crate::{Board, Class, Piece, Square};
 
 #[derive(Default)]
@@ -213,12 +213,10 @@ fn is_checking(
             in_board(discoveries.1[*from], *dest)
                 || in_board(control(*promoted, *dest, occupied & !lift(*from)), enemy_king)
         }
-        Castle { corner } => {
-            let details = &CASTLING_DETAILS[*corner];
-            in_board(
-                control(create_piece(side::W, class::R), details.rook_line.1, occupied),
-                enemy_king,
-            )
+        Castle { corner, king, rook } => {
+            let (king_target, rook_target) = castle_targets(*corner);
+            let occupied = (occupied & !lift(*king) & !lift(*rook)) | lift(king_target);
+            in_board(control(create_piece(side::W, class::R), rook_target, occupied), enemy_king)
         }
     }
 }
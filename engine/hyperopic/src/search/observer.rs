@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use crate::moves::Move;
+use crate::search::SearchLine;
+
+/// Snapshot of the search taken when an iteration of the iterative deepening
+/// loop completes.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IterationSummary {
+    pub depth: u8,
+    /// Larger +ve score better for side to move
+    pub relative_eval: i32,
+    pub optimal_path: Vec<Move>,
+    /// Every line computed in this iteration ranked best first
+    pub lines: Vec<SearchLine>,
+    pub elapsed: Duration,
+    /// Total nodes visited since the search began
+    pub nodes: u64,
+}
+
+/// Receives progress updates while a search is running so callers can report
+/// what the engine is thinking. Both methods default to doing nothing and the
+/// unit type is provided as an observer which ignores everything.
+pub trait SearchObserver {
+    /// Called each time an iteration completes successfully
+    fn on_iteration_complete(&mut self, _summary: &IterationSummary) {}
+
+    /// Called when the search at the root moves onto a new move, the index
+    /// is the position of the move in the search order starting from zero.
+    fn on_root_move(&mut self, _depth: u8, _m: &Move, _index: usize) {}
+}
+
+impl SearchObserver for () {}
+
+#[cfg(test)]
+mod test {
+    use super::{IterationSummary, SearchObserver};
+    use crate::moves::Move;
+    use crate::position::Position;
+    use crate::search::{search, MultiPv, SearchParameters, TranspositionsImpl};
+
+    #[derive(Default)]
+    struct Recorder {
+        iterations: Vec<IterationSummary>,
+        root_moves: Vec<(u8, Move, usize)>,
+    }
+
+    impl SearchObserver for Recorder {
+        fn on_iteration_complete(&mut self, summary: &IterationSummary) {
+            self.iterations.push(summary.clone())
+        }
+
+        fn on_root_move(&mut self, depth: u8, m: &Move, index: usize) {
+            self.root_moves.push((depth, m.clone(), index))
+        }
+    }
+
+    #[test]
+    fn observer_notified_of_progress() {
+        let position: Position = "1. e4 e5 2. Nf3 Nc6".parse().unwrap();
+        let mut recorder = Recorder::default();
+        let outcome = search(
+            position.into(),
+            SearchParameters {
+                end: 3,
+                table: &mut TranspositionsImpl::new(10_000),
+                observer: &mut recorder,
+                multi_pv: MultiPv::default(),
+                tablebase: None,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(vec![1, 2, 3], recorder.iterations.iter().map(|s| s.depth).collect::<Vec<_>>());
+        assert!(recorder.iterations.windows(2).all(|w| w[0].nodes < w[1].nodes));
+        let last = recorder.iterations.last().unwrap();
+        assert_eq!(outcome.optimal_path, last.optimal_path);
+        assert_eq!(outcome.relative_eval, last.relative_eval);
+        for depth in 1..=3 {
+            let at_depth = recorder.root_moves.iter().filter(|(d, _, _)| *d == depth);
+            assert!(at_depth.enumerate().all(|(i, (_, _, index))| i == *index));
+        }
+    }
+}
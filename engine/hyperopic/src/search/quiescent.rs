@@ -9,7 +9,7 @@ use crate::moves::Move::{Castle, Enpassant, Normal, Promote};
 use crate::moves::{Move, MoveFacet, Moves};
 use crate::node;
 use crate::node::TreeNode;
-use crate::position::TerminalState;
+use crate::search::SearchStatistics;
 
 const Q_CHECK_CAP: i32 = -1;
 const DELTA_SKIP_MARGIN: i32 = 200;
@@ -22,18 +22,58 @@ pub fn full_search(node: &mut TreeNode) -> Result<i32> {
 }
 
 pub fn search(node: &mut TreeNode, alpha: i32, beta: i32) -> Result<i32> {
-    search_impl(node, alpha, beta, -1)
+    search_impl(node, alpha, beta, -1, &mut Counter { ply: 0, stats: &mut Default::default() })
+}
+
+/// Quiescent search entered from the given distance to the root of the main
+/// search which records the nodes it visits in the given statistics.
+pub(crate) fn search_counted(
+    node: &mut TreeNode,
+    alpha: i32,
+    beta: i32,
+    ply: usize,
+    stats: &mut SearchStatistics,
+) -> Result<i32> {
+    search_impl(node, alpha, beta, -1, &mut Counter { ply, stats })
+}
+
+struct Counter<'a> {
+    ply: usize,
+    stats: &'a mut SearchStatistics,
+}
+
+impl Counter<'_> {
+    /// The distance from the root of the main search at the given quiescent depth
+    fn ply(&self, depth: i32) -> usize {
+        self.ply + (-1 - depth) as usize
+    }
+
+    fn visit(&mut self, depth: i32) {
+        // The entry node was already counted by the main search
+        if depth != -1 {
+            self.stats.nodes += 1;
+        }
+        self.stats.qnodes += 1;
+        self.stats.seldepth = self.stats.seldepth.max(self.ply(depth) as u8);
+    }
 }
 
 /// Performs a depth limited search looking to evaluate only quiet positions,
 /// i.e. those with no attack moves.
-fn search_impl(node: &mut TreeNode, mut alpha: i32, beta: i32, depth: i32) -> Result<i32> {
+fn search_impl(
+    node: &mut TreeNode,
+    mut alpha: i32,
+    beta: i32,
+    depth: i32,
+    counter: &mut Counter,
+) -> Result<i32> {
+    counter.visit(depth);
     // We know the start node not terminal otherwise wouldn't have entered the quiescent search
     if depth != -1 {
         match node.position().compute_terminal_state() {
-            Some(TerminalState::Loss) => return Ok(node::LOSS_VALUE),
-            Some(TerminalState::Draw) => return Ok(node::DRAW_VALUE),
-            _ => {}
+            Some(state) if state.is_loss() => return Ok(node::mated_in(counter.ply(depth))),
+            Some(_) => return Ok(node::DRAW_VALUE),
+            None => {}
         }
     }
     // If we aren't in check then we can use the static eval as the initial
@@ -74,7 +114,7 @@ fn search_impl(node: &mut TreeNode, mut alpha: i32, beta: i32, depth: i32) -> Re
             }
         };
         node.make(m)?;
-        let next_result = -search_impl(node, -beta, -alpha, depth - 1)?;
+        let next_result = -search_impl(node, -beta, -alpha, depth - 1, counter)?;
         node.unmake()?;
         result = cmp::max(result, next_result);
         alpha = cmp::max(alpha, result);
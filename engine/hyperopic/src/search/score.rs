@@ -0,0 +1,79 @@
+use std::fmt::{Display, Formatter};
+
+use serde::Serializer;
+
+use crate::node;
+
+/// The evaluation of a position from the perspective of the side to move,
+/// either a positional estimate or a forced mate within some number of moves.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Score {
+    Centipawns(i32),
+    /// The number of moves until mate, negative if the side to move is mated
+    Mate(i32),
+}
+
+/// Tablebase wins are reported in centipawns as by other engines, reduced by
+/// the distance in ply from the root.
+const TABLEBASE_WIN_CENTIPAWNS: i32 = 20_000;
+
+impl From<i32> for Score {
+    fn from(relative_eval: i32) -> Self {
+        if node::is_mate(relative_eval) {
+            let ply = node::WIN_VALUE - relative_eval.abs();
+            let moves = (ply + 1) / 2;
+            Score::Mate(if relative_eval > 0 { moves } else { -moves })
+        } else if node::is_tablebase_win(relative_eval) {
+            let ply = node::TABLEBASE_WIN_VALUE - relative_eval.abs();
+            Score::Centipawns(relative_eval.signum() * (TABLEBASE_WIN_CENTIPAWNS - ply))
+        } else {
+            Score::Centipawns(relative_eval)
+        }
+    }
+}
+
+/// Formatted as in the uci protocol, e.g. "cp 35" or "mate -2"
+impl Display for Score {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "cp {}", cp),
+            Score::Mate(moves) => write!(f, "mate {}", moves),
+        }
+    }
+}
+
+impl serde::Serialize for Score {
+    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Score::Centipawns(cp) => serializer.serialize_newtype_variant("Score", 0, "cp", cp),
+            Score::Mate(moves) => serializer.serialize_newtype_variant("Score", 1, "mate", moves),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Score;
+    use crate::node;
+
+    #[test]
+    fn from_eval() {
+        assert_eq!(Score::Centipawns(-125), Score::from(-125));
+        assert_eq!(Score::Mate(1), Score::from(node::mate_in(1)));
+        assert_eq!(Score::Mate(2), Score::from(node::mate_in(3)));
+        assert_eq!(Score::Mate(-1), Score::from(node::mated_in(2)));
+        assert_eq!(Score::Mate(-3), Score::from(node::mated_in(6)));
+        assert_eq!(Score::Centipawns(19_997), Score::from(node::tablebase_win_at(3)));
+        assert_eq!(Score::Centipawns(-19_996), Score::from(-node::tablebase_win_at(4)));
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!("cp 35", Score::Centipawns(35).to_string());
+        assert_eq!("mate -2", Score::Mate(-2).to_string());
+        assert_eq!(r#"{"mate":3}"#, serde_json::to_string(&Score::Mate(3)).unwrap());
+    }
+}
@@ -8,12 +8,14 @@ use crate::constants::{class, create_piece, in_board};
 use crate::moves::Move;
 use crate::node;
 use crate::node::TreeNode;
-use crate::position::{TerminalState, CASTLING_DETAILS};
 use crate::search::end::SearchEnd;
 use crate::search::moves::{MoveGenerator, SearchMove};
+use crate::search::observer::SearchObserver;
 use crate::search::pv::PrincipleVariation;
 use crate::search::quiescent;
-use crate::search::table::{NodeType, Transpositions};
+use crate::search::table::{NodeType, TableEntry, Transpositions};
+use crate::search::SearchStatistics;
+use crate::syzygy::{self, Tablebase, Wdl};
 
 /// Provides relevant callstack information for the search to
 /// use during the traversal of the tree.
@@ -25,6 +27,8 @@ pub struct Context {
     pub depth: u8,
     pub precursors: Vec<Move>,
     pub known_raise_alpha: Option<Move>,
+    /// The number of nodes visited so far in the search
+    pub nodes: u64,
 }
 
 impl Context {
@@ -39,6 +43,7 @@ impl Context {
             root_index: self.root_index,
             precursors: next_precursors,
             known_raise_alpha: None,
+            nodes: self.nodes,
         }
     }
 }
@@ -63,6 +68,13 @@ pub struct TreeSearcher<'a, E: SearchEnd, T: Transpositions> {
     pub table: &'a mut T,
     pub moves: MoveGenerator,
     pub pv: &'a PrincipleVariation,
+    pub observer: &'a mut dyn SearchObserver,
+    /// Running statistics accumulated across all iterations
+    pub stats: &'a mut SearchStatistics,
+    /// Moves in the root position which should not be searched
+    pub excluded: &'a [Move],
+    /// Probed for the result of positions with few enough pieces
+    pub tablebase: Option<&'a dyn Tablebase>,
 }
 
 fn reposition_first(dest: &mut Vec<SearchMove>, new_first: &Move) {
@@ -80,25 +92,46 @@ enum TableLookup {
 
 impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
     pub fn search(&mut self, node: &mut TreeNode, mut ctx: Context) -> Result<SearchResponse> {
+        ctx.nodes = self.stats.nodes;
         if self.end.should_end(&ctx) {
             return Err(anyhow!("Terminated at depth {}", ctx.depth));
         }
+        let ply = ctx.precursors.len();
+        self.stats.nodes += 1;
+        self.stats.seldepth = max(self.stats.seldepth, ply as u8);
         let terminal_state = node.position().compute_terminal_state();
         if ctx.depth == 0 || terminal_state.is_some() {
             return match terminal_state {
-                Some(TerminalState::Loss) => Ok(node::LOSS_VALUE),
-                Some(TerminalState::Draw) => Ok(node::DRAW_VALUE),
-                None => quiescent::search(node, ctx.alpha, ctx.beta),
+                Some(state) if state.is_loss() => Ok(node::mated_in(ply)),
+                Some(_) => Ok(node::DRAW_VALUE),
+                None => quiescent::search_counted(node, ctx.alpha, ctx.beta, ply, self.stats),
             }
             .map(|eval| SearchResponse { eval, path: vec![] });
         }
 
-        let table_entry = match self.do_table_lookup(node, &ctx) {
-            TableLookup::Miss => None,
-            TableLookup::Suggestion(n) => Some(n),
-            TableLookup::Hit(response) => return Ok(response),
+        // The table knows nothing of the root exclusions so must not be used at the root
+        let excluding = ply == 0 && !self.excluded.is_empty();
+        let table_lookup =
+            if excluding { TableLookup::Miss } else { self.do_table_lookup(node, &ctx, ply) };
+        let table_entry = match table_lookup {
+            TableLookup::Miss => {
+                self.stats.tt_misses += 1;
+                None
+            }
+            TableLookup::Suggestion(n) => {
+                self.stats.tt_hits += 1;
+                Some(n)
+            }
+            TableLookup::Hit(response) => {
+                self.stats.tt_hits += 1;
+                return Ok(response);
+            }
         };
 
+        if let Some(eval) = self.probe_tablebase(node, &ctx, ply) {
+            return Ok(SearchResponse { eval, path: vec![] });
+        }
+
         let in_pvs = self.pv.in_pv(ctx.precursors.as_slice());
 
         if !in_pvs && should_try_null_move_pruning(node, &ctx) {
@@ -110,7 +143,10 @@ impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
             }
         }
 
-        let mvs = self.generate_moves(node, &ctx, &table_entry);
+        let mut mvs = self.generate_moves(node, &ctx, &table_entry);
+        if excluding {
+            mvs.retain(|sm| !self.excluded.contains(&sm.m));
+        }
         let start_alpha = ctx.alpha;
         let in_check = node.position().in_check();
         let is_pv_node = in_pvs
@@ -126,6 +162,9 @@ impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
         while i < mvs.len() {
             let sm = &mvs[i];
             let m = &sm.m;
+            if ctx.precursors.is_empty() && !research {
+                self.observer.on_root_move(ctx.depth, m, i);
+            }
 
             // The depth reduction we will search the move with
             let mut r = 1;
@@ -177,13 +216,19 @@ impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
             }
 
             if ctx.alpha >= ctx.beta {
-                self.table.put(
-                    node.position(),
-                    ctx.root_index,
-                    ctx.depth,
-                    ctx.beta,
-                    Cut(m.clone()),
-                );
+                self.stats.cutoffs += 1;
+                if i == 0 {
+                    self.stats.first_move_cutoffs += 1;
+                }
+                if !excluding {
+                    self.table.put(
+                        node.position(),
+                        ctx.root_index,
+                        ctx.depth,
+                        to_table_eval(ctx.beta, ply),
+                        Cut(m.clone()),
+                    );
+                }
                 return Ok(SearchResponse { eval: ctx.beta, path: vec![] });
             }
 
@@ -208,35 +253,92 @@ impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
 
         // Populate the table with the information from this node.
         debug_assert!(best_path.len() > 0);
-        self.table.put(
-            node.position(),
-            ctx.root_index,
-            ctx.depth,
-            score,
-            if raised_alpha {
-                Pv(best_path.clone())
-            } else {
-                All(best_path.first().unwrap().clone())
-            },
-        );
+        if !excluding {
+            self.table.put(
+                node.position(),
+                ctx.root_index,
+                ctx.depth,
+                to_table_eval(score, ply),
+                if raised_alpha {
+                    Pv(best_path.clone())
+                } else {
+                    All(best_path.first().unwrap().clone())
+                },
+            );
+        }
 
         Ok(SearchResponse { eval: ctx.alpha, path: best_path })
     }
 
-    fn do_table_lookup(&self, node: &TreeNode, ctx: &Context) -> TableLookup {
+    /// Probe the tablebase for the result of the position, the eval is bounded
+    /// by the window so a win or loss outside of it causes a cutoff. The probe
+    /// assumes a reset fifty move clock so only happens just after a capture
+    /// or pawn move, and never at the root where every move must be scored.
+    fn probe_tablebase(&mut self, node: &TreeNode, ctx: &Context, ply: usize) -> Option<i32> {
+        let tablebase = self.tablebase?;
+        let position = node.position();
+        if ply == 0 || position.clock != 0 || !syzygy::probeable(tablebase, position) {
+            return None;
+        }
+        let eval = match tablebase.probe_wdl(position)? {
+            Wdl::Win => node::tablebase_win_at(ply),
+            Wdl::Loss => -node::tablebase_win_at(ply),
+            // Cursed wins and blessed losses are drawn by the fifty move rule
+            _ => node::DRAW_VALUE,
+        };
+        self.stats.tb_hits += 1;
+        Some(eval.clamp(ctx.alpha, ctx.beta))
+    }
+
+    /// The concurrent table only keeps the first move of a principal variation
+    /// so the rest of the line is recovered by following the moves stored for
+    /// the principal variation nodes along it, up to the given length.
+    fn extend_path(&self, node: &TreeNode, mut path: Vec<Move>, max_len: usize) -> Vec<Move> {
+        let mut position = node.position().clone();
+        let mut visited = vec![position.key];
+        for m in path.iter() {
+            if position.make(m.clone()).is_err() {
+                return path;
+            }
+            visited.push(position.key);
+        }
+        while path.len() < max_len {
+            let next = match self.table.get(&position) {
+                Some(TableEntry { node_type: Pv(line), .. }) => line.first().cloned(),
+                _ => None,
+            };
+            match next {
+                None => break,
+                Some(m) => {
+                    if position.make(m.clone()).is_err() || visited.contains(&position.key) {
+                        break;
+                    }
+                    visited.push(position.key);
+                    path.push(m);
+                }
+            }
+        }
+        path
+    }
+
+    fn do_table_lookup(&self, node: &TreeNode, ctx: &Context, ply: usize) -> TableLookup {
         // If we are in a repeated position then do not break early using table lookup as we can
         // enter a repeated cycle.
-        if let Some(existing) = self.table.get(node.position()) {
+        if let Some(mut existing) = self.table.get(node.position()) {
+            existing.eval = from_table_eval(existing.eval, ply);
             let is_repeated_position = has_repetition(node);
             match &existing.node_type {
                 n @ Pv(path) => {
-                    if !is_repeated_position
+                    // The root is always searched so the full line is known
+                    if ply > 0
+                        && !is_repeated_position
                         && existing.depth >= ctx.depth
                         && path.len() > 0
                         && is_pseudo_legal(node, path.first().unwrap())
                     {
                         let adjusted_eval = min(ctx.beta, max(ctx.alpha, existing.eval));
-                        TableLookup::Hit(SearchResponse { eval: adjusted_eval, path: path.clone() })
+                        let path = self.extend_path(node, path.clone(), ctx.depth as usize);
+                        TableLookup::Hit(SearchResponse { eval: adjusted_eval, path })
                     } else {
                         TableLookup::Suggestion(n.clone())
                     }
@@ -294,6 +396,28 @@ impl<E: SearchEnd, T: Transpositions> TreeSearcher<'_, E, T> {
     }
 }
 
+/// Mate and tablebase scores are stored relative to the node rather than the
+/// root so they remain correct when the position is reached at a different ply.
+fn to_table_eval(eval: i32, ply: usize) -> i32 {
+    if is_ply_dependent(eval) {
+        eval + eval.signum() * ply as i32
+    } else {
+        eval
+    }
+}
+
+fn from_table_eval(eval: i32, ply: usize) -> i32 {
+    if is_ply_dependent(eval) {
+        eval - eval.signum() * ply as i32
+    } else {
+        eval
+    }
+}
+
+fn is_ply_dependent(eval: i32) -> bool {
+    node::is_mate(eval) || node::is_tablebase_win(eval)
+}
+
 fn has_repetition(node: &TreeNode) -> bool {
     node.position()
         .history
@@ -308,13 +432,15 @@ fn is_pseudo_legal(node: &TreeNode, m: &Move) -> bool {
     match m {
         Move::Null => false,
         Move::Enpassant { capture, .. } => position.enpassant == Some(*capture),
-        &Move::Castle { corner } => {
+        &Move::Castle { corner, king: k_source, rook: r_source } => {
             position.castling_rights[corner] && {
-                let details = &CASTLING_DETAILS[corner];
+                let details = &position.castling_details[corner];
                 let rook = create_piece(position.active, class::R);
                 let king = create_piece(position.active, class::K);
-                position.piece_locs[details.rook_line.0] == Some(rook)
-                    && position.piece_locs[details.king_line.0] == Some(king)
+                details.king_line.0 == k_source
+                    && details.rook_line.0 == r_source
+                    && position.piece_locs[r_source] == Some(rook)
+                    && position.piece_locs[k_source] == Some(king)
             }
         }
         &Move::Normal { moving, from, dest, capture } => {
@@ -339,3 +465,22 @@ fn should_try_null_move_pruning(node: &TreeNode, ctx: &Context) -> bool {
         pawns.count_ones() > 2 && others.count_ones() > 1 || others.count_ones() > 2
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{from_table_eval, to_table_eval};
+    use crate::node;
+
+    #[test]
+    fn table_eval_relative_to_node() {
+        for (eval, moved) in [
+            (node::mate_in(5), node::mate_in(7)),
+            (node::mated_in(4), node::mated_in(6)),
+            (node::tablebase_win_at(3), node::tablebase_win_at(5)),
+            (-node::tablebase_win_at(3), -node::tablebase_win_at(5)),
+            (250, 250),
+        ] {
+            assert_eq!(moved, from_table_eval(to_table_eval(eval, 1), 3));
+        }
+    }
+}
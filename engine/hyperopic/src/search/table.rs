@@ -1,10 +1,21 @@
+use crate::constants::{class, create_piece, piece_class, piece_side, side};
 use crate::moves::Move;
 use crate::position::Position;
 use std::cmp::min;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 pub trait Transpositions {
-    fn get(&self, pos: &Position) -> Option<&TableEntry>;
+    fn get(&self, pos: &Position) -> Option<TableEntry>;
     fn put(&mut self, pos: &Position, root_index: u16, depth: u8, eval: i32, node_type: NodeType);
+    /// Permille of the table which is populated, estimated from a sample of entries
+    fn hashfull(&self) -> u16;
+}
+
+/// The number of entries sampled when estimating how full a table is
+const HASHFULL_SAMPLE: usize = 1000;
+
+fn permille(populated: usize, sampled: usize) -> u16 {
+    (populated * 1000 / sampled.max(1)) as u16
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -28,9 +39,9 @@ pub struct TranspositionsImpl {
 }
 
 impl Transpositions for TranspositionsImpl {
-    fn get(&self, pos: &Position) -> Option<&TableEntry> {
+    fn get(&self, pos: &Position) -> Option<TableEntry> {
         let index = self.index(pos.key);
-        self.inner[index].as_ref().filter(|&m| m.key == pos.key)
+        self.inner[index].as_ref().filter(|&m| m.key == pos.key).cloned()
     }
 
     fn put(&mut self, pos: &Position, root_index: u16, depth: u8, eval: i32, node_type: NodeType) {
@@ -43,6 +54,11 @@ impl Transpositions for TranspositionsImpl {
         }
         self.inner[index] = Some(TableEntry { root_index, depth, eval, key: pos.key, node_type });
     }
+
+    fn hashfull(&self) -> u16 {
+        let sample = &self.inner[..min(HASHFULL_SAMPLE, self.inner.len())];
+        permille(sample.iter().filter(|e| e.is_some()).count(), sample.len())
+    }
 }
 
 impl TranspositionsImpl {
@@ -54,3 +70,222 @@ impl TranspositionsImpl {
         (k % self.inner.len() as u64) as usize
     }
 }
+
+/// Transposition table which can be shared between threads without locking.
+/// Each entry is packed into a single word which is stored alongside the xor
+/// of itself with the position key, a torn write from two racing threads will
+/// then fail the key check on retrieval and be treated as a miss. The cost of
+/// packing is that only the first move of a principal variation is retained
+/// and only the lowest eight bits of the root index are used for aging.
+pub struct ConcurrentTranspositions {
+    inner: Vec<AtomicEntry>,
+}
+
+#[derive(Default)]
+struct AtomicEntry {
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+impl ConcurrentTranspositions {
+    /// The number of bytes occupied by a single entry in the table
+    pub const ENTRY_SIZE: usize = std::mem::size_of::<AtomicEntry>();
+
+    pub fn new(n_entries: usize) -> ConcurrentTranspositions {
+        ConcurrentTranspositions { inner: (0..n_entries).map(|_| AtomicEntry::default()).collect() }
+    }
+
+    fn index(&self, k: u64) -> usize {
+        (k % self.inner.len() as u64) as usize
+    }
+
+    fn load(&self, pos: &Position) -> Option<TableEntry> {
+        let entry = &self.inner[self.index(pos.key)];
+        let data = entry.data.load(Ordering::Relaxed);
+        let check = entry.check.load(Ordering::Relaxed);
+        if data == 0 || check ^ data != pos.key {
+            return None;
+        }
+        let m = decode_move(pos, (data >> MOVE_SHIFT) as u16)?;
+        Some(TableEntry {
+            root_index: (data >> AGE_SHIFT) as u8 as u16,
+            key: pos.key,
+            depth: (data >> DEPTH_SHIFT) as u8,
+            eval: ((data >> EVAL_SHIFT) & EVAL_MASK) as i32 - EVAL_OFFSET,
+            node_type: match data & NODE_TYPE_MASK {
+                PV_TYPE => NodeType::Pv(vec![m]),
+                CUT_TYPE => NodeType::Cut(m),
+                _ => NodeType::All(m),
+            },
+        })
+    }
+
+    fn store(&self, pos: &Position, root_index: u16, depth: u8, eval: i32, node_type: NodeType) {
+        let entry = &self.inner[self.index(pos.key)];
+        let existing = entry.data.load(Ordering::Relaxed);
+        if existing != 0 {
+            let age_diff = (root_index as u8).wrapping_sub((existing >> AGE_SHIFT) as u8);
+            if (existing >> DEPTH_SHIFT) as u8 as u16 > depth as u16 + age_diff as u16 {
+                return;
+            }
+        }
+        let (type_bits, m) = match &node_type {
+            NodeType::Pv(path) => match path.first() {
+                None => return,
+                Some(m) => (PV_TYPE, m),
+            },
+            NodeType::Cut(m) => (CUT_TYPE, m),
+            NodeType::All(m) => (ALL_TYPE, m),
+        };
+        let data = type_bits
+            | ((eval + EVAL_OFFSET) as u64 & EVAL_MASK) << EVAL_SHIFT
+            | (depth as u64) << DEPTH_SHIFT
+            | (root_index as u8 as u64) << AGE_SHIFT
+            | (encode_move(m) as u64) << MOVE_SHIFT;
+        entry.data.store(data, Ordering::Relaxed);
+        entry.check.store(data ^ pos.key, Ordering::Relaxed);
+    }
+
+    fn populated(&self) -> u16 {
+        let sample = &self.inner[..min(HASHFULL_SAMPLE, self.inner.len())];
+        permille(
+            sample.iter().filter(|e| e.data.load(Ordering::Relaxed) != 0).count(),
+            sample.len(),
+        )
+    }
+}
+
+impl Transpositions for ConcurrentTranspositions {
+    fn get(&self, pos: &Position) -> Option<TableEntry> {
+        self.load(pos)
+    }
+
+    fn put(&mut self, pos: &Position, root_index: u16, depth: u8, eval: i32, node_type: NodeType) {
+        self.store(pos, root_index, depth, eval, node_type)
+    }
+
+    fn hashfull(&self) -> u16 {
+        self.populated()
+    }
+}
+
+/// Each search thread holds a shared reference to the same table
+impl Transpositions for &ConcurrentTranspositions {
+    fn get(&self, pos: &Position) -> Option<TableEntry> {
+        self.load(pos)
+    }
+
+    fn put(&mut self, pos: &Position, root_index: u16, depth: u8, eval: i32, node_type: NodeType) {
+        self.store(pos, root_index, depth, eval, node_type)
+    }
+
+    fn hashfull(&self) -> u16 {
+        self.populated()
+    }
+}
+
+// Layout of a packed entry, from the least significant bit:
+// node type (2) | eval (24) | depth (8) | age (8) | move (16)
+const NODE_TYPE_MASK: u64 = 0b11;
+const PV_TYPE: u64 = 1;
+const CUT_TYPE: u64 = 2;
+const ALL_TYPE: u64 = 3;
+const EVAL_SHIFT: u64 = 2;
+const EVAL_MASK: u64 = (1 << 24) - 1;
+const EVAL_OFFSET: i32 = 1 << 23;
+const DEPTH_SHIFT: u64 = 26;
+const AGE_SHIFT: u64 = 34;
+const MOVE_SHIFT: u64 = 42;
+
+// Layout of a packed move, from the least significant bit:
+// from (6) | dest (6) | flag (4), castles store the king and rook squares
+const ENPASSANT_FLAG: u16 = 1;
+const CASTLE_FLAG: u16 = 2;
+const PROMOTE_FLAG: u16 = 3;
+
+fn encode_move(m: &Move) -> u16 {
+    let (from, dest, flag) = match m {
+        Move::Null => (0, 0, 0),
+        Move::Normal { from, dest, .. } => (*from, *dest, 0),
+        Move::Enpassant { from, dest, .. } => (*from, *dest, ENPASSANT_FLAG),
+        Move::Castle { king, rook, .. } => (*king, *rook, CASTLE_FLAG),
+        Move::Promote { from, dest, promoted, .. } => {
+            (*from, *dest, PROMOTE_FLAG + piece_class(*promoted) as u16)
+        }
+    };
+    from as u16 | (dest as u16) << 6 | flag << 12
+}
+
+/// The keys of the position and entry match so we can reconstruct the move
+/// from the board, anything inconsistent is treated as a miss.
+fn decode_move(pos: &Position, encoded: u16) -> Option<Move> {
+    let from = (encoded & 0b111111) as usize;
+    let dest = ((encoded >> 6) & 0b111111) as usize;
+    let active = pos.active;
+    match encoded >> 12 {
+        0 => {
+            let moving = pos.piece_locs[from].filter(|&p| piece_side(p) == active)?;
+            Some(Move::Normal { moving, from, dest, capture: pos.piece_locs[dest] })
+        }
+        ENPASSANT_FLAG => {
+            let capture = if active == side::W { dest.checked_sub(8)? } else { dest + 8 };
+            Some(Move::Enpassant { side: active, from, dest, capture })
+        }
+        CASTLE_FLAG => {
+            let corner = 2 * active + if dest < from { 0 } else { 1 };
+            Some(Move::Castle { corner, king: from, rook: dest })
+        }
+        flag => {
+            let promoted_class = (flag - PROMOTE_FLAG) as usize;
+            (class::N..=class::Q).contains(&promoted_class).then(|| Move::Promote {
+                from,
+                dest,
+                promoted: create_piece(active, promoted_class),
+                capture: pos.piece_locs[dest],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConcurrentTranspositions, NodeType, TableEntry, Transpositions};
+    use crate::moves::Moves;
+    use crate::position::Position;
+
+    #[test]
+    fn moves_survive_packing() {
+        let positions = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 b kq - 0 1",
+            "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        ];
+        for fen in positions {
+            let position = fen.parse::<Position>().unwrap();
+            for m in position.moves(&Moves::All) {
+                let mut table = ConcurrentTranspositions::new(100);
+                table.put(&position, 10, 4, -350, NodeType::Cut(m.clone()));
+                let expected = TableEntry {
+                    root_index: 10,
+                    key: position.key,
+                    depth: 4,
+                    eval: -350,
+                    node_type: NodeType::Cut(m),
+                };
+                assert_eq!(Some(expected), table.get(&position));
+            }
+        }
+    }
+
+    #[test]
+    fn key_mismatch_misses() {
+        let position = Position::default();
+        let mut other = position.clone();
+        other.play("e2e4").unwrap();
+        let mut table = ConcurrentTranspositions::new(1);
+        let m = position.moves(&Moves::All).first().cloned().unwrap();
+        table.put(&position, 0, 3, 20, NodeType::Pv(vec![m.clone()]));
+        assert_eq!(None, table.get(&other));
+        assert_eq!(Some(NodeType::Pv(vec![m])), table.get(&position).map(|e| e.node_type));
+    }
+}
@@ -283,7 +283,7 @@ mod test {
                 dest: H3,
                 capture: None,
             },
-            Move::Castle { corner: corner::BK },
+            Move::Castle { corner: corner::BK, king: E8, rook: H8 },
             Move::Normal {
                 moving: create_piece(side::W, class::Q),
                 from: D1,
@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::moves::{Move, Moves};
+use crate::position::Position;
+use crate::LookupMoveService;
+
+pub use probe::SyzygyTablebase;
+
+mod probe;
+mod table;
+
+/// Dtz values are ranked relative to this, it exceeds any distance in a table
+const MAX_DTZ: i32 = 1 << 18;
+
+/// The result of a position with optimal play from both sides. Cursed wins
+/// and blessed losses are wins and losses which are drawn by the fifty move
+/// rule.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Wdl {
+    Loss = -2,
+    BlessedLoss = -1,
+    Draw = 0,
+    CursedWin = 1,
+    Win = 2,
+}
+
+impl Wdl {
+    fn from_value(value: i32) -> Wdl {
+        match value {
+            v if v <= -2 => Wdl::Loss,
+            -1 => Wdl::BlessedLoss,
+            0 => Wdl::Draw,
+            1 => Wdl::CursedWin,
+            _ => Wdl::Win,
+        }
+    }
+}
+
+/// Endgame tablebase which can be probed for positions with few pieces and
+/// no castling rights. All values are from the perspective of the side to
+/// move.
+pub trait Tablebase: Send + Sync {
+    /// The largest number of pieces, including kings, of any table
+    fn max_pieces(&self) -> usize;
+
+    /// The result of the position assuming the fifty move counter was just
+    /// reset, none if the position cannot be probed.
+    fn probe_wdl(&self, position: &Position) -> Option<Wdl>;
+
+    /// The number of ply until the fifty move counter is reset with optimal
+    /// play, positive if winning and negative if losing, zero for a draw. If
+    /// the win or loss is cursed or blessed the magnitude exceeds 100. None is
+    /// returned if the position cannot be probed.
+    fn probe_dtz(&self, position: &Position) -> Option<i32>;
+}
+
+/// Whether the position is covered by the tablebase
+pub(crate) fn probeable(tablebase: &dyn Tablebase, position: &Position) -> bool {
+    position.castling_rights.iter().all(|&r| !r)
+        && position.piece_locs.iter().flatten().count() <= tablebase.max_pieces()
+}
+
+/// The dtz of a position from which a zeroing move leads to the given wdl
+fn dtz_before_zeroing(wdl: i32) -> i32 {
+    match wdl {
+        2 => 1,
+        1 => 101,
+        -1 => -101,
+        -2 => -1,
+        _ => 0,
+    }
+}
+
+/// Chooses moves in tablebase positions by dtz. Winning positions are
+/// converted by the fastest route to the next pawn move or capture, taking
+/// care not to run into the fifty move rule, and losing positions are
+/// prolonged for as long as possible.
+pub struct TablebaseLookup {
+    tablebase: Arc<dyn Tablebase>,
+}
+
+impl TablebaseLookup {
+    pub fn new(tablebase: Arc<dyn Tablebase>) -> TablebaseLookup {
+        TablebaseLookup { tablebase }
+    }
+}
+
+impl LookupMoveService for TablebaseLookup {
+    fn lookup(&mut self, position: Position) -> Result<Option<Move>> {
+        let clock = position.clock as i32;
+        let repeated = has_repeated(&position);
+        Ok(root_dtz(self.tablebase.as_ref(), position).and_then(|moves| {
+            moves
+                .into_iter()
+                .max_by_key(|(_, dtz)| (rank(*dtz, clock, repeated), -dtz))
+                .map(|(m, _)| m)
+        }))
+    }
+}
+
+/// The dtz of every legal move in the position counted from the position
+/// itself, none if any of the moves could not be probed.
+fn root_dtz(tablebase: &dyn Tablebase, mut position: Position) -> Option<Vec<(Move, i32)>> {
+    if !probeable(tablebase, &position) {
+        return None;
+    }
+    let mut result = vec![];
+    for m in position.moves(&Moves::All) {
+        position.make(m.clone()).ok()?;
+        let dtz = if position.clock == 0 {
+            dtz_before_zeroing(-(tablebase.probe_wdl(&position)? as i32))
+        } else if position.compute_terminal_state().is_some_and(|s| s.is_draw()) {
+            0
+        } else {
+            let dtz = -tablebase.probe_dtz(&position)?;
+            dtz + dtz.signum()
+        };
+        // A mating move is always the fastest way to finish the game
+        let mates = dtz == 2 && position.in_check() && position.moves(&Moves::All).is_empty();
+        position.unmake().ok()?;
+        result.push((m, if mates { 1 } else { dtz }));
+    }
+    Some(result)
+}
+
+/// Wins which can be converted before the fifty move rule are ranked equally,
+/// as are losses which cannot be drawn by the rule.
+fn rank(dtz: i32, clock: i32, repeated: bool) -> i32 {
+    if dtz > 0 {
+        if dtz + clock <= 99 && !repeated {
+            MAX_DTZ
+        } else {
+            MAX_DTZ - (dtz + clock)
+        }
+    } else if dtz < 0 {
+        if -dtz * 2 + clock < 100 {
+            -MAX_DTZ
+        } else {
+            -MAX_DTZ + (-dtz + clock)
+        }
+    } else {
+        0
+    }
+}
+
+/// Whether any position has occurred twice since the last pawn move or capture
+fn has_repeated(position: &Position) -> bool {
+    let keys = position
+        .history
+        .iter()
+        .rev()
+        .take(position.clock)
+        .map(|(discards, _)| discards.key)
+        .chain(std::iter::once(position.key))
+        .collect::<Vec<_>>();
+    keys.iter().collect::<HashSet<_>>().len() < keys.len()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use std::sync::Arc;
+
+    use crate::position::Position;
+    use crate::syzygy::{Tablebase, TablebaseLookup, Wdl};
+    use crate::LookupMoveService;
+
+    /// Answers probes from a list of positions keyed by the start of their fen
+    /// and falls back to a default value for any other position.
+    pub struct MockTablebase {
+        pub entries: Vec<(&'static str, Wdl, i32)>,
+        pub default: Option<(Wdl, i32)>,
+    }
+
+    impl MockTablebase {
+        fn find(&self, position: &Position) -> Option<(Wdl, i32)> {
+            let fen = position.to_string();
+            self.entries
+                .iter()
+                .find(|(f, _, _)| fen.starts_with(f))
+                .map(|(_, wdl, dtz)| (*wdl, *dtz))
+                .or(self.default)
+        }
+    }
+
+    impl Tablebase for MockTablebase {
+        fn max_pieces(&self) -> usize {
+            5
+        }
+
+        fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+            self.find(position).map(|(wdl, _)| wdl)
+        }
+
+        fn probe_dtz(&self, position: &Position) -> Option<i32> {
+            self.find(position).map(|(_, dtz)| dtz)
+        }
+    }
+
+    fn lookup(tablebase: MockTablebase, fen: &str) -> Option<String> {
+        let position: Position = fen.parse().unwrap();
+        TablebaseLookup::new(Arc::new(tablebase)).lookup(position).unwrap().map(|m| m.to_string())
+    }
+
+    #[test]
+    fn prefers_fastest_win() {
+        let tablebase = MockTablebase {
+            entries: vec![
+                ("8/8/8/8/8/2k5/8/K5R1 b", Wdl::Loss, -9),
+                ("8/8/8/8/8/2k4R/8/K7 b", Wdl::Loss, -5),
+            ],
+            default: Some((Wdl::Loss, -20)),
+        };
+        assert_eq!(Some("h1h3".to_owned()), lookup(tablebase, "8/8/8/8/8/2k5/8/K6R w - - 10 60"));
+    }
+
+    #[test]
+    fn avoids_losing_moves() {
+        let tablebase = MockTablebase {
+            entries: vec![
+                ("8/8/8/8/8/2k5/8/K5R1 b", Wdl::Draw, 0),
+                ("8/8/8/8/8/2k4R/8/K7 b", Wdl::Win, 3),
+            ],
+            default: Some((Wdl::Win, 5)),
+        };
+        assert_eq!(Some("h1g1".to_owned()), lookup(tablebase, "8/8/8/8/8/2k5/8/K6R w - - 10 60"));
+    }
+
+    #[test]
+    fn prolongs_loss() {
+        let tablebase = MockTablebase {
+            entries: vec![("8/8/8/8/8/3k4/8/K6R w", Wdl::Win, 15)],
+            default: Some((Wdl::Win, 3)),
+        };
+        assert_eq!(Some("c3d3".to_owned()), lookup(tablebase, "8/8/8/8/8/2k5/8/K6R b - - 0 60"));
+    }
+
+    #[test]
+    fn no_move_if_unknown() {
+        let tablebase = MockTablebase {
+            entries: vec![("8/8/8/8/8/2k4R/8/K7 b", Wdl::Loss, -5)],
+            default: None,
+        };
+        assert_eq!(None, lookup(tablebase, "8/8/8/8/8/2k5/8/K6R w - - 10 60"));
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, Result};
+
+use crate::constants::{class, create_piece, piece_class, side};
+use crate::moves::{Move, Moves};
+use crate::position::Position;
+use crate::syzygy::table::{Table, TableKind, TableValue, NAME_CLASSES};
+use crate::syzygy::{dtz_before_zeroing, probeable, Tablebase, Wdl};
+use crate::Side;
+
+/// Tablebase backed by directories of Syzygy table files. Only the file names
+/// are read up front, each table is parsed the first time it is probed and
+/// the compressed data is read from disk as required by each probe.
+pub struct SyzygyTablebase {
+    wdl: HashMap<String, Table>,
+    dtz: HashMap<String, Table>,
+    max_pieces: usize,
+}
+
+impl SyzygyTablebase {
+    /// Open the tables in the given directories, several directories may be
+    /// given separated in the same way as the PATH environment variable.
+    pub fn open(paths: &str) -> Result<SyzygyTablebase> {
+        let mut tablebase =
+            SyzygyTablebase { wdl: HashMap::new(), dtz: HashMap::new(), max_pieces: 0 };
+        for dir in std::env::split_paths(paths) {
+            let entries =
+                fs::read_dir(&dir).map_err(|e| anyhow!("Cannot read {}: {}", dir.display(), e))?;
+            for entry in entries {
+                let path = entry?.path();
+                let name = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+                let extension = path.extension().and_then(|s| s.to_str()).unwrap_or_default();
+                let kind = match extension {
+                    e if e == TableKind::Wdl.extension() => TableKind::Wdl,
+                    e if e == TableKind::Dtz.extension() => TableKind::Dtz,
+                    _ => continue,
+                };
+                if !is_table_name(name) {
+                    continue;
+                }
+                let table = Table::open(&path, name, kind)?;
+                if kind == TableKind::Wdl {
+                    tablebase.max_pieces = tablebase.max_pieces.max(name.len() - 1);
+                    tablebase.wdl.insert(name.to_owned(), table);
+                } else {
+                    tablebase.dtz.insert(name.to_owned(), table);
+                }
+            }
+        }
+        if tablebase.wdl.is_empty() {
+            Err(anyhow!("No Syzygy tables found in {}", paths))
+        } else {
+            Ok(tablebase)
+        }
+    }
+
+    /// The number of wdl and dtz tables available
+    pub fn table_count(&self) -> (usize, usize) {
+        (self.wdl.len(), self.dtz.len())
+    }
+
+    fn probe_table(&self, kind: TableKind, position: &Position, wdl: i32) -> Result<TableValue> {
+        let tables = match kind {
+            TableKind::Wdl => &self.wdl,
+            TableKind::Dtz => &self.dtz,
+        };
+        let (white, black) = (material(position, side::W), material(position, side::B));
+        let (table, black_stronger) = tables
+            .get(&format!("{}v{}", white, black))
+            .map(|t| (t, false))
+            .or_else(|| tables.get(&format!("{}v{}", black, white)).map(|t| (t, true)))
+            .ok_or_else(|| anyhow!("No {:?} table for {}v{}", kind, white, black))?;
+        table.probe(position, black_stronger, wdl)
+    }
+
+    fn probe_wdl_table(&self, position: &Position) -> Result<i32> {
+        // Only the two kings remain
+        if position.piece_locs.iter().flatten().count() == 2 {
+            return Ok(0);
+        }
+        match self.probe_table(TableKind::Wdl, position, 0)? {
+            TableValue::Value(wdl) => Ok(wdl),
+            TableValue::ChangeSide => Err(anyhow!("Wdl tables store both sides")),
+        }
+    }
+
+    /// The tables do not account for captures, so these are searched before the
+    /// table is consulted. Returns the wdl value and whether the best move is
+    /// zeroing in which case the stored dtz value of the position is unreliable.
+    fn search(&self, position: &mut Position, check_zeroing: bool) -> Result<(i32, bool)> {
+        let moves = position.moves(&Moves::All);
+        let total = moves.len();
+        let mut searched = 0;
+        let mut best = -2;
+        for m in moves {
+            if !is_capture(&m) && (!check_zeroing || !is_pawn_move(&m)) {
+                continue;
+            }
+            searched += 1;
+            position.make(m)?;
+            let value = -self.search(position, false)?.0;
+            position.unmake()?;
+            if value > best {
+                best = value;
+                if value >= 2 {
+                    return Ok((value, true));
+                }
+            }
+        }
+        // If every legal move was searched the stored value may be wrong
+        let no_more_moves = searched > 0 && searched == total;
+        let value = if no_more_moves { best } else { self.probe_wdl_table(position)? };
+        if best >= value {
+            Ok((best, best > 0 || no_more_moves))
+        } else {
+            Ok((value, false))
+        }
+    }
+
+    fn dtz(&self, position: &mut Position) -> Result<i32> {
+        let (wdl, zeroing) = self.search(position, true)?;
+        if wdl == 0 {
+            return Ok(0);
+        } else if zeroing {
+            return Ok(dtz_before_zeroing(wdl));
+        }
+        match self.probe_table(TableKind::Dtz, position, wdl)? {
+            TableValue::Value(dtz) => {
+                let cursed = if wdl.abs() == 1 { 100 } else { 0 };
+                Ok((dtz + cursed) * wdl.signum())
+            }
+            TableValue::ChangeSide => {
+                // The table stores the other side to move so search one ply
+                let mut min_dtz = i32::MAX;
+                for m in position.moves(&Moves::All) {
+                    let zeroing = is_capture(&m) || is_pawn_move(&m);
+                    position.make(m)?;
+                    let mut dtz = if zeroing {
+                        -dtz_before_zeroing(self.search(position, false)?.0)
+                    } else {
+                        -self.dtz(position)?
+                    };
+                    if dtz == 1 && position.in_check() && position.moves(&Moves::All).is_empty() {
+                        min_dtz = 1;
+                    }
+                    if !zeroing {
+                        dtz += dtz.signum();
+                    }
+                    if dtz < min_dtz && dtz.signum() == wdl.signum() {
+                        min_dtz = dtz;
+                    }
+                    position.unmake()?;
+                }
+                // No legal moves means the position is mate
+                Ok(if min_dtz == i32::MAX { -1 } else { min_dtz })
+            }
+        }
+    }
+}
+
+impl Tablebase for SyzygyTablebase {
+    fn max_pieces(&self) -> usize {
+        self.max_pieces
+    }
+
+    fn probe_wdl(&self, position: &Position) -> Option<Wdl> {
+        if !probeable(self, position) {
+            return None;
+        }
+        self.search(&mut position.clone(), false).ok().map(|(wdl, _)| Wdl::from_value(wdl))
+    }
+
+    fn probe_dtz(&self, position: &Position) -> Option<i32> {
+        if !probeable(self, position) {
+            return None;
+        }
+        self.dtz(&mut position.clone()).ok()
+    }
+}
+
+/// Table names list the pieces of each side strongest first, e.g "KRPvKR"
+fn is_table_name(name: &str) -> bool {
+    let order = |c: char| NAME_CLASSES.iter().position(|&(x, _)| x == c);
+    match name.split_once('v') {
+        None => false,
+        Some((white, black)) => [white, black].iter().all(|material| {
+            let indices = material.chars().map(order).collect::<Option<Vec<_>>>();
+            material.starts_with('K')
+                && indices.is_some_and(|indices| indices.windows(2).all(|w| w[0] <= w[1]))
+        }),
+    }
+}
+
+fn material(position: &Position, side: Side) -> String {
+    NAME_CLASSES
+        .iter()
+        .map(|&(c, class)| {
+            let count = position.piece_boards[create_piece(side, class)].count_ones();
+            c.to_string().repeat(count as usize)
+        })
+        .collect()
+}
+
+fn is_capture(m: &Move) -> bool {
+    matches!(
+        m,
+        Move::Normal { capture: Some(_), .. }
+            | Move::Promote { capture: Some(_), .. }
+            | Move::Enpassant { .. }
+    )
+}
+
+fn is_pawn_move(m: &Move) -> bool {
+    match m {
+        Move::Normal { moving, .. } => piece_class(*moving) == class::P,
+        Move::Promote { .. } | Move::Enpassant { .. } => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_table_name, material};
+    use crate::constants::side;
+    use crate::position::Position;
+
+    #[test]
+    fn table_names() {
+        assert!(is_table_name("KQvK"));
+        assert!(is_table_name("KRPvKR"));
+        assert!(is_table_name("KBNvK"));
+        assert!(!is_table_name("KNBvK"));
+        assert!(!is_table_name("QKvK"));
+        assert!(!is_table_name("KQK"));
+    }
+
+    #[test]
+    fn position_material() {
+        let position: Position = "8/8/3k4/8/2r5/8/1PPK4/8 w - - 0 1".parse().unwrap();
+        assert_eq!("KPP", material(&position, side::W));
+        assert_eq!("KR", material(&position, side::B));
+    }
+}
@@ -0,0 +1,857 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+
+use crate::board::iter;
+use crate::constants::{class, create_piece, piece_class, piece_side, side};
+use crate::position::Position;
+use crate::Piece;
+
+// The tables are a port of the Syzygy probing code in Stockfish which uses the
+// numbering A1 = 0, B1 = 1, .., H8 = 63 for squares and the piece codes below,
+// our own square and piece numbering is converted at the boundary.
+
+const WDL_MAGIC: [u8; 4] = [0x71, 0xE8, 0x23, 0x5D];
+const DTZ_MAGIC: [u8; 4] = [0xD7, 0x66, 0x0C, 0xA5];
+
+const HEADER_SPLIT: u8 = 1;
+const HEADER_HAS_PAWNS: u8 = 2;
+
+const FLAG_STM: u8 = 1;
+const FLAG_MAPPED: u8 = 2;
+const FLAG_WIN_PLIES: u8 = 4;
+const FLAG_LOSS_PLIES: u8 = 8;
+const FLAG_WIDE: u8 = 16;
+const FLAG_SINGLE_VALUE: u8 = 128;
+
+/// The order pieces are written in table names, strongest first
+pub(crate) const NAME_CLASSES: [(char, usize); 6] = [
+    ('K', class::K),
+    ('Q', class::Q),
+    ('R', class::R),
+    ('B', class::B),
+    ('N', class::N),
+    ('P', class::P),
+];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum TableKind {
+    Wdl,
+    Dtz,
+}
+
+impl TableKind {
+    pub(crate) fn extension(&self) -> &'static str {
+        match self {
+            TableKind::Wdl => "rtbw",
+            TableKind::Dtz => "rtbz",
+        }
+    }
+
+    fn magic(&self) -> [u8; 4] {
+        match self {
+            TableKind::Wdl => WDL_MAGIC,
+            TableKind::Dtz => DTZ_MAGIC,
+        }
+    }
+}
+
+/// The result of probing a single table
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) enum TableValue {
+    Value(i32),
+    /// Dtz tables only store one side to move, the other must be searched
+    ChangeSide,
+}
+
+/// Lookup tables used to map piece placements to an index in a table
+struct Encoding {
+    map_pawns: [u64; 64],
+    map_b1h1h7: [u64; 64],
+    map_a1d1d4: [u64; 64],
+    map_kk: [[u64; 64]; 10],
+    binomial: [[u64; 64]; 6],
+    lead_pawn_idx: [[u64; 64]; 6],
+    lead_pawns_size: [[u64; 4]; 6],
+}
+
+lazy_static! {
+    static ref ENCODING: Encoding = Encoding::new();
+}
+
+fn file(sq: usize) -> usize {
+    sq & 7
+}
+
+fn rank(sq: usize) -> usize {
+    sq >> 3
+}
+
+/// Signed distance of a square above the a1-h8 diagonal
+fn off_a1h8(sq: usize) -> i32 {
+    rank(sq) as i32 - file(sq) as i32
+}
+
+impl Encoding {
+    fn new() -> Encoding {
+        let mut e = Encoding {
+            map_pawns: [0; 64],
+            map_b1h1h7: [0; 64],
+            map_a1d1d4: [u64::MAX; 64],
+            map_kk: [[0; 64]; 10],
+            binomial: [[0; 64]; 6],
+            lead_pawn_idx: [[0; 64]; 6],
+            lead_pawns_size: [[0; 4]; 6],
+        };
+
+        let mut code = 0;
+        for sq in 0..64 {
+            if off_a1h8(sq) < 0 {
+                e.map_b1h1h7[sq] = code;
+                code += 1;
+            }
+        }
+
+        // Squares of the a1-d1-d4 triangle, those on the diagonal encoded last
+        let mut diagonal = vec![];
+        code = 0;
+        for sq in 0..28 {
+            if off_a1h8(sq) < 0 && file(sq) <= 3 {
+                e.map_a1d1d4[sq] = code;
+                code += 1;
+            } else if off_a1h8(sq) == 0 && file(sq) <= 3 {
+                diagonal.push(sq);
+            }
+        }
+        for sq in diagonal {
+            e.map_a1d1d4[sq] = code;
+            code += 1;
+        }
+
+        // The 462 legal placements of two kings with the first in the triangle
+        let mut both_on_diagonal = vec![];
+        code = 0;
+        for idx in 0..10 {
+            let firsts: Vec<_> = (0..28).filter(|&s| e.map_a1d1d4[s] == idx).collect();
+            for s1 in firsts {
+                for s2 in 0..64 {
+                    let adjacent =
+                        file(s1).abs_diff(file(s2)) <= 1 && rank(s1).abs_diff(rank(s2)) <= 1;
+                    // Skip illegal placements and the second king above the diagonal
+                    if adjacent || (off_a1h8(s1) == 0 && off_a1h8(s2) > 0) {
+                        continue;
+                    } else if off_a1h8(s1) == 0 && off_a1h8(s2) == 0 {
+                        both_on_diagonal.push((idx as usize, s2));
+                    } else {
+                        e.map_kk[idx as usize][s2] = code;
+                        code += 1;
+                    }
+                }
+            }
+        }
+        for (idx, s2) in both_on_diagonal {
+            e.map_kk[idx][s2] = code;
+            code += 1;
+        }
+
+        e.binomial[0][0] = 1;
+        for n in 1..64 {
+            for k in 0..6.min(n + 1) {
+                e.binomial[k][n] = (if k > 0 { e.binomial[k - 1][n - 1] } else { 0 })
+                    + (if k < n { e.binomial[k][n - 1] } else { 0 });
+            }
+        }
+
+        // Pawn squares a2-h7 encoded to 0..47 with the queenside files first
+        let mut available = 48;
+        for lead_count in 1..6 {
+            for f in 0..4 {
+                let mut idx = 0;
+                for r in 1..7 {
+                    let sq = 8 * r + f;
+                    if lead_count == 1 {
+                        e.map_pawns[sq] = available - 1;
+                        e.map_pawns[sq ^ 7] = available - 2;
+                        available -= 2;
+                    }
+                    e.lead_pawn_idx[lead_count][sq] = idx;
+                    idx += e.binomial[lead_count - 1][e.map_pawns[sq] as usize];
+                }
+                e.lead_pawns_size[lead_count][f] = idx;
+            }
+        }
+        e
+    }
+}
+
+/// The decoding information for one side to move and lead pawn file of a table
+#[derive(Debug, Clone, Default)]
+struct PairsData {
+    flags: u8,
+    block_size: u64,
+    span: u64,
+    num_blocks: u64,
+    min_sym_len: u8,
+    lowest_sym: Vec<u16>,
+    base64: Vec<u64>,
+    symlen: Vec<u8>,
+    btree: Vec<[u8; 3]>,
+    sparse_index_size: usize,
+    sparse_index: Vec<u8>,
+    block_length_size: usize,
+    block_length: Vec<u8>,
+    /// File offset of the first compressed block
+    data: u64,
+    pieces: [u8; 7],
+    group_idx: [u64; 8],
+    group_len: [usize; 8],
+    map_idx: [usize; 4],
+}
+
+impl PairsData {
+    fn left(&self, sym: usize) -> usize {
+        let lr = self.btree[sym];
+        (((lr[1] & 0xF) as usize) << 8) | lr[0] as usize
+    }
+
+    fn right(&self, sym: usize) -> usize {
+        let lr = self.btree[sym];
+        ((lr[2] as usize) << 4) | (lr[1] >> 4) as usize
+    }
+
+    fn block_length(&self, block: usize) -> i64 {
+        u16::from_le_bytes([self.block_length[2 * block], self.block_length[2 * block + 1]]) as i64
+    }
+
+    fn set_symlen(&mut self, sym: usize, visited: &mut [bool]) -> u8 {
+        visited[sym] = true;
+        let right = self.right(sym);
+        if right == 0xFFF {
+            return 0;
+        }
+        let left = self.left(sym);
+        if !visited[left] {
+            self.symlen[left] = self.set_symlen(left, visited);
+        }
+        if !visited[right] {
+            self.symlen[right] = self.set_symlen(right, visited);
+        }
+        self.symlen[left].wrapping_add(self.symlen[right]).wrapping_add(1)
+    }
+}
+
+/// Everything read from a table file besides the compressed data itself
+struct Layout {
+    /// Indexed by side to move then by the file of the lead pawn
+    items: Vec<Vec<PairsData>>,
+    /// Maps stored dtz values to real ones
+    map: Vec<u8>,
+}
+
+impl Layout {
+    fn get(&self, stm: usize, file: usize) -> &PairsData {
+        &self.items[stm.min(self.items.len() - 1)][file.min(self.items[0].len() - 1)]
+    }
+}
+
+/// A single table file, only the material described by its name is read on
+/// construction, the rest of the file is parsed the first time it is probed.
+pub(crate) struct Table {
+    kind: TableKind,
+    file: File,
+    file_len: u64,
+    symmetric: bool,
+    has_pawns: bool,
+    has_unique_pieces: bool,
+    piece_count: usize,
+    /// Pawns of the leading side followed by those of the other side
+    pawn_count: [usize; 2],
+    layout: OnceLock<Result<Layout>>,
+}
+
+impl Table {
+    /// Open a table with a name such as "KRPvKR"
+    pub(crate) fn open(path: &Path, name: &str, kind: TableKind) -> Result<Table> {
+        let (white, black) =
+            name.split_once('v').ok_or_else(|| anyhow!("Invalid table name {}", name))?;
+        let count = |material: &str, c: char| material.chars().filter(|&x| x == c).count();
+        let (white_pawns, black_pawns) = (count(white, 'P'), count(black, 'P'));
+        let lead_white = black_pawns == 0 || (white_pawns > 0 && black_pawns >= white_pawns);
+        let file = File::open(path)?;
+        Ok(Table {
+            kind,
+            file_len: file.metadata()?.len(),
+            file,
+            symmetric: white == black,
+            has_pawns: white_pawns + black_pawns > 0,
+            has_unique_pieces: NAME_CLASSES[1..]
+                .iter()
+                .any(|&(c, _)| count(white, c) == 1 || count(black, c) == 1),
+            piece_count: white.len() + black.len(),
+            pawn_count: if lead_white {
+                [white_pawns, black_pawns]
+            } else {
+                [black_pawns, white_pawns]
+            },
+            layout: OnceLock::new(),
+        })
+    }
+
+    fn layout(&self) -> Result<&Layout> {
+        self.layout
+            .get_or_init(|| self.read_layout())
+            .as_ref()
+            .map_err(|e| anyhow!("Failed to read {:?} table: {}", self.kind, e))
+    }
+
+    fn read_layout(&self) -> Result<Layout> {
+        let mut cursor = Cursor { table: self, pos: 0 };
+        if cursor.bytes(4)? != self.kind.magic() {
+            return Err(anyhow!("Bad magic number"));
+        }
+        let header = cursor.u8()?;
+        if (header & HEADER_HAS_PAWNS != 0) != self.has_pawns
+            || (header & HEADER_SPLIT != 0) == self.symmetric
+        {
+            return Err(anyhow!("Header does not match the table name"));
+        }
+        let sides = if self.kind == TableKind::Wdl && !self.symmetric { 2 } else { 1 };
+        let files = if self.has_pawns { 4 } else { 1 };
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut items = vec![vec![PairsData::default(); files]; sides];
+
+        for f in 0..files {
+            let b0 = cursor.u8()?;
+            let b1 = if pp { cursor.u8()? } else { 0 };
+            let order = [
+                [b0 & 0xF, if pp { b1 & 0xF } else { 0xF }],
+                [b0 >> 4, if pp { b1 >> 4 } else { 0xF }],
+            ];
+            for k in 0..self.piece_count {
+                let b = cursor.u8()?;
+                for (i, side_items) in items.iter_mut().enumerate() {
+                    side_items[f].pieces[k] = if i == 1 { b >> 4 } else { b & 0xF };
+                }
+            }
+            for (i, side_items) in items.iter_mut().enumerate() {
+                self.set_groups(&mut side_items[f], order[i], f);
+            }
+        }
+        cursor.align(2);
+
+        for f in 0..files {
+            for side_items in items.iter_mut() {
+                read_sizes(&mut side_items[f], &mut cursor)?;
+            }
+        }
+
+        let map = if self.kind == TableKind::Dtz {
+            read_dtz_map(&mut items[0], &mut cursor)?
+        } else {
+            vec![]
+        };
+
+        for f in 0..files {
+            for side_items in items.iter_mut() {
+                let d = &mut side_items[f];
+                d.sparse_index = cursor.bytes(6 * d.sparse_index_size)?;
+            }
+        }
+        for f in 0..files {
+            for side_items in items.iter_mut() {
+                let d = &mut side_items[f];
+                d.block_length = cursor.bytes(2 * d.block_length_size)?;
+            }
+        }
+        for f in 0..files {
+            for side_items in items.iter_mut() {
+                let d = &mut side_items[f];
+                cursor.align(64);
+                d.data = cursor.pos;
+                cursor.pos += d.num_blocks * d.block_size;
+            }
+        }
+        Ok(Layout { items, map })
+    }
+
+    fn set_groups(&self, d: &mut PairsData, order: [u8; 2], f: usize) {
+        let encoding = &*ENCODING;
+        let mut n = 0;
+        let mut first_len: i32 = if self.has_pawns {
+            0
+        } else if self.has_unique_pieces {
+            3
+        } else {
+            2
+        };
+        d.group_len[n] = 1;
+        for i in 1..self.piece_count {
+            first_len -= 1;
+            if first_len > 0 || d.pieces[i] == d.pieces[i - 1] {
+                d.group_len[n] += 1;
+            } else {
+                n += 1;
+                d.group_len[n] = 1;
+            }
+        }
+        n += 1;
+        d.group_len[n] = 0;
+
+        // The groups are encoded in the order given by the table
+        let pp = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = if pp { 2 } else { 1 };
+        let mut free_squares = 64 - d.group_len[0] - if pp { d.group_len[1] } else { 0 };
+        let mut idx = 1u64;
+        let mut k = 0u8;
+        while next < n || k == order[0] || k == order[1] {
+            if k == order[0] {
+                d.group_idx[0] = idx;
+                idx *= if self.has_pawns {
+                    encoding.lead_pawns_size[d.group_len[0]][f]
+                } else if self.has_unique_pieces {
+                    31332
+                } else {
+                    462
+                };
+            } else if k == order[1] {
+                d.group_idx[1] = idx;
+                idx *= encoding.binomial[d.group_len[1]][48 - d.group_len[0]];
+            } else {
+                d.group_idx[next] = idx;
+                idx *= encoding.binomial[d.group_len[next]][free_squares];
+                free_squares -= d.group_len[next];
+                next += 1;
+            }
+            k += 1;
+        }
+        d.group_idx[n] = idx;
+    }
+
+    /// Probe the table for the given position which must have the material
+    /// of the table, possibly with colours reversed. The wdl value of the
+    /// position is required when probing a dtz table.
+    pub(crate) fn probe(
+        &self,
+        position: &Position,
+        black_stronger: bool,
+        wdl: i32,
+    ) -> Result<TableValue> {
+        let encoding = &*ENCODING;
+        let layout = self.layout()?;
+
+        // Tables are stored from the perspective of the stronger side with white
+        // to move for symmetric material, otherwise flip colours and ranks
+        let flip = black_stronger || (self.symmetric && position.active == side::B);
+        let flip_color = if flip { 8 } else { 0 };
+        let flip_squares = if flip { 56 } else { 0 };
+        let stm = (flip as usize) ^ position.active;
+
+        let mut squares = [0usize; 7];
+        let mut pieces = [0u8; 7];
+        let mut size = 0;
+        let mut lead_pawns = 0u64;
+        let mut tb_file = 0;
+
+        if self.has_pawns {
+            let pc = layout.get(0, 0).pieces[0] ^ flip_color;
+            let lead_side = if pc & 8 == 0 { side::W } else { side::B };
+            lead_pawns = position.piece_boards[create_piece(lead_side, class::P)];
+            for sq in iter(lead_pawns).map(to_tb_square) {
+                squares[size] = sq ^ flip_squares;
+                size += 1;
+            }
+            let lead = (0..size).max_by_key(|&i| encoding.map_pawns[squares[i]]).unwrap_or(0);
+            squares.swap(0, lead);
+            tb_file = file(squares[0]).min(7 - file(squares[0]));
+        }
+        let lead_pawns_count = size;
+
+        // Symmetric pawnless tables can always be probed by flipping colours
+        let one_sided = !self.symmetric || self.has_pawns;
+        if self.kind == TableKind::Dtz
+            && one_sided
+            && (layout.get(stm, tb_file).flags & FLAG_STM) as usize != stm
+        {
+            return Ok(TableValue::ChangeSide);
+        }
+
+        for sq in 0..64 {
+            let ours = sq ^ 7;
+            if let Some(p) = position.piece_locs[ours] {
+                if lead_pawns & (1u64 << ours) == 0 {
+                    squares[size] = sq ^ flip_squares;
+                    pieces[size] = to_tb_piece(p) ^ flip_color;
+                    size += 1;
+                }
+            }
+        }
+        if size != self.piece_count {
+            return Err(anyhow!("Position does not match the table material"));
+        }
+
+        // Reorder the pieces to match the sequence in the table
+        let d = layout.get(stm, tb_file);
+        for i in lead_pawns_count..size - 1 {
+            for j in i + 1..size {
+                if d.pieces[i] == pieces[j] {
+                    pieces.swap(i, j);
+                    squares.swap(i, j);
+                    break;
+                }
+            }
+        }
+
+        if file(squares[0]) > 3 {
+            squares[..size].iter_mut().for_each(|sq| *sq ^= 7);
+        }
+
+        let mut idx;
+        if self.has_pawns {
+            idx = encoding.lead_pawn_idx[lead_pawns_count][squares[0]];
+            squares[1..lead_pawns_count].sort_by_key(|&sq| encoding.map_pawns[sq]);
+            for (i, &sq) in squares.iter().enumerate().take(lead_pawns_count).skip(1) {
+                idx += encoding.binomial[i][encoding.map_pawns[sq] as usize];
+            }
+        } else {
+            if rank(squares[0]) > 3 {
+                squares[..size].iter_mut().for_each(|sq| *sq ^= 56);
+            }
+            // Flip along the a1-h8 diagonal if the first piece off it is above it
+            for i in 0..d.group_len[0] {
+                match off_a1h8(squares[i]) {
+                    0 => continue,
+                    off if off > 0 => squares[i..size]
+                        .iter_mut()
+                        .for_each(|sq| *sq = ((*sq >> 3) | (*sq << 3)) & 63),
+                    _ => {}
+                }
+                break;
+            }
+
+            if self.has_unique_pieces {
+                let (s0, s1, s2) = (squares[0], squares[1], squares[2]);
+                let adjust1 = (s1 > s0) as usize;
+                let adjust2 = (s2 > s0) as usize + (s2 > s1) as usize;
+                idx = if off_a1h8(s0) != 0 {
+                    (encoding.map_a1d1d4[s0] * 63 + (s1 - adjust1) as u64) * 62
+                        + (s2 - adjust2) as u64
+                } else if off_a1h8(s1) != 0 {
+                    (6 * 63 + rank(s0) as u64 * 28 + encoding.map_b1h1h7[s1]) * 62
+                        + (s2 - adjust2) as u64
+                } else if off_a1h8(s2) != 0 {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + rank(s0) as u64 * 7 * 28
+                        + (rank(s1) - adjust1) as u64 * 28
+                        + encoding.map_b1h1h7[s2]
+                } else {
+                    6 * 63 * 62
+                        + 4 * 28 * 62
+                        + 4 * 7 * 28
+                        + rank(s0) as u64 * 7 * 6
+                        + (rank(s1) - adjust1) as u64 * 6
+                        + (rank(s2) - adjust2) as u64
+                };
+            } else {
+                idx = encoding.map_kk[encoding.map_a1d1d4[squares[0]] as usize][squares[1]];
+            }
+        }
+
+        // Encode the remaining groups of identical pieces
+        idx *= d.group_idx[0];
+        let mut group_start = d.group_len[0];
+        let mut remaining_pawns = self.has_pawns && self.pawn_count[1] > 0;
+        let mut next = 1;
+        while d.group_len[next] != 0 {
+            let group_len = d.group_len[next];
+            squares[group_start..group_start + group_len].sort();
+            let mut n = 0;
+            for i in 0..group_len {
+                let sq = squares[group_start + i];
+                let adjust = squares[..group_start].iter().filter(|&&s| sq > s).count();
+                let pawn_adjust = if remaining_pawns { 8 } else { 0 };
+                n += encoding.binomial[i + 1][sq - adjust - pawn_adjust];
+            }
+            remaining_pawns = false;
+            idx += n * d.group_idx[next];
+            group_start += group_len;
+            next += 1;
+        }
+
+        let value = self.decompress(d, idx)?;
+        Ok(TableValue::Value(match self.kind {
+            TableKind::Wdl => value - 2,
+            TableKind::Dtz => map_dtz(d, &layout.map, value, wdl),
+        }))
+    }
+
+    fn decompress(&self, d: &PairsData, idx: u64) -> Result<i32> {
+        if d.flags & FLAG_SINGLE_VALUE != 0 {
+            return Ok(d.min_sym_len as i32);
+        }
+        let k = (idx / d.span) as usize;
+        let entry = &d.sparse_index[6 * k..6 * k + 6];
+        let mut block = u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]) as usize;
+        let mut offset = u16::from_le_bytes([entry[4], entry[5]]) as i64;
+        offset += (idx % d.span) as i64 - (d.span / 2) as i64;
+        while offset < 0 {
+            block -= 1;
+            offset += d.block_length(block) + 1;
+        }
+        while offset > d.block_length(block) {
+            offset -= d.block_length(block) + 1;
+            block += 1;
+        }
+
+        // Symbols are read past the end of the block so leave some slack
+        let start = d.data + block as u64 * d.block_size;
+        let bytes = self.read_padded(start, d.block_size as usize + 8)?;
+        let word =
+            |i: usize| u32::from_be_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let mut buf64 = ((word(0) as u64) << 32) | word(4) as u64;
+        let mut ptr = 8;
+        let mut buf64_size = 64;
+        let min_sym_len = d.min_sym_len as usize;
+        let mut sym;
+        loop {
+            let mut len = 0;
+            while buf64 < d.base64[len] {
+                len += 1;
+            }
+            sym = ((buf64 - d.base64[len]) >> (64 - len - min_sym_len)) as usize;
+            sym += d.lowest_sym[len] as usize;
+            if offset < d.symlen[sym] as i64 + 1 {
+                break;
+            }
+            offset -= d.symlen[sym] as i64 + 1;
+            len += min_sym_len;
+            buf64 <<= len;
+            buf64_size -= len;
+            if buf64_size <= 32 {
+                buf64_size += 32;
+                if ptr + 4 > bytes.len() {
+                    return Err(anyhow!("Corrupt block {}", block));
+                }
+                buf64 |= (word(ptr) as u64) << (64 - buf64_size);
+                ptr += 4;
+            }
+        }
+
+        // Expand the pair symbol until we reach the value at the offset
+        while d.symlen[sym] != 0 {
+            let left = d.left(sym);
+            if offset < d.symlen[left] as i64 + 1 {
+                sym = left;
+            } else {
+                offset -= d.symlen[left] as i64 + 1;
+                sym = d.right(sym);
+            }
+        }
+        Ok(d.left(sym) as i32)
+    }
+
+    fn read(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        read_at(&self.file, &mut buf, offset)?;
+        Ok(buf)
+    }
+
+    fn read_padded(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        let available = self.file_len.saturating_sub(offset).min(len as u64) as usize;
+        let mut buf = vec![0u8; len];
+        read_at(&self.file, &mut buf[..available], offset)?;
+        Ok(buf)
+    }
+}
+
+fn map_dtz(d: &PairsData, map: &[u8], value: i32, wdl: i32) -> i32 {
+    const WDL_MAP: [usize; 5] = [1, 3, 0, 2, 0];
+    let mut value = value;
+    if d.flags & FLAG_MAPPED != 0 {
+        let idx = d.map_idx[WDL_MAP[(wdl + 2) as usize]] + value as usize;
+        value = if d.flags & FLAG_WIDE != 0 {
+            u16::from_le_bytes([map[2 * idx], map[2 * idx + 1]]) as i32
+        } else {
+            map[idx] as i32
+        };
+    }
+    // Values are stored in moves unless flagged as plies
+    if (wdl == 2 && d.flags & FLAG_WIN_PLIES == 0)
+        || (wdl == -2 && d.flags & FLAG_LOSS_PLIES == 0)
+        || wdl == 1
+        || wdl == -1
+    {
+        value *= 2;
+    }
+    value + 1
+}
+
+fn read_sizes(d: &mut PairsData, cursor: &mut Cursor) -> Result<()> {
+    d.flags = cursor.u8()?;
+    if d.flags & FLAG_SINGLE_VALUE != 0 {
+        // The single value every position in the table maps to
+        d.min_sym_len = cursor.u8()?;
+        return Ok(());
+    }
+    let table_size = d.group_idx[d.group_len.iter().position(|&len| len == 0).unwrap_or(7)];
+    d.block_size = 1 << cursor.u8()?;
+    d.span = 1 << cursor.u8()?;
+    d.sparse_index_size = table_size.div_ceil(d.span) as usize;
+    let padding = cursor.u8()? as usize;
+    d.num_blocks = cursor.u32()? as u64;
+    d.block_length_size = d.num_blocks as usize + padding;
+    let max_sym_len = cursor.u8()?;
+    d.min_sym_len = cursor.u8()?;
+    if max_sym_len < d.min_sym_len || d.min_sym_len == 0 {
+        return Err(anyhow!("Invalid symbol lengths"));
+    }
+    let n = (max_sym_len - d.min_sym_len + 1) as usize;
+    d.lowest_sym = (0..n).map(|_| cursor.u16()).collect::<Result<Vec<_>>>()?;
+
+    // Canonical Huffman code bases, left aligned in 64 bits
+    d.base64 = vec![0; n];
+    for i in (0..n - 1).rev() {
+        d.base64[i] = d.base64[i + 1]
+            .wrapping_add(d.lowest_sym[i] as u64)
+            .wrapping_sub(d.lowest_sym[i + 1] as u64)
+            / 2;
+    }
+    for (i, base) in d.base64.iter_mut().enumerate() {
+        *base <<= 64 - i - d.min_sym_len as usize;
+    }
+
+    let symbols = cursor.u16()? as usize;
+    d.btree = cursor.bytes(3 * symbols)?.chunks(3).map(|c| [c[0], c[1], c[2]]).collect();
+    d.symlen = vec![0; symbols];
+    let mut visited = vec![false; symbols];
+    for sym in 0..symbols {
+        if !visited[sym] {
+            d.symlen[sym] = d.set_symlen(sym, &mut visited);
+        }
+    }
+    cursor.pos += (symbols & 1) as u64;
+    Ok(())
+}
+
+fn read_dtz_map(items: &mut [PairsData], cursor: &mut Cursor) -> Result<Vec<u8>> {
+    let start = cursor.pos;
+    for d in items.iter_mut().filter(|d| d.flags & FLAG_MAPPED != 0) {
+        if d.flags & FLAG_WIDE != 0 {
+            cursor.align(2);
+            for i in 0..4 {
+                d.map_idx[i] = ((cursor.pos - start) / 2 + 1) as usize;
+                let len = cursor.u16()? as u64;
+                cursor.pos += 2 * len;
+            }
+        } else {
+            for i in 0..4 {
+                d.map_idx[i] = (cursor.pos - start + 1) as usize;
+                let len = cursor.u8()? as u64;
+                cursor.pos += len;
+            }
+        }
+    }
+    cursor.align(2);
+    cursor.table.read(start, (cursor.pos - start) as usize)
+}
+
+/// Sequential reads through the header of a table file
+struct Cursor<'a> {
+    table: &'a Table,
+    pos: u64,
+}
+
+impl Cursor<'_> {
+    fn bytes(&mut self, len: usize) -> Result<Vec<u8>> {
+        let bytes = self.table.read(self.pos, len)?;
+        self.pos += len as u64;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn align(&mut self, n: u64) {
+        self.pos = self.pos.next_multiple_of(n);
+    }
+}
+
+#[cfg(unix)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    file.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    let mut read = 0;
+    while read < buf.len() {
+        match file.seek_read(&mut buf[read..], offset + read as u64)? {
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => read += n,
+        }
+    }
+    Ok(())
+}
+
+/// Convert one of our squares (H1 = 0) to the table numbering (A1 = 0)
+fn to_tb_square(sq: usize) -> usize {
+    sq ^ 7
+}
+
+fn to_tb_piece(piece: Piece) -> u8 {
+    (piece_class(piece) + 1 + 8 * piece_side(piece)) as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Encoding, ENCODING};
+
+    #[test]
+    fn king_placements() {
+        let encoding = Encoding::new();
+        let max = encoding.map_kk.iter().flat_map(|row| row.iter()).max().cloned().unwrap();
+        assert_eq!(461, max);
+    }
+
+    #[test]
+    fn triangle_squares() {
+        let mut codes: Vec<_> =
+            ENCODING.map_a1d1d4.iter().cloned().filter(|&c| c != u64::MAX).collect();
+        codes.sort();
+        assert_eq!((0..10).collect::<Vec<_>>(), codes);
+    }
+
+    #[test]
+    fn binomial_coefficients() {
+        assert_eq!(1, ENCODING.binomial[0][10]);
+        assert_eq!(10, ENCODING.binomial[1][10]);
+        assert_eq!(45, ENCODING.binomial[2][10]);
+        assert_eq!(7_028_847, ENCODING.binomial[5][63]);
+    }
+
+    #[test]
+    fn pawn_squares() {
+        let mut codes: Vec<_> = (8..56).map(|sq| ENCODING.map_pawns[sq]).collect();
+        codes.sort();
+        assert_eq!((0..48).collect::<Vec<_>>(), codes);
+        // A single lead pawn may stand on any of the six ranks of its file
+        assert_eq!([6, 6, 6, 6], ENCODING.lead_pawns_size[1]);
+    }
+}
@@ -1,8 +1,12 @@
+use crate::epd::EpdEntry;
 use crate::moves::Move;
 use crate::node::TreeNode;
 use crate::position::Position;
-use crate::search::{SearchParameters, TranspositionsImpl};
-use crate::{node, Symmetric};
+use crate::search::{
+    ConcurrentTranspositions, MultiPv, ParallelSearchParameters, Score, SearchOutcome,
+    SearchParameters, TranspositionsImpl,
+};
+use crate::Symmetric;
 
 const TABLE_SIZE: usize = 10_000;
 
@@ -21,8 +25,32 @@ fn test(position: &str, expected_move_pool: Vec<&str>, is_won: bool, depth: usiz
 
 fn test_impl(board: TreeNode, expected_move_pool: Vec<Move>, is_won: bool, depth: usize) {
     let mut table = TranspositionsImpl::new(TABLE_SIZE);
-    let params = SearchParameters { end: depth, table: &mut table };
-    match crate::search::search(board, params) {
+    let params = SearchParameters {
+        end: depth,
+        table: &mut table,
+        observer: &mut (),
+        multi_pv: MultiPv::default(),
+        tablebase: None,
+    };
+    check_outcome(crate::search::search(board.clone(), params), &expected_move_pool, is_won);
+    let table = ConcurrentTranspositions::new(TABLE_SIZE);
+    let params = ParallelSearchParameters {
+        end: depth,
+        table: &table,
+        helpers: 3,
+        observer: &mut (),
+        multi_pv: MultiPv::default(),
+        tablebase: None,
+    };
+    check_outcome(crate::search::search_parallel(board, params), &expected_move_pool, is_won);
+}
+
+fn check_outcome(
+    outcome: anyhow::Result<SearchOutcome>,
+    expected_move_pool: &[Move],
+    is_won: bool,
+) {
+    match outcome {
         Err(message) => panic!("{}", message),
         Ok(outcome) => {
             assert!(
@@ -31,8 +59,12 @@ fn test_impl(board: TreeNode, expected_move_pool: Vec<Move>, is_won: bool, depth
                 serde_json::to_string(&outcome).unwrap()
             );
             if is_won {
-                assert_eq!(node::WIN_VALUE, outcome.relative_eval);
+                assert!(matches!(outcome.score, Score::Mate(n) if n > 0), "{:?}", outcome.score);
             }
+            let stats = &outcome.statistics;
+            assert!(stats.qnodes < stats.nodes);
+            assert!(stats.first_move_cutoffs <= stats.cutoffs);
+            assert!(stats.seldepth >= outcome.depth);
         }
     }
 }
@@ -114,3 +146,87 @@ fn win_material() {
 fn enpassant_win_pawn() {
     test("8/6rk/p1p1p2p/1pPqPp2/1PNP4/1PQ5/5RPK/3b4 w - b6 0 49", vec!["c5b6"], false, 1)
 }
+
+#[test]
+fn mate_distance() {
+    let cases = [
+        ("r2r2k1/5ppp/1N2p3/1n6/3Q4/2B5/5PPP/1R3RK1 w - - 4 21", 1),
+        ("8/8/8/4Q3/8/6R1/2n1pkBK/8 w - - 0 1", 2),
+        ("3qr2k/1b1p2pp/7N/3Q2b1/4P3/8/5PP1/6K1 w - - 0 1", 2),
+    ];
+    for (fen, moves) in cases {
+        let position: Position = fen.parse().unwrap();
+        for node in [position.clone(), position.reflect()] {
+            let mut table = TranspositionsImpl::new(TABLE_SIZE);
+            let params = SearchParameters {
+                end: 5,
+                table: &mut table,
+                observer: &mut (),
+                multi_pv: MultiPv::default(),
+                tablebase: None,
+            };
+            let outcome = crate::search::search(node.clone().into(), params).unwrap();
+            assert_eq!(Score::Mate(moves), outcome.score, "{}", fen);
+            // The losing side sees the same mate after the first move
+            if moves > 1 {
+                let mut losing = node;
+                losing.make(outcome.best_move).unwrap();
+                let mut table = TranspositionsImpl::new(TABLE_SIZE);
+                let params = SearchParameters {
+                    end: 5,
+                    table: &mut table,
+                    observer: &mut (),
+                    multi_pv: MultiPv::default(),
+                    tablebase: None,
+                };
+                let outcome = crate::search::search(losing.into(), params).unwrap();
+                assert_eq!(Score::Mate(1 - moves), outcome.score, "{}", fen);
+            }
+        }
+    }
+}
+
+#[test]
+fn epd_suite() {
+    let suite = [
+        "r2r2k1/5ppp/1N2p3/1n6/3Q4/2B5/5PPP/1R3RK1 w - - bm Qxg7#; dm 1; id \"mate_0\";",
+        "8/8/8/4Q3/8/6R1/2n1pkBK/8 w - - bm Rd3; dm 2; id \"mate_1\";",
+        "3qr2k/1b1p2pp/7N/3Q2b1/4P3/8/5PP1/6K1 w - - bm Qg8+; dm 2; id \"mate_3\";",
+        "8/6rk/p1p1p2p/1pPqPp2/1PNP4/1PQ5/5RPK/3b4 w - b6 bm cxb6; id \"enpassant_win_pawn\";",
+    ];
+    for line in suite {
+        let entry: EpdEntry = line.parse().unwrap();
+        let mut table = TranspositionsImpl::new(TABLE_SIZE);
+        let params = SearchParameters {
+            end: 4,
+            table: &mut table,
+            observer: &mut (),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
+        };
+        let outcome = crate::search::search(entry.position.clone().into(), params).unwrap();
+        assert!(entry.is_solved(&outcome.best_move, outcome.score), "{}", line);
+    }
+}
+
+#[test]
+fn principal_variation_survives_table_hits() {
+    let position: Position = "r4rk1/5ppp/8/1Bn1p3/Q7/8/5PPP/1R3RK1 w Qq - 5 27".parse().unwrap();
+    let table = ConcurrentTranspositions::new(TABLE_SIZE);
+    let search = || {
+        let params = ParallelSearchParameters {
+            end: 4,
+            table: &table,
+            helpers: 0,
+            observer: &mut (),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
+        };
+        crate::search::search_parallel(position.clone().into(), params).unwrap()
+    };
+    let first = search();
+    // The second search finds every child of the root in the table
+    let second = search();
+    assert_eq!(4, first.optimal_path.len(), "{:?}", first.optimal_path);
+    assert_eq!(first.optimal_path, second.optimal_path);
+}
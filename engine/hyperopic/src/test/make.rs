@@ -18,7 +18,7 @@ fn execute_test(from_fen: &str, m: Move, dest_fen: &str) {
 fn white_kingside_castle() {
     execute_test(
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R w KQkq - 0 1",
-        Move::Castle { corner: corner::WK },
+        Move::Castle { corner: corner::WK, king: E1, rook: H1 },
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R4RK1 b kq - 1 1",
     );
 }
@@ -27,7 +27,7 @@ fn white_kingside_castle() {
 fn white_queenside_castle() {
     execute_test(
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R w KQkq - 0 1",
-        Move::Castle { corner: corner::WQ },
+        Move::Castle { corner: corner::WQ, king: E1, rook: A1 },
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/2KR3R b kq - 1 1",
     );
 }
@@ -36,7 +36,7 @@ fn white_queenside_castle() {
 fn black_kingside_castle() {
     execute_test(
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R b KQkq - 0 1",
-        Move::Castle { corner: corner::BK },
+        Move::Castle { corner: corner::BK, king: E8, rook: H8 },
         "r4rk1/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R w KQ - 1 2",
     );
 }
@@ -45,7 +45,7 @@ fn black_kingside_castle() {
 fn black_queenside_castle() {
     execute_test(
         "r3k2r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R b KQkq - 0 1",
-        Move::Castle { corner: corner::BQ },
+        Move::Castle { corner: corner::BQ, king: E8, rook: A8 },
         "2kr3r/p2qpp2/1n1b4/2p5/2B5/1N6/2Q2PP1/R3K2R w KQ - 1 2",
     );
 }
@@ -184,3 +184,16 @@ fn enpassant() {
         "8/6rk/pPp1p2p/3qPp2/1PNP4/1PQ5/5RPK/3b4 b - - 0 49",
     )
 }
+
+#[test]
+fn chess960_castle_onto_rook_square() {
+    let mut position: Position = "1r2k1r1/8/8/8/8/8/8/1R2K1R1 w GBgb - 0 1".parse().unwrap();
+    let original = position.clone();
+    position.make(Move::Castle { corner: corner::WK, king: E1, rook: G1 }).unwrap();
+    assert_eq!("1r2k1r1/8/8/8/8/8/8/1R3RK1 b kq - 1 1", position.to_string());
+    position.make(Move::Castle { corner: corner::BQ, king: E8, rook: B8 }).unwrap();
+    assert_eq!("2kr2r1/8/8/8/8/8/8/1R3RK1 w - - 2 2", position.to_string());
+    position.unmake().unwrap();
+    position.unmake().unwrap();
+    assert_eq!(original, position);
+}
@@ -11,7 +11,10 @@ pub(crate) mod facets;
 mod make;
 mod move_comparison;
 mod moves;
+mod multi_pv;
+mod perft;
 mod pinned;
+mod tablebase;
 mod termination;
 
 pub fn assert_boards_equal(expected: Board, actual: Board) {
@@ -28,7 +31,11 @@ impl Symmetric for Move {
 
         match self {
             Null => Null,
-            Castle { corner } => Castle { corner: reflect_corner(*corner) },
+            Castle { corner, king, rook } => Castle {
+                corner: reflect_corner(*corner),
+                king: reflect_square(*king),
+                rook: reflect_square(*rook),
+            },
             Normal { moving, from, dest, capture } => Normal {
                 moving: reflect_piece(*moving),
                 from: reflect_square(*from),
@@ -1,5 +1,5 @@
 use crate::position::Position;
-use crate::search::{SearchOutcome, SearchParameters, TranspositionsImpl};
+use crate::search::{MultiPv, SearchOutcome, SearchParameters, TranspositionsImpl};
 
 #[test]
 fn sanity_case() {
@@ -71,7 +71,13 @@ fn search_after_move(pgn: &str, mv: &str, depth: usize) -> SearchOutcome {
     board.play(mv).expect(format!("{} invalid on {}", mv, board).as_str());
     crate::search::search(
         board.into(),
-        SearchParameters { end: depth, table: &mut TranspositionsImpl::new(TABLE_SIZE) },
+        SearchParameters {
+            end: depth,
+            table: &mut TranspositionsImpl::new(TABLE_SIZE),
+            observer: &mut (),
+            multi_pv: MultiPv::default(),
+            tablebase: None,
+        },
     )
     .map_err(|e| panic!("Could not search at {}: {}", pgn, e))
     .unwrap()
@@ -8,7 +8,7 @@ use std::collections::BTreeSet;
 use crate::moves::MoveFacet::{Attacking, Checking, Promoting};
 use crate::moves::{Move, Moves};
 use crate::parse::StringIndexMap;
-use crate::position::Position;
+use crate::position::{Position, CASTLING_DETAILS};
 use crate::Symmetric;
 use anyhow::{anyhow, Error, Result};
 use std::str::FromStr;
@@ -44,7 +44,15 @@ impl FromStr for Move {
                     promoted: pieces.index(slice(s, 5, 2)),
                     capture: pieces.index_op(slice(s, 7, 2)),
                 }),
-                'c' => Ok(Move::Castle { corner: corners.index(slice(s, 1, 2)) }),
+                'c' => {
+                    let corner = corners.index(slice(s, 1, 2));
+                    let details = &CASTLING_DETAILS[corner];
+                    Ok(Move::Castle {
+                        corner,
+                        king: details.king_line.0,
+                        rook: details.rook_line.0,
+                    })
+                }
                 _ => Err(anyhow!("Cannot parse {} as a move", s)),
             },
         }
@@ -183,6 +191,9 @@ mod parsing_formatting_test {
 
     #[test]
     fn castle() {
-        assert_eq!(Move::Castle { corner: corner::BK }, Move::from_str("cbk").unwrap());
+        assert_eq!(
+            Move::Castle { corner: corner::BK, king: E8, rook: H8 },
+            Move::from_str("cbk").unwrap()
+        );
     }
 }
@@ -0,0 +1,81 @@
+use crate::moves::Moves;
+use crate::position::Position;
+use crate::search::{
+    ConcurrentTranspositions, MultiPv, ParallelSearchParameters, Score, SearchOutcome,
+    SearchParameters, TranspositionsImpl,
+};
+use itertools::Itertools;
+
+const TABLE_SIZE: usize = 10_000;
+
+fn search(position: &Position, depth: usize, multi_pv: MultiPv) -> SearchOutcome {
+    let mut table = TranspositionsImpl::new(TABLE_SIZE);
+    let params = SearchParameters {
+        end: depth,
+        table: &mut table,
+        observer: &mut (),
+        multi_pv,
+        tablebase: None,
+    };
+    crate::search::search(position.clone().into(), params).unwrap()
+}
+
+fn assert_ranked(outcome: &SearchOutcome) {
+    assert!(outcome.lines.windows(2).all(|w| w[0].relative_eval >= w[1].relative_eval));
+    assert!(outcome.lines.iter().map(|line| &line.root_move).all_unique());
+    assert!(outcome.lines.iter().all(|line| line.path.first() == Some(&line.root_move)));
+    let best = &outcome.lines[0];
+    assert_eq!(outcome.best_move, best.root_move);
+    assert_eq!(outcome.relative_eval, best.relative_eval);
+    assert_eq!(outcome.optimal_path, best.path);
+}
+
+#[test]
+fn top_lines() {
+    let position: Position =
+        "r2r2k1/5ppp/1N2p3/1n6/3Q4/2B5/5PPP/1R3RK1 w - - 4 21".parse().unwrap();
+    let outcome = search(&position, 3, MultiPv::Top(3));
+    assert_eq!(3, outcome.lines.len());
+    assert_ranked(&outcome);
+    assert_eq!("d4g7", outcome.best_move.to_string());
+    assert_eq!(Score::Mate(1), outcome.score);
+    assert!(outcome.lines[1..].iter().all(|line| !matches!(line.score, Score::Mate(n) if n > 0)));
+}
+
+#[test]
+fn single_line_by_default() {
+    let position: Position = "1. e4 e5 2. Nf3 Nc6 3. Bb5".parse().unwrap();
+    let outcome = search(&position, 3, MultiPv::default());
+    assert_eq!(1, outcome.lines.len());
+    assert_ranked(&outcome);
+}
+
+#[test]
+fn all_root_moves() {
+    let position: Position =
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3".parse().unwrap();
+    let legal = position.moves(&Moves::All);
+    let outcome = search(&position, 2, MultiPv::All);
+    assert_eq!(legal.len(), outcome.lines.len());
+    assert_ranked(&outcome);
+    assert!(legal.iter().all(|m| outcome.lines.iter().any(|line| &line.root_move == m)));
+    // Asking for more lines than there are moves is capped
+    assert_eq!(legal.len(), search(&position, 2, MultiPv::Top(100)).lines.len());
+}
+
+#[test]
+fn parallel_lines() {
+    let position: Position = "1. d4 d5 2. c4 e6 3. Nc3".parse().unwrap();
+    let table = ConcurrentTranspositions::new(TABLE_SIZE);
+    let params = ParallelSearchParameters {
+        end: 3,
+        table: &table,
+        helpers: 2,
+        observer: &mut (),
+        multi_pv: MultiPv::Top(2),
+        tablebase: None,
+    };
+    let outcome = crate::search::search_parallel(position.into(), params).unwrap();
+    assert_eq!(2, outcome.lines.len());
+    assert_ranked(&outcome);
+}
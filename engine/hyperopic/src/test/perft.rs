@@ -0,0 +1,175 @@
+use crate::perft::{divide, perft};
+use crate::position::Position;
+
+fn execute_test(fen: &str, expected: &[u64]) {
+    let mut position = fen.parse::<Position>().unwrap();
+    let original = position.clone();
+    for (i, &count) in expected.iter().enumerate() {
+        assert_eq!(count, perft(&mut position, i + 1).unwrap(), "{} at depth {}", fen, i + 1);
+    }
+    assert_eq!(original, position);
+}
+
+#[test]
+fn start_position() {
+    execute_test("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902]);
+}
+
+#[test]
+fn kiwipete() {
+    execute_test(
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97862],
+    );
+}
+
+#[test]
+fn position_3() {
+    execute_test("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]);
+}
+
+#[test]
+fn position_4() {
+    execute_test(
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467],
+    );
+}
+
+#[test]
+fn position_4_mirrored() {
+    execute_test(
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        &[6, 264, 9467],
+    );
+}
+
+#[test]
+fn position_5() {
+    execute_test("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+}
+
+#[test]
+fn position_6() {
+    execute_test(
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2079, 89890],
+    );
+}
+
+fn execute_deep_test(fen: &str, depth: usize, expected: u64) {
+    let mut position = fen.parse::<Position>().unwrap();
+    assert_eq!(expected, perft(&mut position, depth).unwrap(), "{}", fen);
+}
+
+#[test]
+fn illegal_enpassant_exposes_king() {
+    execute_deep_test("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 4, 10138);
+    execute_deep_test("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 4, 10276);
+}
+
+#[test]
+fn enpassant_gives_check() {
+    execute_deep_test("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 4, 13931);
+}
+
+#[test]
+fn castling_gives_check() {
+    execute_deep_test("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 4, 6399);
+    execute_deep_test("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 4, 7418);
+}
+
+#[test]
+fn castling_rights_lost_by_capture() {
+    execute_deep_test("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 3, 27826);
+}
+
+#[test]
+fn castling_prevented_by_attack() {
+    execute_deep_test("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 3, 50509);
+}
+
+#[test]
+fn enpassant_along_pin() {
+    execute_deep_test("6k1/8/8/8/1Pp5/8/B7/6K1 b - b3 0 1", 4, 3480);
+}
+
+#[test]
+fn chess960() {
+    execute_test(
+        "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9",
+        &[21, 528, 12189],
+    );
+    execute_test(
+        "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9",
+        &[21, 807, 18002],
+    );
+    execute_test("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10471]);
+    execute_test(
+        "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9",
+        &[22, 593, 13440],
+    );
+}
+
+#[test]
+fn chess960_castling_rook_shields_king_target() {
+    execute_deep_test("4k3/8/8/8/8/8/8/rR1K4 w B - 0 1", 4, 18483);
+}
+
+#[test]
+fn promotion_out_of_check() {
+    execute_deep_test("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 4, 19174);
+}
+
+#[test]
+fn promotion_gives_check() {
+    execute_deep_test("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", 6, 217342);
+    execute_deep_test("8/P1k5/K7/8/8/8/8/8 w - - 0 1", 6, 92683);
+}
+
+#[test]
+fn discovered_check() {
+    execute_deep_test("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 4, 31961);
+}
+
+#[test]
+fn stalemate_and_checkmate() {
+    execute_deep_test("K1k5/8/P7/8/8/8/8/8 w - - 0 1", 6, 2217);
+    execute_deep_test("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", 4, 23527);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let mut position =
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1".parse().unwrap();
+    let divided = divide(&mut position, 2).unwrap();
+    assert_eq!(48, divided.len());
+    assert_eq!(2039, divided.iter().map(|(_, n)| n).sum::<u64>());
+}
+
+/// The full depth suite is too slow to run with debug assertions enabled
+#[test]
+#[ignore]
+fn deep_suite() {
+    let cases = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", 5, 4865609),
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", 4, 4085603),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 5, 674624),
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", 4, 422333),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", 4, 2103487),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", 4, 3894594),
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", 6, 1134888),
+        ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", 6, 1015133),
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", 6, 1440467),
+        ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", 6, 661072),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", 6, 803711),
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", 4, 1274206),
+        ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", 4, 1720476),
+        ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", 6, 3821001),
+        ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", 5, 1004658),
+        ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", 7, 567584),
+    ];
+    for (fen, depth, expected) in cases {
+        execute_deep_test(fen, depth, expected);
+    }
+}
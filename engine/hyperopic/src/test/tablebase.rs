@@ -0,0 +1,136 @@
+use crate::node;
+use crate::position::Position;
+use crate::search::{MultiPv, SearchOutcome, SearchParameters, TranspositionsImpl};
+use crate::syzygy::test::MockTablebase;
+use crate::syzygy::Wdl;
+
+const TABLE_SIZE: usize = 10_000;
+
+/// White is in check and the only sensible move wins the queen
+const QUEEN_CAPTURE: &str = "4k3/8/8/8/8/8/3q4/R3K3 w - - 0 1";
+
+fn search(fen: &str, tablebase: &MockTablebase, depth: usize) -> SearchOutcome {
+    let position: Position = fen.parse().unwrap();
+    let mut table = TranspositionsImpl::new(TABLE_SIZE);
+    let params = SearchParameters {
+        end: depth,
+        table: &mut table,
+        observer: &mut (),
+        multi_pv: MultiPv::default(),
+        tablebase: Some(tablebase),
+    };
+    crate::search::search(position.into(), params).unwrap()
+}
+
+#[test]
+fn drawn_capture() {
+    let tablebase = MockTablebase { entries: vec![], default: Some((Wdl::Draw, 0)) };
+    let outcome = search(QUEEN_CAPTURE, &tablebase, 3);
+    assert_eq!("e1d2", outcome.best_move.to_string());
+    assert_eq!(node::DRAW_VALUE, outcome.relative_eval);
+    assert!(outcome.statistics.tb_hits > 0);
+}
+
+#[test]
+fn won_capture() {
+    let tablebase = MockTablebase { entries: vec![], default: Some((Wdl::Loss, -9)) };
+    let outcome = search(QUEEN_CAPTURE, &tablebase, 3);
+    assert_eq!("e1d2", outcome.best_move.to_string());
+    assert_eq!(node::tablebase_win_at(1), outcome.relative_eval);
+}
+
+#[test]
+fn root_not_probed() {
+    // The root is drawn according to the tablebase but must still be searched
+    let tablebase = MockTablebase { entries: vec![], default: Some((Wdl::Draw, 0)) };
+    let outcome = search("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", &tablebase, 3);
+    assert!(outcome.relative_eval > 0);
+}
+
+/// The KQvK, KRvK and KPvK fixture tables, see resources/syzygy/README.md.
+/// The expected values were computed by the independent retrograde analysis
+/// in tools/syzygy-fixtures.py.
+mod fixtures {
+    use std::sync::Arc;
+
+    use crate::position::Position;
+    use crate::syzygy::{SyzygyTablebase, Tablebase, TablebaseLookup, Wdl};
+    use crate::LookupMoveService;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/syzygy");
+
+    fn tablebase() -> SyzygyTablebase {
+        SyzygyTablebase::open(FIXTURES).unwrap()
+    }
+
+    fn assert_probe(tablebase: &SyzygyTablebase, fen: &str, wdl: Wdl, dtz: i32) {
+        let position: Position = fen.parse().unwrap();
+        assert_eq!(Some(wdl), tablebase.probe_wdl(&position), "wdl {}", fen);
+        assert_eq!(Some(dtz), tablebase.probe_dtz(&position), "dtz {}", fen);
+    }
+
+    #[test]
+    fn tables_found() {
+        let tablebase = tablebase();
+        assert_eq!(3, tablebase.max_pieces());
+        assert_eq!((3, 3), tablebase.table_count());
+    }
+
+    #[test]
+    fn queen() {
+        let tablebase = tablebase();
+        // Only one side of each dtz table is stored so one of each pair of
+        // probes needs the one ply search of a side change
+        assert_probe(&tablebase, "8/8/8/4k3/8/8/8/KQ6 w - - 0 1", Wdl::Win, 17);
+        assert_probe(&tablebase, "8/8/8/4k3/8/8/8/KQ6 b - - 0 1", Wdl::Loss, -18);
+        // Colour flipped, black has the queen
+        assert_probe(&tablebase, "kq6/8/8/8/4K3/8/8/8 b - - 0 1", Wdl::Win, 17);
+        assert_probe(&tablebase, "kq6/8/8/8/4K3/8/8/8 w - - 0 1", Wdl::Loss, -18);
+        // The undefended queen is captured
+        assert_probe(&tablebase, "8/8/8/8/8/8/1k6/1Q1K4 b - - 0 1", Wdl::Draw, 0);
+        // Stalemate
+        assert_probe(&tablebase, "k7/2Q5/1K6/8/8/8/8/8 b - - 0 1", Wdl::Draw, 0);
+    }
+
+    #[test]
+    fn rook() {
+        let tablebase = tablebase();
+        assert_probe(&tablebase, "k7/8/1K6/8/8/8/8/7R w - - 0 1", Wdl::Win, 1);
+        assert_probe(&tablebase, "8/8/8/8/3k4/8/8/R3K3 w - - 0 1", Wdl::Win, 27);
+        assert_probe(&tablebase, "8/8/8/8/3k4/8/8/R3K3 b - - 0 1", Wdl::Loss, -28);
+        assert_probe(&tablebase, "3k4/8/8/8/8/8/r7/4K3 b - - 0 1", Wdl::Win, 19);
+        assert_probe(&tablebase, "3k4/8/8/8/8/8/r7/4K3 w - - 0 1", Wdl::Loss, -22);
+        assert_probe(&tablebase, "8/8/8/8/8/8/2k5/K1R5 b - - 0 1", Wdl::Draw, 0);
+    }
+
+    #[test]
+    fn pawn() {
+        let tablebase = tablebase();
+        // Pawns on each of the files covered by the table and their mirrors
+        assert_probe(&tablebase, "k7/8/8/8/8/8/P7/K7 w - - 0 1", Wdl::Draw, 0);
+        assert_probe(&tablebase, "8/8/8/8/4k3/8/1P6/1K6 w - - 0 1", Wdl::Win, 11);
+        assert_probe(&tablebase, "8/8/8/8/4k3/8/1P6/1K6 b - - 0 1", Wdl::Loss, -14);
+        assert_probe(&tablebase, "8/1k6/8/8/3K4/8/2P5/8 w - - 0 1", Wdl::Win, 1);
+        assert_probe(&tablebase, "8/1k6/8/8/3K4/8/2P5/8 b - - 0 1", Wdl::Loss, -6);
+        assert_probe(&tablebase, "4k3/8/4K3/4P3/8/8/8/8 w - - 0 1", Wdl::Win, 3);
+        assert_probe(&tablebase, "4k3/8/4K3/4P3/8/8/8/8 b - - 0 1", Wdl::Loss, -4);
+        assert_probe(&tablebase, "8/8/8/8/8/4k3/4P3/4K3 w - - 0 1", Wdl::Draw, 0);
+        assert_probe(&tablebase, "8/8/8/8/8/4k3/4P3/4K3 b - - 0 1", Wdl::Draw, 0);
+        assert_probe(&tablebase, "8/6k1/8/4K3/8/8/6P1/8 w - - 0 1", Wdl::Win, 3);
+        // Colour flipped, black has the pawn
+        assert_probe(&tablebase, "8/6p1/8/8/4k3/8/6K1/8 b - - 0 1", Wdl::Win, 3);
+        assert_probe(&tablebase, "8/6p1/8/8/4k3/8/6K1/8 w - - 0 1", Wdl::Loss, -6);
+    }
+
+    #[test]
+    fn root_moves() {
+        let mut lookup = TablebaseLookup::new(Arc::new(tablebase()));
+        let mut best = |fen: &str| {
+            let position: Position = fen.parse().unwrap();
+            lookup.lookup(position).unwrap().map(|m| m.to_string())
+        };
+        assert_eq!(Some("h1h8".to_owned()), best("k7/8/1K6/8/8/8/8/7R w - - 0 1"));
+        // Pushing two squares lets the king catch the pawn
+        assert_eq!(Some("c2c3".to_owned()), best("8/1k6/8/8/3K4/8/2P5/8 w - - 0 1"));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::position::{Position, TerminalState};
+use crate::constants::boards::LIGHT_SQUARES;
+use crate::constants::in_board;
+use crate::constants::square::*;
+use crate::position::{DrawRule, Position, TerminalState};
 
 fn execute_test(expected: Option<TerminalState>, input: &str) {
     let board = input.parse::<Position>().unwrap();
@@ -8,7 +11,10 @@ fn execute_test(expected: Option<TerminalState>, input: &str) {
 
 #[test]
 fn checkmate() {
-    execute_test(Some(TerminalState::Loss), "5R1k/pp2R2p/8/1b2r3/3p3q/8/PPB3P1/6K1 b - - 0 36");
+    execute_test(
+        Some(TerminalState::Checkmate),
+        "5R1k/pp2R2p/8/1b2r3/3p3q/8/PPB3P1/6K1 b - - 0 36",
+    );
 }
 
 #[test]
@@ -33,12 +39,15 @@ fn not_terminal4() {
 
 #[test]
 fn stalemate() {
-    execute_test(Some(TerminalState::Draw), "6k1/6p1/7p/8/1p6/p1qp4/8/3K4 w - - 0 45");
+    execute_test(Some(TerminalState::Stalemate), "6k1/6p1/7p/8/1p6/p1qp4/8/3K4 w - - 0 45");
 }
 
 #[test]
 fn fifty_moves_1() {
-    execute_test(Some(TerminalState::Draw), "8/8/8/8/3B4/7K/2k1Q3/1q6 b - - 100 120")
+    execute_test(
+        Some(TerminalState::FiftyMoves(DrawRule::Claimable)),
+        "8/8/8/8/3B4/7K/2k1Q3/1q6 b - - 100 120",
+    )
 }
 
 #[test]
@@ -107,7 +116,7 @@ fn repetition_7() {
 #[test]
 fn repetition_8() {
     execute_test(
-        Some(TerminalState::Draw),
+        Some(TerminalState::Repetition(DrawRule::Claimable)),
         "1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. O-O Nxe4 \
         5. Re1 Nd6 6. Nxe5 Be7 7. Bf1 Nxe5 8. Rxe5 O-O 9. d4 Ne8 10. d5 Bc5 11. Be3 Be7 \
         12. Bd2 Bc5 13. Be3 Bb4 14. Bd2 Bc5 15. Be3",
@@ -117,7 +126,7 @@ fn repetition_8() {
 #[test]
 fn repetition_9() {
     execute_test(
-        Some(TerminalState::Draw),
+        Some(TerminalState::Repetition(DrawRule::Claimable)),
         "1. e4 e5 2. Nf3 Nc6 3. Bb5 Nf6 4. O-O Nxe4 \
         5. Re1 Nd6 6. Nxe5 Be7 7. Bf1 Nxe5 8. Rxe5 O-O 9. d4 Ne8 10. d5 Bc5 11. Be3 Be7 \
         12. Bd2 Bc5 13. Be3 Bb4 14. Bd2 Bc5 15. Be3",
@@ -127,7 +136,7 @@ fn repetition_9() {
 #[test]
 fn repetition_10() {
     execute_test(
-        Some(TerminalState::Draw),
+        Some(TerminalState::Repetition(DrawRule::Claimable)),
         "1. Nf3 Nf6 2. d4 g6 3. Bg5 Bg7 4. Nbd2 O-O 5. e4 d5 6. e5 Ne4 7. Nxe4 dxe4 \
         8. Nd2 Qxd4 9. Bxe7 Re8 10. Bf6 Bxf6 11. exf6 Qxb2 12. Bc4 e3 13. fxe3 Bg4 14. Qxg4 Qxa1+ \
         15. Qd1 Qxd1+ 16. Kxd1 Rxe3 17. Re1 Rxe1+ 18. Kxe1 Nd7 19. Ne4 Re8 20. Bd3 Nxf6 \
@@ -140,8 +149,101 @@ fn repetition_10() {
 #[test]
 fn repetition_11() {
     execute_test(
-        Some(TerminalState::Draw),
+        Some(TerminalState::Repetition(DrawRule::Claimable)),
         "1. e3 e6 2. Qf3 Nf6 3. Kd1 Nc6 4. d4 d5 5. Bb5 e5 6. Qg3 exd4 7. exd4 Ne4 8. Qe3 Be7 \
         9. f3 Nd6 10. Bxc6+ bxc6 11. h4 Nf5 12. Qc3 Bd7 13. h5 Ng3 14. Rh2 Nf1 15. Rh1 Ng3 16. Rh2 Nf1 17. Rh1 Ng3"
     )
 }
+
+#[test]
+fn fifty_moves_2() {
+    execute_test(
+        Some(TerminalState::FiftyMoves(DrawRule::Automatic)),
+        "8/8/8/8/3B4/7K/2k1Q3/1q6 b - - 150 145",
+    )
+}
+
+#[test]
+fn checkmate_on_fiftieth_move() {
+    execute_test(
+        Some(TerminalState::Checkmate),
+        "5R1k/pp2R2p/8/1b2r3/3p3q/8/PPB3P1/6K1 b - - 100 86",
+    );
+}
+
+#[test]
+fn repetition_fivefold() {
+    execute_test(
+        Some(TerminalState::Repetition(DrawRule::Automatic)),
+        "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. Nf3 Nf6 6. Ng1 Ng8 7. Nf3 Nf6 8. Ng1 Ng8",
+    )
+}
+
+#[test]
+fn repetition_fourfold() {
+    execute_test(
+        Some(TerminalState::Repetition(DrawRule::Claimable)),
+        "1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3 Nf6 4. Ng1 Ng8 5. Nf3 Nf6 6. Ng1 Ng8",
+    )
+}
+
+#[test]
+fn insufficient_material_bare_kings() {
+    execute_test(Some(TerminalState::InsufficientMaterial), "8/8/4k3/8/8/2K5/8/8 w - - 0 60");
+}
+
+#[test]
+fn insufficient_material_knight() {
+    execute_test(Some(TerminalState::InsufficientMaterial), "8/8/4k3/8/8/2K5/5N2/8 b - - 0 60");
+}
+
+#[test]
+fn insufficient_material_bishop() {
+    execute_test(Some(TerminalState::InsufficientMaterial), "8/8/4k1b1/8/8/2K5/8/8 w - - 0 60");
+}
+
+#[test]
+fn insufficient_material_same_colour_bishops() {
+    execute_test(Some(TerminalState::InsufficientMaterial), "8/8/4k1b1/8/8/2K5/8/1B6 w - - 0 60");
+}
+
+#[test]
+fn sufficient_material_opposite_colour_bishops() {
+    execute_test(None, "8/8/4k1b1/8/8/2K5/8/2B5 w - - 0 60");
+}
+
+#[test]
+fn light_squares() {
+    for square in [H1, A2, D1, E8, A8] {
+        assert!(in_board(LIGHT_SQUARES, square), "{}", square);
+    }
+    for square in [A1, H2, E1, D8, H8] {
+        assert!(!in_board(LIGHT_SQUARES, square), "{}", square);
+    }
+}
+
+#[test]
+fn sufficient_material_two_knights() {
+    execute_test(None, "8/8/4k3/8/8/2K5/5N2/6N1 b - - 0 60");
+}
+
+#[test]
+fn sufficient_material_knight_each() {
+    execute_test(None, "8/8/4k1n1/8/8/2K5/5N2/8 b - - 0 60");
+}
+
+#[test]
+fn sufficient_material_pawn() {
+    execute_test(None, "8/8/4k3/8/8/2K5/5P2/8 b - - 0 60");
+}
+
+#[test]
+fn result_tags() {
+    use crate::constants::side;
+    assert_eq!("0-1", TerminalState::Checkmate.result(side::W));
+    assert_eq!("1-0", TerminalState::Checkmate.result(side::B));
+    assert_eq!("1/2-1/2", TerminalState::Stalemate.result(side::W));
+    assert_eq!("1/2-1/2", TerminalState::Repetition(DrawRule::Claimable).result(side::B));
+    assert!(!TerminalState::FiftyMoves(DrawRule::Claimable).is_automatic());
+    assert!(TerminalState::InsufficientMaterial.is_automatic());
+}
@@ -2,7 +2,7 @@ use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Datelike, Timelike, Utc};
 use clap::Parser;
-use hyperopic::Engine;
+use hyperopic::{Engine, LookupMoveService};
 use lazy_static::lazy_static;
 use lichess_api::ratings::{ChallengeRequest, OnlineBot, TimeLimitType, TimeLimits};
 use lichess_api::{LichessClient, LichessEndgameClient};
@@ -10,7 +10,7 @@ use lichess_events::events::{Challenge, GameStart};
 use lichess_events::{EventProcessor, LichessEvent, StreamParams};
 use lichess_game::{EmptyCancellationHook, Metadata};
 use log::LevelFilter;
-use openings::{DynamoOpeningService, OpeningTable};
+use openings::{DynamoOpeningService, FileOpeningService, OpeningFile, OpeningTable};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
 use simple_logger::SimpleLogger;
@@ -21,6 +21,7 @@ use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::sleep;
 
 const TABLE_SIZE: usize = 5_000_000;
+const OPENINGS_MAX_DEPTH: u8 = 10;
 
 lazy_static! {
     // Every 10 days we do 2 blitz days, 1 rapid and 7 bullet
@@ -59,6 +60,9 @@ struct Args {
     time_limit: Option<u32>,
     #[arg(long)]
     time_increment: Option<u32>,
+    /// Json lines output of the pgn extractor to use instead of the DynamoDB table
+    #[arg(long)]
+    openings_file: Option<String>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -76,8 +80,12 @@ async fn main() {
     log::info!("Our id is \"{}\"", bot_id.as_str());
     let cloned_id = bot_id.clone();
     let cloned_token = args.auth_token.clone();
+    let openings = args.openings_file.clone().map(|path| {
+        FileOpeningService::try_from(OpeningFile { path, max_depth: OPENINGS_MAX_DEPTH })
+            .expect("Bad openings file")
+    });
     let (tx, rx) = tokio::sync::mpsc::channel::<GameStarted>(32);
-    tokio::spawn(async move { run_event_stream(cloned_token, cloned_id, tx).await });
+    tokio::spawn(async move { run_event_stream(cloned_token, cloned_id, openings, tx).await });
     search_for_game(&args, bot_id.clone(), rx).await;
 }
 
@@ -267,7 +275,12 @@ struct BotState {
     pub games_in_progress: usize,
 }
 
-async fn run_event_stream(auth_token: String, bot_id: String, tx: Sender<GameStarted>) {
+async fn run_event_stream(
+    auth_token: String,
+    bot_id: String,
+    openings: Option<FileOpeningService>,
+    tx: Sender<GameStarted>,
+) {
     lichess_events::stream(
         StreamParams {
             status_poll_frequency: Duration::from_secs(300),
@@ -282,6 +295,7 @@ async fn run_event_stream(auth_token: String, bot_id: String, tx: Sender<GameSta
             lichess: LichessClient::new(auth_token.clone()),
             games_started: Default::default(),
             table_size: TABLE_SIZE,
+            openings,
             tx,
         },
     )
@@ -294,7 +308,7 @@ fn opening_table() -> DynamoOpeningService {
         region: "eu-west-2".to_string(),
         position_key: "PositionFEN".to_string(),
         move_key: "Moves".to_string(),
-        max_depth: 10,
+        max_depth: OPENINGS_MAX_DEPTH,
     }
     .try_into()
     .expect("Bad opening table config")
@@ -306,6 +320,8 @@ struct EventProcessorImpl {
     lichess: LichessClient,
     games_started: HashSet<String>,
     table_size: usize,
+    /// Used instead of the opening table if given
+    openings: Option<FileOpeningService>,
     tx: Sender<GameStarted>,
 }
 
@@ -328,9 +344,13 @@ impl EventProcessor for EventProcessorImpl {
                         our_bot_id: self.our_bot_id.clone(),
                         auth_token: self.auth_token.clone(),
                     };
+                    let openings: Box<dyn LookupMoveService> = match &self.openings {
+                        Some(service) => Box::new(service.clone()),
+                        None => Box::new(opening_table()),
+                    };
                     let engine = Engine::new(
                         self.table_size,
-                        vec![Box::new(opening_table()), Box::new(LichessEndgameClient::default())],
+                        vec![openings, Box::new(LichessEndgameClient::default())],
                     );
                     self.tx
                         .send(GameStarted {
@@ -0,0 +1,90 @@
+use std::fs;
+
+use anyhow::{anyhow, Result};
+
+use hyperopic::constants::side;
+use hyperopic::epd::EpdEntry;
+use hyperopic::position::Position;
+
+/// What the evaluation of a position is fitted to, both from white's
+/// perspective. A result is the score of the game the position was taken
+/// from and a score is converted to an expected result using the fitted
+/// scaling constant.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Label {
+    Result(f64),
+    Score(i32),
+}
+
+pub struct LabelledPosition {
+    pub position: Position,
+    pub label: Label,
+}
+
+/// Read an EPD file where every position is labelled either with the game
+/// result as a "c9" operation, e.g. c9 "1/2-1/2", or with a score in the
+/// engine's own units as a "ce" operation relative to the side to move.
+pub fn load(path: &str) -> Result<Vec<LabelledPosition>> {
+    let content = fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(i, line)| parse_line(line).map_err(|e| anyhow!("Bad line {}: {}", i + 1, e)))
+        .collect()
+}
+
+fn parse_line(line: &str) -> Result<LabelledPosition> {
+    let entry = line.parse::<EpdEntry>()?;
+    let label = match (entry.operation("c9").and_then(|o| o.first()), entry.centipawns) {
+        (Some(result), _) => Label::Result(parse_result(result)?),
+        (None, Some(score)) => {
+            Label::Score(if entry.position.active == side::W { score } else { -score })
+        }
+        (None, None) => return Err(anyhow!("No c9 result or ce score in {}", line)),
+    };
+    Ok(LabelledPosition { position: entry.position, label })
+}
+
+fn parse_result(result: &str) -> Result<f64> {
+    match result {
+        "1-0" => Ok(1.0),
+        "0-1" => Ok(0.0),
+        "1/2-1/2" => Ok(0.5),
+        other => other
+            .parse::<f64>()
+            .ok()
+            .filter(|r| (0.0..=1.0).contains(r))
+            .ok_or(anyhow!("Unknown result {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_line, Label};
+
+    #[test]
+    fn game_results() {
+        let label = |result: &str| {
+            parse_line(&format!("4k3/8/8/8/8/8/4P3/4K3 b - - c9 \"{}\";", result)).unwrap().label
+        };
+        assert_eq!(Label::Result(1.0), label("1-0"));
+        assert_eq!(Label::Result(0.0), label("0-1"));
+        assert_eq!(Label::Result(0.5), label("1/2-1/2"));
+        assert_eq!(Label::Result(0.25), label("0.25"));
+    }
+
+    #[test]
+    fn scores_relative_to_white() {
+        let white = parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - ce 120;").unwrap();
+        assert_eq!(Label::Score(120), white.label);
+        let black = parse_line("4k3/8/8/8/8/8/4P3/4K3 b - - ce 120;").unwrap();
+        assert_eq!(Label::Score(-120), black.label);
+    }
+
+    #[test]
+    fn unlabelled() {
+        assert!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - id \"x\";").is_err());
+        assert!(parse_line("4k3/8/8/8/8/8/4P3/4K3 w - - c9 \"2-0\";").is_err());
+    }
+}
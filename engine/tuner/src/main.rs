@@ -0,0 +1,74 @@
+mod data;
+mod tune;
+mod weights;
+
+use std::fs;
+use std::thread;
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+use hyperopic::EvalParams;
+
+use crate::tune::Tuner;
+
+/// Fit the evaluation weights to a set of labelled positions by minimising the
+/// difference between the expected result of each position's quiet evaluation
+/// and its label. The tuned params can be loaded by the engine.
+#[derive(Parser)]
+struct Cli {
+    /// EPD file of positions labelled with a game result as a "c9" operation
+    /// or with a target score as a "ce" operation
+    #[arg(long)]
+    positions: String,
+    /// File the tuned params are written to as json after every pass
+    #[arg(long)]
+    output: String,
+    /// Params to start from rather than the compiled defaults
+    #[arg(long)]
+    params: Option<String>,
+    /// Scaling constant mapping evaluations to expected results, fitted to
+    /// the positions labelled with a result if not given
+    #[arg(long)]
+    k: Option<f64>,
+    /// The amount each integer weight is moved by in a single step
+    #[arg(long, default_value_t = 5)]
+    step: i64,
+    #[arg(long, default_value_t = 20)]
+    max_passes: usize,
+    /// The groups of params which are tuned
+    #[arg(long, value_delimiter = ',', default_value = "material,tables,pawns,safety,development")]
+    groups: Vec<String>,
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let params = match cli.params.as_ref() {
+        None => EvalParams::default(),
+        Some(path) => EvalParams::load(path)?,
+    };
+    let positions = data::load(&cli.positions)?;
+    println!("Loaded {} positions from {}", positions.len(), cli.positions);
+    let threads =
+        cli.threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let mut tuner = Tuner::new(positions, threads);
+    match cli.k {
+        Some(k) => tuner.set_k(k),
+        None => {
+            tuner.fit_k(&params)?;
+        }
+    };
+    println!("Using k = {:.6}", tuner.k());
+    tuner.local_search(&params, &cli.groups, cli.step, cli.max_passes, |_, _, params| {
+        write_params(&cli.output, params)
+    })?;
+    println!("Tuned params written to {}", cli.output);
+    Ok(())
+}
+
+fn write_params(path: &str, params: &EvalParams) -> Result<()> {
+    let json = serde_json::to_string_pretty(params)?;
+    fs::write(path, json).map_err(|e| anyhow!("Cannot write {}: {}", path, e))
+}
@@ -0,0 +1,224 @@
+use std::thread;
+
+use anyhow::{anyhow, Result};
+
+use hyperopic::constants::side;
+use hyperopic::node::TreeNode;
+use hyperopic::search::quiescent;
+use hyperopic::EvalParams;
+
+use crate::data::{Label, LabelledPosition};
+use crate::weights::Weights;
+
+/// Range searched when fitting the scaling constant
+const K_RANGE: (f64, f64) = (0.0, 10.0);
+const K_ITERATIONS: usize = 100;
+
+/// The expected result for white given an evaluation from their perspective
+pub fn sigmoid(k: f64, eval: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+pub struct Tuner {
+    positions: Vec<LabelledPosition>,
+    threads: usize,
+    k: f64,
+}
+
+impl Tuner {
+    pub fn new(positions: Vec<LabelledPosition>, threads: usize) -> Tuner {
+        Tuner { positions, threads: threads.max(1), k: 1.0 }
+    }
+
+    pub fn k(&self) -> f64 {
+        self.k
+    }
+
+    pub fn set_k(&mut self, k: f64) {
+        self.k = k
+    }
+
+    /// Fit the scaling constant which minimises the error of the positions
+    /// labelled with a game result under the given params.
+    pub fn fit_k(&mut self, params: &EvalParams) -> Result<f64> {
+        let evals = self.evaluate(params)?;
+        let results: Vec<_> = self
+            .positions
+            .iter()
+            .zip(evals)
+            .filter_map(|(p, eval)| match p.label {
+                Label::Result(result) => Some((result, eval)),
+                Label::Score(_) => None,
+            })
+            .collect();
+        if results.is_empty() {
+            return Err(anyhow!("No positions labelled with a result to fit k"));
+        }
+        let error =
+            |k: f64| results.iter().map(|&(r, eval)| (r - sigmoid(k, eval)).powi(2)).sum::<f64>();
+        // The error is unimodal in k so a ternary search finds the minimum
+        let (mut lo, mut hi) = K_RANGE;
+        for _ in 0..K_ITERATIONS {
+            let (m1, m2) = (lo + (hi - lo) / 3.0, hi - (hi - lo) / 3.0);
+            if error(m1) < error(m2) {
+                hi = m2
+            } else {
+                lo = m1
+            }
+        }
+        self.k = (lo + hi) / 2.0;
+        Ok(self.k)
+    }
+
+    /// The mean squared error between the expected result of the quiet
+    /// evaluation of each position and its label.
+    pub fn error(&self, params: &EvalParams) -> Result<f64> {
+        let evals = self.evaluate(params)?;
+        let total = self
+            .positions
+            .iter()
+            .zip(evals)
+            .map(|(p, eval)| {
+                let target = match p.label {
+                    Label::Result(result) => result,
+                    Label::Score(score) => sigmoid(self.k, score),
+                };
+                (target - sigmoid(self.k, eval)).powi(2)
+            })
+            .sum::<f64>();
+        Ok(total / self.positions.len().max(1) as f64)
+    }
+
+    /// The quiescent evaluation of every position from white's perspective,
+    /// the positions are split evenly between the threads.
+    fn evaluate(&self, params: &EvalParams) -> Result<Vec<i32>> {
+        let chunk_size = self.positions.len().div_ceil(self.threads).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .positions
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk.iter().map(|p| quiet_eval(p, params)).collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            let mut evals = Vec::with_capacity(self.positions.len());
+            for handle in handles {
+                evals.extend(handle.join().map_err(|_| anyhow!("Evaluation thread panicked"))??);
+            }
+            Ok(evals)
+        })
+    }
+
+    /// Texel's local search, each weight in turn is moved one step in either
+    /// direction and the change is kept if the error decreases. Passes over
+    /// the weights are repeated until none improve or the pass limit is hit,
+    /// the given callback receives the best params after every pass.
+    pub fn local_search(
+        &self,
+        params: &EvalParams,
+        groups: &[String],
+        step: i64,
+        max_passes: usize,
+        mut on_pass: impl FnMut(usize, f64, &EvalParams) -> Result<()>,
+    ) -> Result<EvalParams> {
+        let mut best = Weights::new(params)?;
+        let mut best_error = self.error(params)?;
+        let pointers = best.pointers(groups);
+        println!("Tuning {} weights, initial error {:.8}", pointers.len(), best_error);
+        for pass in 1..=max_passes {
+            let mut improved = 0;
+            for pointer in pointers.iter() {
+                for direction in [1, -1] {
+                    let candidate = match best.adjust(pointer, direction, step) {
+                        None => continue,
+                        Some(weights) => weights,
+                    };
+                    let candidate_params = match candidate.params() {
+                        Err(_) => continue,
+                        Ok(params) => params,
+                    };
+                    let error = self.error(&candidate_params)?;
+                    if error < best_error {
+                        best = candidate;
+                        best_error = error;
+                        improved += 1;
+                        break;
+                    }
+                }
+            }
+            println!("Pass {}: improved {} weights, error {:.8}", pass, improved, best_error);
+            let params = best.params()?;
+            on_pass(pass, best_error, &params)?;
+            if improved == 0 {
+                break;
+            }
+        }
+        best.params()
+    }
+}
+
+fn quiet_eval(labelled: &LabelledPosition, params: &EvalParams) -> Result<i32> {
+    let position = labelled.position.clone();
+    let parity = if position.active == side::W { 1 } else { -1 };
+    let mut node = TreeNode::new(position, params);
+    Ok(parity * quiescent::full_search(&mut node)?)
+}
+
+#[cfg(test)]
+mod test {
+    use hyperopic::EvalParams;
+
+    use super::{sigmoid, Tuner};
+    use crate::data::{Label, LabelledPosition};
+
+    fn labelled(fen: &str, label: Label) -> LabelledPosition {
+        LabelledPosition { position: fen.parse().unwrap(), label }
+    }
+
+    #[test]
+    fn sigmoid_symmetric() {
+        assert_eq!(0.5, sigmoid(1.0, 0));
+        assert!((sigmoid(0.5, 300) + sigmoid(0.5, -300) - 1.0).abs() < 1e-12);
+        assert!(sigmoid(1.0, 400) > 0.9);
+    }
+
+    #[test]
+    fn fitted_k_reduces_error() {
+        let positions = vec![
+            labelled("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", Label::Result(0.5)),
+            labelled("4k3/8/8/8/8/8/3QP3/4K3 w - - 0 1", Label::Result(1.0)),
+            labelled("4k3/8/8/8/8/8/3NP3/4K3 b - - 0 1", Label::Result(0.5)),
+            labelled("3rk3/8/8/8/8/8/4P3/4K3 w - - 0 1", Label::Result(0.0)),
+        ];
+        let params = EvalParams::default();
+        let mut tuner = Tuner::new(positions, 2);
+        let initial = tuner.error(&params).unwrap();
+        let k = tuner.fit_k(&params).unwrap();
+        assert!(k > 0.0 && k < 10.0);
+        assert!(tuner.error(&params).unwrap() <= initial);
+    }
+
+    #[test]
+    fn local_search_improves_error() {
+        let positions = vec![
+            labelled("4k3/pp6/8/8/8/8/4P3/4K3 w - - 0 1", Label::Score(-100)),
+            labelled("4k3/8/8/8/8/8/PP2P3/4K3 w - - 0 1", Label::Score(600)),
+        ];
+        let mut tuner = Tuner::new(positions, 1);
+        tuner.set_k(1.0);
+        let params = EvalParams::default();
+        let initial = tuner.error(&params).unwrap();
+        let groups = vec!["material".to_owned()];
+        let mut passes = 0;
+        let tuned = tuner
+            .local_search(&params, &groups, 10, 2, |_, _, _| {
+                passes += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert!(tuner.error(&tuned).unwrap() < initial);
+        assert!(passes > 0);
+    }
+}
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use serde_json::Value;
+
+use hyperopic::EvalParams;
+
+/// Weights which have no effect on the evaluation, the king is never captured
+/// and pawns never stand on the first or last rank.
+const FIXED: [&str; 4] =
+    ["/material/mid_values/5", "/material/end_values/5", "/tables/pawn/0/", "/tables/pawn/7/"];
+
+/// The eval params in their serialised form so that every numeric weight can
+/// be addressed by a json pointer, new params are picked up automatically.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    root: Value,
+}
+
+impl Weights {
+    pub fn new(params: &EvalParams) -> Result<Weights> {
+        Ok(Weights { root: serde_json::to_value(params)? })
+    }
+
+    pub fn params(&self) -> Result<EvalParams> {
+        Ok(serde_json::from_value(self.root.clone())?)
+    }
+
+    /// Pointers to every weight in the given top level groups which can
+    /// affect the evaluation.
+    pub fn pointers(&self, groups: &[String]) -> Vec<String> {
+        let mut pointers = vec![];
+        for group in groups {
+            if let Some(value) = self.root.get(group) {
+                collect_pointers(value, format!("/{}", group), &mut pointers);
+            }
+        }
+        pointers.retain(|p| !FIXED.iter().any(|fixed| p.starts_with(fixed) || p == fixed));
+        pointers
+    }
+
+    /// Move the weight at the pointer by the given number of steps, integers
+    /// move by the integer step and decimals by a fraction of their value. A
+    /// weight moved out of the range of its type is caught by [Weights::params].
+    pub fn adjust(&self, pointer: &str, steps: i64, int_step: i64) -> Option<Weights> {
+        let mut root = self.root.clone();
+        let value = root.pointer_mut(pointer)?;
+        *value = match (value.as_i64(), value.as_f64()) {
+            (Some(n), _) => Value::from(n + steps * int_step),
+            (None, Some(x)) => Value::from(x + steps as f64 * (x.abs() * 0.05).max(0.01)),
+            _ => return None,
+        };
+        Some(Weights { root })
+    }
+}
+
+fn collect_pointers(value: &Value, pointer: String, pointers: &mut Vec<String>) {
+    match value {
+        Value::Object(fields) => fields
+            .iter()
+            .for_each(|(k, v)| collect_pointers(v, format!("{}/{}", pointer, k), pointers)),
+        Value::Array(elements) => elements
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| collect_pointers(v, format!("{}/{}", pointer, i), pointers)),
+        Value::Number(_) => pointers.push(pointer),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hyperopic::EvalParams;
+
+    use super::Weights;
+
+    #[test]
+    fn pointers_skip_fixed_weights() {
+        let weights = Weights::new(&EvalParams::default()).unwrap();
+        let pointers = weights.pointers(&["material".to_owned(), "tables".to_owned()]);
+        assert_eq!(10 + 48 * 2 + 5 * 32 * 2, pointers.len());
+        assert!(pointers.contains(&"/material/mid_values/0".to_owned()));
+        assert!(pointers.contains(&"/tables/pawn/1/0/1".to_owned()));
+        assert!(!pointers.contains(&"/material/end_values/5".to_owned()));
+        assert!(!pointers.iter().any(|p| p.starts_with("/tables/pawn/7/")));
+    }
+
+    #[test]
+    fn adjust_weights() {
+        let weights = Weights::new(&EvalParams::default()).unwrap();
+        let adjusted = weights
+            .adjust("/castling/penalty", 1, 5)
+            .and_then(|w| w.adjust("/castling/penalty", -20, 5))
+            .and_then(|w| w.adjust("/pawns/doubled_pawn_penalty/0", -1, 5))
+            .unwrap()
+            .params()
+            .unwrap();
+        let mut expected = EvalParams::default();
+        expected.castling.penalty = -25;
+        expected.pawns.doubled_pawn_penalty.0 = -20;
+        assert_eq!(expected, adjusted);
+    }
+
+    #[test]
+    fn adjust_decimal_weights() {
+        let weights = Weights::new(&EvalParams::default()).unwrap();
+        let adjusted = weights.adjust("/safety/endgame_multiplier", 2, 5).unwrap();
+        assert!((0.12 - adjusted.params().unwrap().safety.endgame_multiplier).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_weights_rejected() {
+        let weights = Weights::new(&EvalParams::default()).unwrap();
+        assert!(weights.adjust("/development/undeveloped_cost", -3, 5).unwrap().params().is_err());
+        assert!(weights.adjust("/castling/missing", 1, 5).is_none());
+    }
+}
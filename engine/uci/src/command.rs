@@ -0,0 +1,204 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error, Result};
+use hyperopic::position::Position;
+use itertools::Itertools;
+
+/// The subset of the commands sent from a GUI to the engine which we support
+#[derive(Debug, Clone, PartialEq)]
+pub enum UciCommand {
+    Uci,
+    IsReady,
+    UciNewGame,
+    SetOption { name: String, value: Option<String> },
+    Position(Box<Position>),
+    Go(GoParameters),
+    PonderHit,
+    Stop,
+    Quit,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GoParameters {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movetime: Option<Duration>,
+    pub depth: Option<usize>,
+    pub nodes: Option<u64>,
+    pub infinite: bool,
+    pub ponder: bool,
+}
+
+impl FromStr for UciCommand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = s.split_whitespace().collect_vec();
+        match tokens.first().cloned() {
+            Some("uci") => Ok(UciCommand::Uci),
+            Some("isready") => Ok(UciCommand::IsReady),
+            Some("ucinewgame") => Ok(UciCommand::UciNewGame),
+            Some("setoption") => parse_setoption(&tokens[1..]),
+            Some("position") => {
+                parse_position(&tokens[1..]).map(|p| UciCommand::Position(Box::new(p)))
+            }
+            Some("go") => parse_go(&tokens[1..]).map(UciCommand::Go),
+            Some("ponderhit") => Ok(UciCommand::PonderHit),
+            Some("stop") => Ok(UciCommand::Stop),
+            Some("quit") => Ok(UciCommand::Quit),
+            _ => Err(anyhow!("Unrecognized command: {}", s)),
+        }
+    }
+}
+
+/// setoption name <id> [value <x>] where both the name and value may contain spaces
+fn parse_setoption(tokens: &[&str]) -> Result<UciCommand> {
+    if tokens.first() != Some(&"name") {
+        return Err(anyhow!("Expected option name in {:?}", tokens));
+    }
+    let value_index = tokens.iter().position(|t| *t == "value");
+    let name = tokens[1..value_index.unwrap_or(tokens.len())].join(" ");
+    let value = value_index.map(|i| tokens[i + 1..].join(" "));
+    Ok(UciCommand::SetOption { name, value })
+}
+
+/// position [startpos | fen <fen>] [moves <move1> ... <movei>]
+fn parse_position(tokens: &[&str]) -> Result<Position> {
+    let moves_index = tokens.iter().position(|t| *t == "moves").unwrap_or(tokens.len());
+    let mut position = match tokens.first() {
+        Some(&"startpos") => Position::default(),
+        Some(&"fen") => tokens[1..moves_index].join(" ").parse()?,
+        _ => return Err(anyhow!("Expected startpos or fen in {:?}", tokens)),
+    };
+    if moves_index < tokens.len() {
+        position.play(tokens[moves_index + 1..].join(" "))?;
+    }
+    Ok(position)
+}
+
+fn parse_go(tokens: &[&str]) -> Result<GoParameters> {
+    let mut params = GoParameters::default();
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        let mut next_millis = || -> Result<Duration> {
+            let value = tokens.next().ok_or(anyhow!("No value given for {}", token))?;
+            // Some GUIs send negative times when a player is out of time
+            Ok(Duration::from_millis(value.parse::<i64>()?.max(0) as u64))
+        };
+        match *token {
+            "wtime" => params.wtime = Some(next_millis()?),
+            "btime" => params.btime = Some(next_millis()?),
+            "winc" => params.winc = Some(next_millis()?),
+            "binc" => params.binc = Some(next_millis()?),
+            "movetime" => params.movetime = Some(next_millis()?),
+            "depth" => {
+                let value = tokens.next().ok_or(anyhow!("No value given for depth"))?;
+                params.depth = Some(value.parse()?)
+            }
+            "nodes" => {
+                let value = tokens.next().ok_or(anyhow!("No value given for nodes"))?;
+                params.nodes = Some(value.parse()?)
+            }
+            "infinite" => params.infinite = true,
+            "ponder" => params.ponder = true,
+            // Ignore parameters we don't support yet
+            _ => {}
+        }
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{GoParameters, UciCommand};
+    use hyperopic::position::Position;
+    use std::time::Duration;
+
+    fn parse(input: &str) -> UciCommand {
+        input.parse().unwrap()
+    }
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(UciCommand::Uci, parse("uci"));
+        assert_eq!(UciCommand::IsReady, parse("isready"));
+        assert_eq!(UciCommand::UciNewGame, parse("ucinewgame"));
+        assert_eq!(UciCommand::PonderHit, parse("ponderhit"));
+        assert_eq!(UciCommand::Stop, parse(" stop "));
+        assert_eq!(UciCommand::Quit, parse("quit"));
+        assert!("xboard".parse::<UciCommand>().is_err());
+    }
+
+    #[test]
+    fn setoption() {
+        assert_eq!(
+            UciCommand::SetOption { name: "Hash".to_string(), value: Some("128".to_string()) },
+            parse("setoption name Hash value 128")
+        );
+        assert_eq!(
+            UciCommand::SetOption { name: "Clear Hash".to_string(), value: None },
+            parse("setoption name Clear Hash")
+        );
+    }
+
+    #[test]
+    fn position_startpos() {
+        let mut expected = Position::default();
+        expected.play("e2e4 e7e5 g1f3").unwrap();
+        assert_eq!(
+            UciCommand::Position(Box::new(expected)),
+            parse("position startpos moves e2e4 e7e5 g1f3")
+        );
+        assert_eq!(UciCommand::Position(Box::default()), parse("position startpos"));
+    }
+
+    #[test]
+    fn position_fen() {
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let mut expected: Position = fen.parse().unwrap();
+        expected.play("f1b5").unwrap();
+        assert_eq!(
+            UciCommand::Position(Box::new(expected)),
+            parse(format!("position fen {} moves f1b5", fen).as_str())
+        );
+    }
+
+    #[test]
+    fn go() {
+        assert_eq!(
+            UciCommand::Go(GoParameters {
+                wtime: Some(Duration::from_millis(60000)),
+                btime: Some(Duration::from_millis(0)),
+                winc: Some(Duration::from_millis(1000)),
+                binc: Some(Duration::from_millis(1000)),
+                ..GoParameters::default()
+            }),
+            parse("go wtime 60000 btime -20 winc 1000 binc 1000")
+        );
+        assert_eq!(
+            UciCommand::Go(GoParameters {
+                movetime: Some(Duration::from_millis(500)),
+                depth: Some(6),
+                nodes: Some(100000),
+                ..GoParameters::default()
+            }),
+            parse("go movetime 500 depth 6 nodes 100000")
+        );
+        assert_eq!(
+            UciCommand::Go(GoParameters { infinite: true, ..GoParameters::default() }),
+            parse("go infinite")
+        );
+        assert_eq!(
+            UciCommand::Go(GoParameters {
+                wtime: Some(Duration::from_millis(1000)),
+                btime: Some(Duration::from_millis(2000)),
+                ponder: true,
+                ..GoParameters::default()
+            }),
+            parse("go ponder wtime 1000 btime 2000")
+        );
+    }
+}
@@ -0,0 +1,368 @@
+mod command;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use hyperopic::constants::side;
+use hyperopic::moves::Move;
+use hyperopic::polyglot::PolyglotBook;
+use hyperopic::position::Position;
+use hyperopic::search::end::NodeLimit;
+use hyperopic::search::observer::{IterationSummary, SearchObserver};
+use hyperopic::search::{ConcurrentTranspositions, MultiPv, SearchControl};
+use hyperopic::syzygy::{SyzygyTablebase, TablebaseLookup};
+use hyperopic::{ComputeMoveInput, Engine, EvalParams, LookupMoveService};
+use itertools::Itertools;
+use lichess_api::LichessEndgameClient;
+use openings::{DynamoOpeningService, OpeningTable};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::task::JoinHandle;
+
+use crate::command::{GoParameters, UciCommand};
+
+const ENGINE_NAME: &str = "Hyperopic";
+const ENGINE_AUTHOR: &str = "Thomas Ball";
+const MAX_HASH_MB: usize = 4096;
+const MAX_THREADS: usize = 64;
+const MAX_MULTI_PV: usize = 256;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut state = UciState::default();
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match line.parse::<UciCommand>() {
+            Err(e) => println!("info string {}", e),
+            Ok(UciCommand::Quit) => {
+                state.stop_search().await;
+                break;
+            }
+            Ok(command) => {
+                if let Err(e) = state.process(command).await {
+                    println!("info string {}", e)
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The configurable options we expose to the GUI, changing any of them
+/// will cause the engine to be rebuilt before the next search.
+#[derive(Debug, Clone, PartialEq)]
+struct EngineOptions {
+    hash_mb: usize,
+    threads: usize,
+    multi_pv: usize,
+    ponder: bool,
+    own_book: bool,
+    book_table: String,
+    book_region: String,
+    book_file: String,
+    book_depth: u8,
+    syzygy_path: String,
+    lichess_tablebase: bool,
+    eval_file: String,
+    chess960: bool,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            hash_mb: 64,
+            threads: 1,
+            multi_pv: 1,
+            ponder: false,
+            own_book: false,
+            book_table: "MyopicOpenings".to_string(),
+            book_region: "eu-west-2".to_string(),
+            book_file: String::new(),
+            book_depth: 10,
+            syzygy_path: String::new(),
+            lichess_tablebase: false,
+            eval_file: String::new(),
+            chess960: false,
+        }
+    }
+}
+
+impl EngineOptions {
+    fn print(&self) {
+        println!("option name Hash type spin default {} min 1 max {}", self.hash_mb, MAX_HASH_MB);
+        println!(
+            "option name Threads type spin default {} min 1 max {}",
+            self.threads, MAX_THREADS
+        );
+        println!(
+            "option name MultiPV type spin default {} min 1 max {}",
+            self.multi_pv, MAX_MULTI_PV
+        );
+        println!("option name Ponder type check default {}", self.ponder);
+        println!("option name OwnBook type check default {}", self.own_book);
+        println!("option name BookTable type string default {}", self.book_table);
+        println!("option name BookRegion type string default {}", self.book_region);
+        println!(
+            "option name BookFile type string default {}",
+            if self.book_file.is_empty() { "<empty>" } else { self.book_file.as_str() }
+        );
+        println!("option name BookDepth type spin default {} min 0 max 255", self.book_depth);
+        println!(
+            "option name SyzygyPath type string default {}",
+            if self.syzygy_path.is_empty() { "<empty>" } else { self.syzygy_path.as_str() }
+        );
+        println!("option name LichessTablebase type check default {}", self.lichess_tablebase);
+        println!(
+            "option name EvalFile type string default {}",
+            if self.eval_file.is_empty() { "<empty>" } else { self.eval_file.as_str() }
+        );
+        println!("option name UCI_Chess960 type check default {}", self.chess960);
+    }
+
+    fn set(&mut self, name: &str, value: Option<String>) -> Result<()> {
+        let value = value.ok_or(anyhow!("No value given for option {}", name));
+        match name.to_lowercase().as_str() {
+            "hash" => self.hash_mb = value?.parse::<usize>()?.clamp(1, MAX_HASH_MB),
+            "threads" => self.threads = value?.parse::<usize>()?.clamp(1, MAX_THREADS),
+            "multipv" => self.multi_pv = value?.parse::<usize>()?.clamp(1, MAX_MULTI_PV),
+            // Pondering is controlled by the GUI, we only need to advertise support
+            "ponder" => self.ponder = value?.parse()?,
+            "ownbook" => self.own_book = value?.parse()?,
+            "booktable" => self.book_table = value?,
+            "bookregion" => self.book_region = value?,
+            // GUIs send <empty> to clear a string option
+            "bookfile" => {
+                let value = value.unwrap_or_default();
+                self.book_file = if value == "<empty>" { String::new() } else { value }
+            }
+            "bookdepth" => self.book_depth = value?.parse()?,
+            "syzygypath" => {
+                let value = value.unwrap_or_default();
+                self.syzygy_path = if value == "<empty>" { String::new() } else { value }
+            }
+            "lichesstablebase" => self.lichess_tablebase = value?.parse()?,
+            "evalfile" => {
+                let value = value.unwrap_or_default();
+                self.eval_file = if value == "<empty>" { String::new() } else { value }
+            }
+            // Positions carry their own castling squares, this only changes how castles are written
+            "uci_chess960" => self.chess960 = value?.parse()?,
+            _ => return Err(anyhow!("Unknown option {}", name)),
+        };
+        Ok(())
+    }
+
+    fn build_engine(&self) -> Result<Engine> {
+        let table_size = self.hash_mb * 1024 * 1024 / ConcurrentTranspositions::ENTRY_SIZE;
+        let mut lookups: Vec<Box<dyn LookupMoveService>> = vec![];
+        if self.own_book && !self.book_file.is_empty() {
+            let book = PolyglotBook::open(&self.book_file)?.with_max_ply(self.book_depth as usize);
+            lookups.push(Box::new(book));
+        } else if self.own_book {
+            let service: DynamoOpeningService = OpeningTable {
+                name: self.book_table.clone(),
+                region: self.book_region.clone(),
+                position_key: "PositionFEN".to_string(),
+                move_key: "Moves".to_string(),
+                max_depth: self.book_depth,
+            }
+            .try_into()?;
+            lookups.push(Box::new(service));
+        }
+        let tablebase = if self.syzygy_path.is_empty() {
+            None
+        } else {
+            let tablebase = Arc::new(SyzygyTablebase::open(&self.syzygy_path)?);
+            lookups.push(Box::new(TablebaseLookup::new(tablebase.clone())));
+            Some(tablebase)
+        };
+        if self.lichess_tablebase {
+            lookups.push(Box::new(LichessEndgameClient::default()));
+        }
+        let engine = Engine::new(table_size, lookups)
+            .with_threads(self.threads)
+            .with_multi_pv(MultiPv::Top(self.multi_pv))
+            .with_observer(Box::new(InfoPrinter { chess960: self.chess960 }));
+        let engine = match tablebase {
+            None => engine,
+            Some(tablebase) => engine.with_tablebase(tablebase),
+        };
+        Ok(if self.eval_file.is_empty() {
+            engine
+        } else {
+            engine.with_eval_params(EvalParams::load(&self.eval_file)?)
+        })
+    }
+}
+
+#[derive(Default)]
+struct UciState {
+    options: EngineOptions,
+    engine: Option<Engine>,
+    position: Position,
+    search: Option<JoinHandle<()>>,
+    control: Option<Arc<SearchControl>>,
+    /// The time we will search for once the pondered move is played
+    ponder_time: Option<Duration>,
+}
+
+impl UciState {
+    async fn process(&mut self, command: UciCommand) -> Result<()> {
+        match command {
+            UciCommand::Uci => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                EngineOptions::default().print();
+                println!("uciok");
+            }
+            UciCommand::IsReady => {
+                if self.search.is_none() {
+                    self.engine()?;
+                }
+                println!("readyok");
+            }
+            UciCommand::UciNewGame => {
+                self.stop_search().await;
+                // Rebuilding the engine clears the transposition table
+                self.engine = None;
+                self.position = Position::default();
+            }
+            UciCommand::SetOption { name, value } => {
+                self.stop_search().await;
+                self.options.set(name.as_str(), value)?;
+                self.engine = None;
+            }
+            UciCommand::Position(position) => self.position = *position,
+            UciCommand::Go(params) => {
+                self.await_search().await;
+                self.go(params)?
+            }
+            UciCommand::PonderHit => {
+                if let Some(control) = self.control.as_ref() {
+                    control.set_deadline(self.ponder_time.take().unwrap_or(Duration::MAX));
+                }
+            }
+            UciCommand::Stop => {
+                if let Some(control) = self.control.as_ref() {
+                    control.stop()
+                }
+            }
+            UciCommand::Quit => self.stop_search().await,
+        };
+        Ok(())
+    }
+
+    fn engine(&mut self) -> Result<&Engine> {
+        if self.engine.is_none() {
+            self.engine = Some(self.options.build_engine()?);
+        }
+        Ok(self.engine.as_ref().unwrap())
+    }
+
+    fn go(&mut self, params: GoParameters) -> Result<()> {
+        let position = self.position.clone();
+        let engine = self.engine()?;
+        let (remaining, increment) = if position.active == side::W {
+            (params.wtime, params.winc)
+        } else {
+            (params.btime, params.binc)
+        };
+        let search_time = match (params.movetime, remaining) {
+            (Some(movetime), _) => Some(movetime),
+            (None, Some(remaining)) if !params.infinite => {
+                Some(engine.allocate_time(&ComputeMoveInput {
+                    position: position.clone(),
+                    remaining,
+                    increment: increment.unwrap_or(Duration::ZERO),
+                }))
+            }
+            _ => None,
+        };
+        // When pondering the clock only starts once the GUI sends ponderhit
+        let deadline = if params.ponder { None } else { search_time };
+        let handle = match (params.depth, params.nodes) {
+            (Some(depth), Some(nodes)) => {
+                engine.spawn_compute_move(position, deadline, (depth, NodeLimit(nodes)))
+            }
+            (Some(depth), None) => engine.spawn_compute_move(position, deadline, depth),
+            (None, Some(nodes)) => engine.spawn_compute_move(position, deadline, NodeLimit(nodes)),
+            (None, None) => engine.spawn_compute_move(position, deadline, ()),
+        };
+        let control = handle.control();
+        let wait_for_stop = params.infinite || params.ponder;
+        let chess960 = self.options.chess960;
+        self.control = Some(control.clone());
+        self.ponder_time = if params.ponder { search_time } else { None };
+        self.search = Some(tokio::task::spawn_blocking(move || {
+            let output = handle.join();
+            if wait_for_stop {
+                // We must not report a move until told to stop in infinite or ponder
+                // mode, unless a ponderhit arrives and the search can finish normally
+                while !control.is_stopped() && !control.has_deadline() {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+            match output {
+                Err(e) => {
+                    println!("info string {}", e);
+                    println!("bestmove 0000");
+                }
+                Ok(output) => {
+                    let best_move = output.best_move.to_uci(chess960);
+                    let ponder = output.search_details.and_then(|details| {
+                        details.optimal_path.get(1).map(|m| m.to_uci(chess960))
+                    });
+                    match ponder {
+                        Some(ponder) => println!("bestmove {} ponder {}", best_move, ponder),
+                        None => println!("bestmove {}", best_move),
+                    }
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    async fn await_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            search.await.ok();
+        }
+    }
+
+    async fn stop_search(&mut self) {
+        if let Some(control) = self.control.take() {
+            control.stop()
+        }
+        self.await_search().await;
+    }
+}
+
+/// Streams the progress of the search to the GUI
+struct InfoPrinter {
+    chess960: bool,
+}
+
+impl SearchObserver for InfoPrinter {
+    fn on_iteration_complete(&mut self, summary: &IterationSummary) {
+        let millis = summary.elapsed.as_millis();
+        for (i, line) in summary.lines.iter().enumerate() {
+            println!(
+                "info depth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+                summary.depth,
+                i + 1,
+                line.score,
+                summary.nodes,
+                summary.nodes as u128 * 1000 / millis.max(1),
+                millis,
+                line.path.iter().map(|m| m.to_uci(self.chess960)).join(" ")
+            );
+        }
+    }
+
+    fn on_root_move(&mut self, depth: u8, m: &Move, index: usize) {
+        let m = m.to_uci(self.chess960);
+        println!("info depth {} currmove {} currmovenumber {}", depth, m, index + 1);
+    }
+}
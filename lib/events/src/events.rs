@@ -14,6 +14,9 @@ pub enum LichessEvent {
 pub struct Challenge {
     pub id: String,
     pub variant: Variant,
+    /// Only present for challenges from a custom starting position
+    #[serde(rename = "initialFen")]
+    pub initial_fen: Option<String>,
     #[serde(rename = "timeControl")]
     pub time_control: TimeControl,
     pub challenger: Challenger,
@@ -150,6 +153,7 @@ mod test {
                     Challenge {
                         id: "x0ORBDis".to_owned(),
                         variant: Variant { key: "standard".to_owned() },
+                        initial_fen: None,
                         challenger: Challenger { id: "th0masb".to_string() },
                         time_control: TimeControl::Unlimited,
                     },
@@ -212,6 +216,7 @@ mod test {
                     Challenge {
                         id: "qG23jvtf".to_owned(),
                         variant: Variant { key: "standard".to_owned() },
+                        initial_fen: None,
                         challenger: Challenger { id: "th0masb".to_string() },
                         time_control: TimeControl::Correspondence { days_per_turn: 2 },
                     },
@@ -276,6 +281,63 @@ mod test {
                     Challenge {
                         id: "fLIBOP1V".to_owned(),
                         variant: Variant { key: "standard".to_owned() },
+                        initial_fen: None,
+                        challenger: Challenger { id: "th0masb".to_string() },
+                        time_control: TimeControl::Clock {
+                            clock: ClockTimeControl { limit: 600, increment: 3 }
+                        },
+                    },
+                    challenge
+                ),
+            },
+        }
+    }
+
+    #[test]
+    fn deserialize_challenge_from_position() {
+        let json = r#"
+        {
+          "type": "challenge",
+          "challenge": {
+            "id": "H9fIRZUk",
+            "url": "https://lichess.org/H9fIRZUk",
+            "status": "created",
+            "challenger": {
+              "id": "th0masb",
+              "name": "th0masb",
+              "rating": 1500
+            },
+            "variant": {
+              "key": "fromPosition",
+              "name": "From Position",
+              "short": "FEN"
+            },
+            "rated": false,
+            "speed": "rapid",
+            "timeControl": {
+              "type": "clock",
+              "limit": 600,
+              "increment": 3,
+              "show": "10+3"
+            },
+            "color": "random",
+            "initialFen": "r3k2r/pp1q1ppp/n1p2n2/4p3/3pP2P/3P1QP1/PPPN1PB1/R3K2R w KQkq - 1 13"
+          }
+        }
+        "#;
+
+        match serde_json::from_str::<LichessEvent>(json) {
+            Err(error) => panic!("Parse error: {}", error),
+            Ok(event) => match event {
+                LichessEvent::GameStart { .. } => panic!("Wrong event: {:?}", event),
+                LichessEvent::Challenge { challenge } => assert_eq!(
+                    Challenge {
+                        id: "H9fIRZUk".to_owned(),
+                        variant: Variant { key: "fromPosition".to_owned() },
+                        initial_fen: Some(
+                            "r3k2r/pp1q1ppp/n1p2n2/4p3/3pP2P/3P1QP1/PPPN1PB1/R3K2R w KQkq - 1 13"
+                                .to_owned()
+                        ),
                         challenger: Challenger { id: "th0masb".to_string() },
                         time_control: TimeControl::Clock {
                             clock: ClockTimeControl { limit: 600, increment: 3 }
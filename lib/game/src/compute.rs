@@ -1,40 +1,68 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use hyperopic::moves::Move;
+use hyperopic::position::Position;
+use hyperopic::search::SearchControl;
 use hyperopic::{ComputeMoveInput, Engine};
+use std::sync::Arc;
 use std::time::Duration;
 
+/// A move chosen for the side to move along with the evaluation of the search
+/// from their perspective, no evaluation is given if the move was looked up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChosenMove {
+    pub best_move: Move,
+    pub relative_eval: Option<i32>,
+}
+
 #[async_trait]
 pub trait MoveChooser {
+    /// Choose a move in the position reached by playing the given moves from
+    /// the starting position, which is given in FEN.
     async fn choose(
         &mut self,
+        initial_fen: &str,
         moves_played: &str,
         remaining: Duration,
         increment: Duration,
-    ) -> Result<Move>;
+    ) -> Result<ChosenMove>;
 }
 
 #[async_trait]
 impl MoveChooser for Engine {
     async fn choose(
         &mut self,
+        initial_fen: &str,
         moves_played: &str,
         remaining: Duration,
         increment: Duration,
-    ) -> Result<Move> {
-        let position = moves_played.parse()?;
-        tokio::task::block_in_place(|| {
-            self.compute_move(ComputeMoveInput { position, remaining, increment })
-        })
-        .map(|output| {
-            match output.search_details {
-                None => log::info!("Used move from lookup"),
-                Some(details) => {
-                    let formatted = serde_json::to_string(&details).unwrap_or("error".to_string());
-                    log::info!("Computed: {}", formatted);
-                }
-            };
-            output.best_move
+    ) -> Result<ChosenMove> {
+        let mut position = Position::from_fen(initial_fen)?;
+        position.play(moves_played)?;
+        let input = ComputeMoveInput { position, remaining, increment };
+        let handle =
+            self.spawn_compute_move(input.position.clone(), Some(self.allocate_time(&input)), ());
+        // If the game loop is cancelled this future is dropped and the search must stop
+        let _stop_on_drop = StopOnDrop(handle.control());
+        let output = tokio::task::spawn_blocking(move || handle.join()).await??;
+        match &output.search_details {
+            None => log::info!("Used move from lookup"),
+            Some(details) => {
+                let formatted = serde_json::to_string(details).unwrap_or("error".to_string());
+                log::info!("Computed: {}", formatted);
+            }
+        };
+        Ok(ChosenMove {
+            relative_eval: output.search_details.map(|details| details.relative_eval),
+            best_move: output.best_move,
         })
     }
 }
+
+struct StopOnDrop(Arc<SearchControl>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop()
+    }
+}
@@ -50,6 +50,19 @@ pub struct GameFull {
     pub state: GameState,
     #[serde(rename = "initialFen")]
     pub initial_fen: String,
+    #[serde(default)]
+    pub variant: Variant,
+}
+
+#[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
+pub struct Variant {
+    pub key: String,
+}
+
+impl Default for Variant {
+    fn default() -> Self {
+        Variant { key: "standard".to_string() }
+    }
 }
 
 #[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -60,6 +73,8 @@ pub struct GameState {
     pub winc: u64,
     pub binc: u64,
     pub status: String,
+    #[serde(default)]
+    pub winner: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Clone, Eq, PartialEq)]
@@ -181,7 +196,8 @@ mod test {
                         btime: 1000,
                         winc: 0,
                         binc: 0,
-                        status: String::from("started")
+                        status: String::from("started"),
+                        winner: None,
                     },
                     state
                 ),
@@ -242,10 +258,58 @@ mod test {
                             btime: 1000,
                             winc: 0,
                             binc: 0,
-                            status: String::from("started")
+                            status: String::from("started"),
+                            winner: None,
                         },
                         content.state
                     );
+                    assert_eq!(Variant { key: "standard".to_string() }, content.variant);
+                }
+                _ => panic!("Wrong type {:?}", event),
+            },
+        }
+    }
+
+    #[test]
+    fn deserialize_chess960_game_full() {
+        let json = r#"{
+            "type": "gameFull",
+            "id": "123",
+            "variant": {
+                "key": "chess960",
+                "name": "Chess960",
+                "short": "960"
+            },
+            "white": {
+                "id": "th0masb"
+            },
+            "black": {
+                "id": "myopic-bot"
+            },
+            "clock": {
+                "initial": 1200000,
+                "increment": 10000
+            },
+            "state": {
+                "moves": "",
+                "wtime": 1000,
+                "btime": 1000,
+                "winc": 0,
+                "binc": 0,
+                "status": "started"
+            },
+            "initialFen": "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        }"#;
+
+        match serde_json::from_str::<GameEvent>(json) {
+            Err(error) => panic!("Parse error {:?}", error),
+            Ok(event) => match event {
+                GameEvent::GameFull { content } => {
+                    assert_eq!(Variant { key: "chess960".to_string() }, content.variant);
+                    assert_eq!(
+                        "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1",
+                        content.initial_fen.as_str()
+                    );
                 }
                 _ => panic!("Wrong type {:?}", event),
             },
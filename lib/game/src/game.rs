@@ -8,6 +8,7 @@ use tokio_util::sync::CancellationToken;
 
 use anyhow::{anyhow, Result};
 use hyperopic::constants::side;
+use hyperopic::pgn::{PgnGame, PgnTags};
 use hyperopic::position::Position;
 use hyperopic::Side;
 
@@ -18,6 +19,11 @@ use crate::messages;
 
 const STARTED_STATUS: &'static str = "started";
 const CREATED_STATUS: &'static str = "created";
+const STANDARD_VARIANT: &str = "standard";
+const FROM_POSITION_VARIANT: &str = "fromPosition";
+const CHESS960_VARIANT: &str = "chess960";
+const START_POSITION: &str = "startpos";
+const DRAW_STATUSES: [&str; 2] = ["draw", "stalemate"];
 const MOVE_LATENCY_MS: u64 = 200;
 const MIN_COMPUTE_TIME_MS: u64 = 200;
 
@@ -41,6 +47,9 @@ pub struct Game<M: MoveChooser> {
     inferred_metadata: Option<InferredGameMetadata>,
     lichess: LichessService,
     moves: M,
+    initial_position: Position,
+    chess960: bool,
+    pgn_tags: PgnTags,
     position_count: usize,
     cancel_token: CancellationToken,
     states_processed: HashSet<String>,
@@ -60,6 +69,9 @@ impl<M: MoveChooser> From<GameConfig<M>> for Game<M> {
             moves: conf.moves,
             bot_id: conf.bot_id,
             inferred_metadata: None,
+            initial_position: Position::default(),
+            chess960: false,
+            pgn_tags: PgnTags::default(),
             position_count: 0,
             cancel_token: conf.cancel_token,
             states_processed: HashSet::default(),
@@ -112,9 +124,22 @@ impl<M: MoveChooser> Game<M> {
     }
 
     async fn process_game(&mut self, game: GameFull) -> Result<GameExecutionState> {
-        if game.initial_fen.as_str() != "startpos" {
-            return Err(anyhow!("Custom start positions not currently supported"));
-        }
+        self.chess960 = match game.variant.key.as_str() {
+            STANDARD_VARIANT | FROM_POSITION_VARIANT => false,
+            CHESS960_VARIANT => true,
+            variant => return Err(anyhow!("Unsupported variant {}", variant)),
+        };
+        self.initial_position = match game.initial_fen.as_str() {
+            START_POSITION => Position::default(),
+            fen => Position::from_fen(fen)?,
+        };
+        self.pgn_tags = PgnTags {
+            event: "Lichess bot game".to_owned(),
+            site: format!("https://lichess.org/{}", self.lichess.game_id),
+            white: game.white.id.clone(),
+            black: game.black.id.clone(),
+            ..PgnTags::default()
+        };
         // Track info required for playing future gamestates
         self.inferred_metadata = Some(InferredGameMetadata {
             clock: game.clock,
@@ -142,36 +167,69 @@ impl<M: MoveChooser> Game<M> {
             return Ok(GameExecutionState::Running);
         }
         log::debug!("Parsing previous game moves: {}", state.moves);
-        let position = state.moves.parse::<Position>()?;
+        let mut position = self.initial_position.clone();
+        position.play(&state.moves)?;
         let active = position.active;
         let position_count = position.history.len();
         self.position_count = position_count;
         match state.status.as_str() {
             STARTED_STATUS | CREATED_STATUS => {
                 let metadata = self.get_latest_metadata()?.clone();
+                let game_id = self.lichess.game_id.as_str();
                 if active != metadata.lambda_side {
                     log::debug!("It is not our turn, waiting for opponents move");
                     Ok(GameExecutionState::Running)
+                } else if let Some(terminal) =
+                    position.compute_terminal_state().filter(|t| t.is_automatic())
+                {
+                    // Lichess will end the game itself, wait for the final state
+                    log::info!(
+                        "{}: Game over by {:?} with result {}",
+                        game_id,
+                        terminal,
+                        terminal.result(active)
+                    );
+                    Ok(GameExecutionState::Running)
                 } else {
                     let (remaining, increment) = if metadata.lambda_side == side::W {
                         (state.wtime, state.winc)
                     } else {
                         (state.btime, state.binc)
                     };
+                    let initial_fen = self.initial_position.to_string();
                     tokio::select! {
                         _ = self.cancel_token.cancelled() => {
                             log::info!("Move selection cancelled!");
                             Ok(GameExecutionState::Cancelled)
                         },
                         computed_move_result = self.moves.choose(
+                            initial_fen.as_str(),
                             state.moves.as_str(),
                             Duration::from_millis(max(MIN_COMPUTE_TIME_MS, remaining - MOVE_LATENCY_MS)),
                             Duration::from_millis(increment)
                         ) => {
-                            let m = computed_move_result?;
-                            let game_id = self.lichess.game_id.as_str();
-                            log::info!("{}: Posting {}", game_id, m);
-                            self.lichess.client.post_move(game_id, m.to_string().as_str()).await?;
+                            let chosen = computed_move_result?;
+                            // Claiming a draw ends the game immediately so only do so if we
+                            // are not winning, we still move in case the claim is refused
+                            if let Some(terminal) = position.compute_terminal_state() {
+                                if chosen.relative_eval.is_some_and(|eval| eval <= 0) {
+                                    log::info!("{}: Claiming draw by {:?}", game_id, terminal);
+                                    if let Err(e) = self.lichess.client.offer_draw(game_id).await {
+                                        log::warn!("{}: Failed to claim draw: {}", game_id, e);
+                                    }
+                                } else {
+                                    log::info!("{}: Playing on over {:?}", game_id, terminal);
+                                }
+                            }
+                            let mv = chosen.best_move;
+                            let m = mv.to_uci(self.chess960);
+                            // The san is only for the log so never let it stop us moving
+                            let san = position.to_san(&mv).unwrap_or_else(|e| {
+                                log::warn!("{}: Failed to format {} as san: {}", game_id, m, e);
+                                m.clone()
+                            });
+                            log::info!("{}: Posting {} ({})", game_id, m, san);
+                            self.lichess.client.post_move(game_id, m.as_str()).await?;
                             Ok(GameExecutionState::Running)
                         }
                     }
@@ -180,11 +238,31 @@ impl<M: MoveChooser> Game<M> {
             // All other possibilities indicate the game is over
             status => {
                 log::info!("Game finished with status: {}!", status);
+                match self.export_pgn(&position, &state) {
+                    Ok(pgn) => log::info!("{}: Final game\n{}", self.lichess.game_id, pgn),
+                    Err(e) => log::warn!("{}: Failed to export pgn: {}", self.lichess.game_id, e),
+                }
                 Ok(GameExecutionState::Finished)
             }
         }
     }
 
+    /// Lichess reports the winner for decisive results like resignation which
+    /// cannot be inferred from the final position.
+    fn export_pgn(&self, position: &Position, state: &GameState) -> Result<String> {
+        let result = match state.winner.as_deref() {
+            Some("white") => Some("1-0"),
+            Some("black") => Some("0-1"),
+            _ => Some("1/2-1/2").filter(|_| DRAW_STATUSES.contains(&state.status.as_str())),
+        };
+        let tags = PgnTags { result: result.map(|r| r.to_owned()), ..self.pgn_tags.clone() };
+        let mut game = PgnGame::from_history(tags, position)?;
+        if self.chess960 {
+            game.extra_tags.push(("Variant".to_owned(), "Chess960".to_owned()));
+        }
+        game.to_pgn()
+    }
+
     fn get_latest_metadata(&self) -> Result<&InferredGameMetadata> {
         self.inferred_metadata.as_ref().ok_or(anyhow!("Metadata not initialized"))
     }
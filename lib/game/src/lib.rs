@@ -6,7 +6,7 @@ use reqwest::Response;
 use tokio_util::sync::CancellationToken;
 
 pub use cancel::{CancellationHook, EmptyCancellationHook};
-pub use compute::MoveChooser;
+pub use compute::{ChosenMove, MoveChooser};
 use response_stream::{LoopAction, StreamHandler};
 
 use crate::game::{Game, GameConfig, GameExecutionState};
@@ -65,6 +65,17 @@ impl LichessClient {
             .map(|response| response.status())
     }
 
+    /// Offer a draw, or claim one if the position has been repeated three times.
+    pub async fn offer_draw(&self, game_id: &str) -> Result<StatusCode> {
+        self.client
+            .post(format!("{}/{}/draw/yes", GAME_ENDPOINT, game_id).as_str())
+            .bearer_auth(&self.auth_token)
+            .send()
+            .await
+            .map_err(|error| anyhow!("Error offering draw in {}: {}", game_id, error))
+            .map(|response| response.status())
+    }
+
     pub async fn post_move(&self, game_id: &str, mv: &str) -> Result<StatusCode> {
         // Add timeout and retry logic
         let response = self
@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error, Result};
+use hyperopic::moves::Move;
+use hyperopic::position::Position;
+use hyperopic::LookupMoveService;
+use log::info;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{choose_move, position_index, MoveRecord};
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct OpeningFile {
+    /// Json lines file written by the pgn extractor
+    pub path: String,
+    #[serde(rename = "maxDepth")]
+    pub max_depth: u8,
+}
+
+/// One line of the pgn extractor output
+#[derive(Deserialize)]
+struct DatabaseEntry {
+    position: String,
+    moves: Vec<MoveRecord>,
+}
+
+/// Serves opening moves from a local file which is indexed in memory when the
+/// service is created, clones of the service share the same index.
+#[derive(Clone)]
+pub struct FileOpeningService {
+    index: Arc<HashMap<String, Vec<MoveRecord>>>,
+    max_depth: u8,
+}
+
+impl TryFrom<OpeningFile> for FileOpeningService {
+    type Error = Error;
+
+    fn try_from(value: OpeningFile) -> std::result::Result<Self, Self::Error> {
+        let file = File::open(&value.path)
+            .map_err(|e| anyhow!("Cannot open opening file {}: {}", value.path, e))?;
+        let service = FileOpeningService::from_reader(BufReader::new(file), value.max_depth)?;
+        info!("Loaded {} opening positions from {}", service.len(), value.path);
+        Ok(service)
+    }
+}
+
+impl FileOpeningService {
+    pub fn from_reader<R: BufRead>(reader: R, max_depth: u8) -> Result<FileOpeningService> {
+        let mut index: HashMap<String, Vec<MoveRecord>> = HashMap::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str::<DatabaseEntry>(&line)
+                .map_err(|e| anyhow!("Bad opening entry on line {}: {}", i + 1, e))?;
+            index.entry(entry.position).or_default().extend(entry.moves);
+        }
+        Ok(FileOpeningService { index: Arc::new(index), max_depth })
+    }
+
+    /// The number of positions with known moves
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    fn lookup_with(&self, position: Position, f: impl Fn() -> usize) -> Result<Option<Move>> {
+        let pos_count = position.history.len();
+        if pos_count > self.max_depth as usize {
+            return Ok(None);
+        }
+        match self.index.get(&position_index(&position)) {
+            None => Ok(None),
+            Some(records) => {
+                let chosen = choose_move(records, f)?;
+                let parsed = position.clone().play(&chosen)?;
+                let m = parsed.first().cloned().ok_or(anyhow!(
+                    "{} not parsed on {}",
+                    chosen,
+                    position
+                ))?;
+                Ok(Some(m))
+            }
+        }
+    }
+}
+
+impl LookupMoveService for FileOpeningService {
+    fn lookup(&mut self, position: Position) -> Result<Option<Move>> {
+        self.lookup_with(position, rand::random)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hyperopic::position::Position;
+
+    use super::FileOpeningService;
+
+    const ENTRIES: &str = r#"{"position":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq","moves":[{"mv":"e2e4","freq":3},{"mv":"d2d4","freq":1}]}
+
+{"position":"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq","moves":[{"mv":"c7c5","freq":2}]}
+"#;
+
+    fn lookup(service: &FileOpeningService, moves: &str, choice: usize) -> Option<String> {
+        let position: Position = moves.parse().unwrap();
+        service.lookup_with(position, || choice).unwrap().map(|m| m.to_string())
+    }
+
+    #[test]
+    fn weighted_by_frequency() {
+        let service = FileOpeningService::from_reader(ENTRIES.as_bytes(), 10).unwrap();
+        assert_eq!(2, service.len());
+        assert_eq!(Some("d2d4".to_owned()), lookup(&service, "", 0));
+        for i in 1..4 {
+            assert_eq!(Some("e2e4".to_owned()), lookup(&service, "", i));
+        }
+        assert_eq!(Some("c7c5".to_owned()), lookup(&service, "e2e4", 0));
+    }
+
+    #[test]
+    fn unknown_position() {
+        let service = FileOpeningService::from_reader(ENTRIES.as_bytes(), 10).unwrap();
+        assert_eq!(None, lookup(&service, "d2d4", 0));
+    }
+
+    #[test]
+    fn max_depth() {
+        let service = FileOpeningService::from_reader(ENTRIES.as_bytes(), 0).unwrap();
+        assert_eq!(Some("e2e4".to_owned()), lookup(&service, "", 1));
+        assert_eq!(None, lookup(&service, "e2e4", 0));
+    }
+
+    #[test]
+    fn bad_entry() {
+        let result = FileOpeningService::from_reader("{\"position\":1}".as_bytes(), 10);
+        assert!(result.is_err());
+    }
+}
@@ -11,6 +11,27 @@ use rusoto_core::Region;
 use rusoto_dynamodb::{AttributeValue, DynamoDb, DynamoDbClient, GetItemInput};
 use serde_derive::{Deserialize, Serialize};
 
+pub use file::{FileOpeningService, OpeningFile};
+
+mod file;
+
+/// Where opening moves are looked up, either a DynamoDB table or a local file
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum OpeningSource {
+    Dynamo(OpeningTable),
+    File(OpeningFile),
+}
+
+impl OpeningSource {
+    pub fn into_service(self) -> Result<Box<dyn LookupMoveService>> {
+        Ok(match self {
+            OpeningSource::Dynamo(table) => Box::new(DynamoOpeningService::try_from(table)?),
+            OpeningSource::File(file) => Box::new(FileOpeningService::try_from(file)?),
+        })
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 pub struct OpeningTable {
     pub name: String,
@@ -45,8 +66,7 @@ impl LookupMoveService for DynamoOpeningService {
                 info!("No lookup as {} > {}", pos_count, self.params.max_depth);
                 Ok(None)
             } else {
-                // The table index comprises, the pieces, active square, castling rights
-                let index = position.to_string().split_whitespace().take(3).join(" ");
+                let index = position_index(&position);
                 info!("Querying table {} for position {}", self.params.name, index);
                 self.client
                     .get_item(self.create_request(index))
@@ -96,7 +116,11 @@ impl DynamoOpeningService {
                 )),
                 Some(move_set) => {
                     info!("Found matching set {:?}!", move_set);
-                    let chosen = choose_move(move_set, rand::random)?;
+                    let records = move_set
+                        .iter()
+                        .filter_map(|s| MoveRecord::from_str(s.as_str()).ok())
+                        .collect::<Vec<_>>();
+                    let chosen = choose_move(&records, rand::random)?;
                     info!("Chose {} from set", &chosen);
                     Ok(chosen)
                 }
@@ -105,12 +129,14 @@ impl DynamoOpeningService {
     }
 }
 
-fn choose_move(available: &Vec<String>, f: impl Fn() -> usize) -> Result<String> {
-    let records = available
-        .into_iter()
-        .filter_map(|s| MoveRecord::from_str(s.as_str()).ok())
-        .sorted_by_key(|r| r.freq)
-        .collect::<Vec<_>>();
+/// The index comprises the pieces, active side and castling rights
+fn position_index(position: &Position) -> String {
+    position.to_string().split_whitespace().take(3).join(" ")
+}
+
+/// Choose a move with probability proportional to its frequency
+fn choose_move(available: &[MoveRecord], f: impl Fn() -> usize) -> Result<String> {
+    let records = available.iter().sorted_by_key(|r| r.freq).collect::<Vec<_>>();
 
     let frequency_sum = records.iter().map(|r| r.freq).sum::<usize>();
 
@@ -121,7 +147,7 @@ fn choose_move(available: &Vec<String>, f: impl Fn() -> usize) -> Result<String>
         let mut sum = 0usize;
         for record in records {
             if sum <= record_choice && record_choice < sum + record.freq {
-                return Ok(record.mv);
+                return Ok(record.mv.clone());
             }
             sum += record.freq;
         }
@@ -131,6 +157,7 @@ fn choose_move(available: &Vec<String>, f: impl Fn() -> usize) -> Result<String>
 
 const MOVE_FREQ_SEPARATOR: &'static str = ":";
 
+#[derive(Debug, Clone, Deserialize)]
 struct MoveRecord {
     mv: String,
     freq: usize,
@@ -150,12 +177,16 @@ impl FromStr for MoveRecord {
 
 #[cfg(test)]
 mod test {
-    use super::choose_move;
+    use std::str::FromStr;
+
+    use super::{choose_move, MoveRecord};
 
     #[test]
     fn test_choose_move() {
-        let choices =
-            vec![format!("a2a3:1"), format!("b2b4:1"), format!("g8f6:3"), format!("e1g1:20")];
+        let choices = ["a2a3:1", "b2b4:1", "g8f6:3", "e1g1:20"]
+            .iter()
+            .map(|s| MoveRecord::from_str(s).unwrap())
+            .collect::<Vec<_>>();
 
         assert_eq!(format!("a2a3"), choose_move(&choices, || { 0 }).unwrap());
         assert_eq!(format!("b2b4"), choose_move(&choices, || { 1 }).unwrap());
@@ -1,8 +1,10 @@
+use hyperopic::polyglot::BookEntry;
 use std::collections::HashMap;
+use std::hash::Hash;
 
 #[derive(Default)]
-pub struct PositionStore {
-    inner: HashMap<String, Vec<MoveRecord>>,
+pub struct PositionStore<K = String> {
+    inner: HashMap<K, Vec<MoveRecord>>,
     stats: StoreStats,
 }
 
@@ -17,11 +19,17 @@ pub struct StoreStats {
 pub struct MoveRecord {
     mv: String,
     freq: usize,
+    /// The move encoded for a Polyglot book
+    #[serde(skip)]
+    book_move: u16,
+    /// Half points scored with the move, two for a win and one for a draw
+    #[serde(skip)]
+    points: usize,
 }
 
 impl MoveRecord {
-    fn new(mv: String) -> MoveRecord {
-        MoveRecord { mv, freq: 1 }
+    pub fn new(mv: String, book_move: u16, points: usize) -> MoveRecord {
+        MoveRecord { mv, freq: 1, book_move, points }
     }
 }
 
@@ -31,36 +39,69 @@ pub struct DatabaseEntry {
     pub moves: Vec<MoveRecord>,
 }
 
-impl PositionStore {
+impl<K: Eq + Hash> PositionStore<K> {
     pub fn stats(&self) -> &StoreStats {
         &self.stats
     }
 
-    pub fn entries(&self) -> impl Iterator<Item = DatabaseEntry> + '_ {
-        self.inner.iter().map(|(k, v)| DatabaseEntry {
-            position: k.clone(),
-            moves: v.iter().map(|r| r.clone()).collect(),
-        })
-    }
-
-    pub fn process(&mut self, position: String, suggested_move: String) {
-        match self.inner.get_mut(position.as_str()) {
+    pub fn process(&mut self, position: K, record: MoveRecord) {
+        match self.inner.get_mut(&position) {
             None => {
                 self.stats.new_position_inserts += 1;
-                self.inner.insert(position, vec![MoveRecord::new(suggested_move)]);
+                self.inner.insert(position, vec![record]);
             }
             Some(records) => {
-                match records.iter_mut().find(|record| record.mv == suggested_move) {
+                match records.iter_mut().find(|existing| existing.mv == record.mv) {
                     None => {
                         self.stats.new_alternate_move_inserts += 1;
-                        records.push(MoveRecord::new(suggested_move))
+                        records.push(record)
                     }
-                    Some(record) => {
+                    Some(existing) => {
                         self.stats.duplicates += 1;
-                        record.freq += 1
+                        existing.freq += 1;
+                        existing.points += record.points;
                     }
                 };
             }
         };
     }
+
+    fn frequent_moves(&self, min_freq: usize) -> impl Iterator<Item = (&K, Vec<&MoveRecord>)> {
+        self.inner
+            .iter()
+            .map(move |(k, v)| (k, v.iter().filter(|r| r.freq >= min_freq).collect::<Vec<_>>()))
+            .filter(|(_, moves)| !moves.is_empty())
+    }
+}
+
+impl PositionStore<String> {
+    pub fn entries(&self, min_freq: usize) -> impl Iterator<Item = DatabaseEntry> + '_ {
+        self.frequent_moves(min_freq).map(|(k, v)| DatabaseEntry {
+            position: k.clone(),
+            moves: v.into_iter().cloned().collect(),
+        })
+    }
+}
+
+impl PositionStore<u64> {
+    /// Create the entries of a Polyglot book keyed by position, the weight of
+    /// each move is its frequency or, if weighted by result, the points it scored.
+    pub fn book_entries(&self, min_freq: usize, result_weighted: bool) -> Vec<BookEntry> {
+        let mut entries = vec![];
+        for (key, moves) in self.frequent_moves(min_freq) {
+            let weights: Vec<_> =
+                moves.iter().map(|r| if result_weighted { r.points } else { r.freq }).collect();
+            // Weights are only relative so scale them down if any won't fit
+            let max = weights.iter().max().cloned().unwrap_or(0).max(u16::MAX as usize);
+            for (record, weight) in moves.into_iter().zip(weights) {
+                entries.push(BookEntry {
+                    key: *key,
+                    raw_move: record.book_move,
+                    weight: (weight * u16::MAX as usize / max) as u16,
+                    learn: 0,
+                })
+            }
+        }
+        entries
+    }
 }
@@ -9,9 +9,10 @@ pub struct Errors {
 }
 
 impl Errors {
-    pub fn add_read_error(&mut self, file: String, game_index: usize) {
+    /// Games which could not be read from the PGN are located by line number
+    pub fn add_read_error(&mut self, file: String, line: usize) {
         self.read_error_total += 1;
-        Errors::add_location(&mut self.read_error_locations, file, game_index)
+        Errors::add_location(&mut self.read_error_locations, file, line)
     }
 
     pub fn add_parse_error(&mut self, file: String, game_index: usize) {
@@ -19,13 +20,13 @@ impl Errors {
         Errors::add_location(&mut self.parse_error_locations, file, game_index)
     }
 
-    fn add_location(map: &mut HashMap<String, Vec<usize>>, file: String, game_index: usize) {
+    fn add_location(map: &mut HashMap<String, Vec<usize>>, file: String, location: usize) {
         match map.get_mut(&file) {
             None => {
-                map.insert(file, vec![game_index]);
+                map.insert(file, vec![location]);
             }
             Some(entries) => {
-                entries.push(game_index);
+                entries.push(location);
             }
         }
     }
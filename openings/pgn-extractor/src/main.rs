@@ -1,17 +1,19 @@
 mod data;
 mod errors;
-mod game_stream;
 
+use data::{MoveRecord, PositionStore};
 use errors::Errors;
-use game_stream::GameStream;
+use hyperopic::constants::side;
 use hyperopic::moves::Move;
+use hyperopic::pgn::{PgnReader, PgnRecord};
+use hyperopic::polyglot::{encode_move, polyglot_key, write_book};
 use hyperopic::position::Position;
+use hyperopic::Side;
 use itertools::Itertools;
+use std::io::{BufRead, BufReader, BufWriter};
 use std::{collections::HashMap, error::Error, fs, fs::File, path::PathBuf};
 use structopt::StructOpt;
 
-#[macro_use]
-extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 
@@ -35,6 +37,23 @@ struct Opt {
     #[structopt(long = "positions-only")]
     #[serde(rename = "positions-only")]
     positions_only: bool,
+    /// Write a Polyglot opening book to this path instead of printing
+    /// the database entries.
+    #[structopt(long = "book", parse(from_os_str))]
+    book: Option<PathBuf>,
+    /// Moves played fewer times than this are left out.
+    #[structopt(long = "min-frequency", default_value = "1")]
+    #[serde(rename = "min-frequency")]
+    min_frequency: usize,
+    /// Moves made at or beyond this ply are ignored.
+    #[structopt(long = "max-ply")]
+    #[serde(rename = "max-ply")]
+    max_ply: Option<usize>,
+    /// Weight book moves by the points they scored rather than how
+    /// often they were played.
+    #[structopt(long = "result-weighted")]
+    #[serde(rename = "result-weighted")]
+    result_weighted: bool,
 }
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -49,7 +68,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     let file_paths = get_pgn_file_paths(&opt)?;
     let game_counts = count_games(&file_paths)?;
 
-    let mut store = data::PositionStore::default();
+    let mut store = PositionStore::<String>::default();
+    let mut book_store = PositionStore::<u64>::default();
+    let max_ply = opt.max_ply.unwrap_or(usize::MAX);
 
     let game_progress = indicatif::ProgressBar::new(0);
     game_progress.set_style(
@@ -69,36 +90,57 @@ fn main() -> Result<(), Box<dyn Error>> {
 
         let pgn_file = File::open(&path)?;
 
-        for (i, game_result) in GameStream::new(pgn_file).enumerate() {
+        for (i, game_result) in PgnReader::new(BufReader::new(pgn_file)).enumerate() {
             game_progress.set_position(i as u64);
             match game_result {
-                Err(_) => {
-                    errors.add_read_error(file_name.clone(), i);
+                Err(error) => {
+                    errors.add_read_error(file_name.clone(), error.line);
                 }
-                Ok(game) => match parse_entries(opt.search_offset, opt.search_depth, game.as_str())
-                {
-                    Err(_) => {
-                        errors.add_parse_error(file_name.clone(), i);
+                Ok(game) => {
+                    match parse_entries(opt.search_offset, opt.search_depth, max_ply, &game) {
+                        Err(_) => {
+                            errors.add_parse_error(file_name.clone(), i);
+                        }
+                        Ok(entries) => {
+                            for entry in entries {
+                                let record = MoveRecord::new(
+                                    entry.mv.to_string(),
+                                    encode_move(&entry.mv),
+                                    entry.points,
+                                );
+                                if opt.book.is_some() {
+                                    book_store.process(polyglot_key(&entry.position), record)
+                                } else if !matches!(entry.mv, Move::Enpassant { .. }) {
+                                    // Ignore enpassant moves for now as the database
+                                    // positions don't record the enpassant square
+                                    store.process(database_position(&entry.position), record)
+                                }
+                            }
+                        }
                     }
-                    Ok(entries) => entries
-                        .into_iter()
-                        .for_each(|entry| store.process(entry.position, entry.mv)),
-                },
+                }
             }
         }
     }
 
     game_progress.finish();
     eprintln!();
-    for database_entry in store.entries() {
-        if opt.positions_only {
-            println!("{}", database_entry.position)
-        } else {
-            println!("{}", serde_json::to_string(&database_entry)?);
+    if let Some(book_path) = opt.book.as_ref() {
+        let entries = book_store.book_entries(opt.min_frequency, opt.result_weighted);
+        eprintln!("Writing {} book entries to {}", entries.len(), path_to_string(book_path));
+        write_book(entries, &mut BufWriter::new(File::create(book_path)?))?;
+    } else {
+        for database_entry in store.entries(opt.min_frequency) {
+            if opt.positions_only {
+                println!("{}", database_entry.position)
+            } else {
+                println!("{}", serde_json::to_string(&database_entry)?);
+            }
         }
     }
     eprintln!("Errors:\n{}\n", serde_json::to_string_pretty(&errors)?);
-    eprintln!("Stats:\n{}", serde_json::to_string_pretty(store.stats())?);
+    let stats = if opt.book.is_some() { book_store.stats() } else { store.stats() };
+    eprintln!("Stats:\n{}", serde_json::to_string_pretty(stats)?);
 
     Ok(())
 }
@@ -115,7 +157,12 @@ fn count_games(file_paths: &Vec<PathBuf>) -> Result<HashMap<String, usize>, Box<
     let mut dest = HashMap::new();
     for path in file_paths {
         let path_str = path_to_string(path);
-        dest.insert(path_str, GameStream::new(File::open(path)?).count());
+        // Only used to size the progress bar so counting the game tags is enough
+        let mut count = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            count += line?.starts_with("[Event ") as usize;
+        }
+        dest.insert(path_str, count);
     }
     Ok(dest)
 }
@@ -124,28 +171,39 @@ fn path_to_string(path: &PathBuf) -> String {
     path.to_str().expect("Couldn't convert path to string").to_string()
 }
 
+/// The database index comprises the pieces, active side and castling rights
+fn database_position(position: &Position) -> String {
+    position.to_string().split_whitespace().take(3).join(" ")
+}
+
+/// The half points scored by the given side according to the game result,
+/// games without a decisive result are counted as draws.
+fn result_points(game: &PgnRecord, side: Side) -> usize {
+    let result = game.result.as_deref().or(game.tag("Result"));
+    match (result, side) {
+        (Some("1-0"), side::W) | (Some("0-1"), side::B) => 2,
+        (Some("1-0"), _) | (Some("0-1"), _) => 0,
+        _ => 1,
+    }
+}
+
 fn parse_entries(
     offset: usize,
     depth: usize,
-    game: &str,
+    max_ply: usize,
+    game: &PgnRecord,
 ) -> Result<Vec<CollectionEntry>, anyhow::Error> {
-    let position = game.parse::<Position>()?;
-    let moves: Vec<_> =
-        position.history.iter().map(|(_, m)| m.clone()).take(offset + depth).collect();
+    let end = (offset + depth).min(max_ply);
+    let moves: Vec<_> = game.mainline().take(end).cloned().collect();
 
-    let (mut board, mut entries) = (Position::default(), vec![]);
+    let (mut board, mut entries) = (game.start.clone(), vec![]);
     for (i, mv) in moves.into_iter().enumerate() {
         if i >= offset {
-            match mv {
-                // Ignore enpassant moves for now
-                Move::Enpassant { .. } => {}
-                _ => {
-                    entries.push(CollectionEntry {
-                        position: board.to_string().split_whitespace().take(3).join(" "),
-                        mv: mv.to_string(),
-                    });
-                }
-            }
+            entries.push(CollectionEntry {
+                position: board.clone(),
+                mv: mv.clone(),
+                points: result_points(game, board.active),
+            });
         }
         board.make(mv)?;
     }
@@ -153,8 +211,105 @@ fn parse_entries(
     Ok(entries)
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug)]
 struct CollectionEntry {
-    position: String,
-    mv: String,
+    position: Position,
+    mv: Move,
+    points: usize,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{database_position, parse_entries};
+    use crate::data::{MoveRecord, PositionStore};
+    use hyperopic::pgn::PgnReader;
+    use hyperopic::polyglot::{encode_move, polyglot_key, write_book, PolyglotBook};
+    use hyperopic::position::Position;
+    use std::env;
+    use std::fs::File;
+    use std::io::BufReader;
+
+    const RELATIVE_RESOURCE_PATH: &str = "resources/test";
+
+    fn read_games(name: &str) -> PgnReader<BufReader<File>> {
+        let path = format!(
+            "{}/{}/{}",
+            env::var("CARGO_MANIFEST_DIR").unwrap(),
+            RELATIVE_RESOURCE_PATH,
+            name
+        );
+        PgnReader::new(BufReader::new(File::open(path).unwrap()))
+    }
+
+    #[test]
+    fn single_game_pgn() {
+        let games: Vec<_> = read_games("single-game.pgn").collect();
+        // The moves between 4...Bc5 and 9.Qf3 are missing so the queen move is illegal
+        assert_eq!(2, games.len());
+        assert_eq!(13, games[0].as_ref().unwrap_err().line);
+        let truncated = games[1].as_ref().unwrap();
+        assert_eq!(Some("London2"), truncated.tag("Event"));
+        assert!(truncated.moves.is_empty());
+    }
+
+    #[test]
+    fn multi_game_pgn() {
+        let games: Vec<_> = read_games("multi-game.pgn").collect();
+        // Both games are missing moves, one malformed game does not prevent
+        // reading the others
+        assert_eq!(3, games.len());
+        assert_eq!(13, games[0].as_ref().unwrap_err().line);
+        assert_eq!(27, games[1].as_ref().unwrap_err().line);
+        let trailing_tags = games[2].as_ref().unwrap();
+        assert_eq!(Some("C65"), trailing_tags.tag("ECO"));
+        assert!(trailing_tags.moves.is_empty());
+    }
+
+    #[test]
+    fn entries_from_offset() {
+        let pgn = "[Event \"?\"]\n\n1.e4 e5 2.Nf3 Nc6 3.Bb5 1-0";
+        let game = PgnReader::new(pgn.as_bytes()).next().unwrap().unwrap();
+        let entries = parse_entries(1, 2, usize::MAX, &game).unwrap();
+        let moves: Vec<_> = entries.iter().map(|e| e.mv.to_string()).collect();
+        assert_eq!(vec!["e7e5", "g1f3"], moves);
+        assert_eq!(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq",
+            database_position(&entries[0].position)
+        );
+        assert_eq!(vec![0, 2], entries.iter().map(|e| e.points).collect::<Vec<_>>());
+        assert_eq!(2, parse_entries(0, 5, 2, &game).unwrap().len());
+    }
+
+    #[test]
+    fn book_from_games() {
+        let pgn = "[Result \"1-0\"]\n\n1.e4 e5 1-0\n\n\
+            [Result \"1/2-1/2\"]\n\n1.e4 c5 1/2-1/2\n\n\
+            [Result \"0-1\"]\n\n1.d4 d5 0-1\n";
+        let mut store = PositionStore::<u64>::default();
+        for game in PgnReader::new(pgn.as_bytes()) {
+            for entry in parse_entries(0, 1, usize::MAX, &game.unwrap()).unwrap() {
+                let record =
+                    MoveRecord::new(entry.mv.to_string(), encode_move(&entry.mv), entry.points);
+                store.process(polyglot_key(&entry.position), record);
+            }
+        }
+        let mut bytes = vec![];
+        write_book(store.book_entries(1, false), &mut bytes).unwrap();
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        let start = Position::default();
+        let weights = |book: &PolyglotBook| {
+            book.moves(&start).into_iter().map(|(m, w)| (m.to_string(), w)).collect::<Vec<_>>()
+        };
+        assert_eq!(vec![("e2e4".to_owned(), 2), ("d2d4".to_owned(), 1)], weights(&book));
+
+        let mut bytes = vec![];
+        write_book(store.book_entries(1, true), &mut bytes).unwrap();
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(vec![("e2e4".to_owned(), 3), ("d2d4".to_owned(), 0)], weights(&book));
+
+        let mut bytes = vec![];
+        write_book(store.book_entries(2, false), &mut bytes).unwrap();
+        let book = PolyglotBook::from_bytes(&bytes).unwrap();
+        assert_eq!(vec![("e2e4".to_owned(), 2)], weights(&book));
+    }
 }
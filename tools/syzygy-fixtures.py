@@ -0,0 +1,647 @@
+"""Writes the small Syzygy tables used as fixtures by the hyperopic tablebase
+tests. The values come from a retrograde solver which is independent of the
+engine and the tables are written in the Syzygy file format, so the official
+tables are drop in replacements. Usage:
+
+    python3 syzygy-fixtures.py write <dir>
+    python3 syzygy-fixtures.py probe <fen>..
+
+The probe command prints the WDL and DTZ (ply) of each position from the side
+to move, these are the expected values used by the tests."""
+import heapq
+import os
+import struct
+import sys
+from collections import Counter
+from functools import lru_cache
+
+KING = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)]
+ROOK = [(1, 0), (-1, 0), (0, 1), (0, -1)]
+BISHOP = [(1, 1), (1, -1), (-1, 1), (-1, -1)]
+
+
+def sq(f, r):
+    return r * 8 + f
+
+
+def fr(s):
+    return s % 8, s // 8
+
+
+def on(f, r):
+    return 0 <= f < 8 and 0 <= r < 8
+
+
+def king_moves(s):
+    f, r = fr(s)
+    return [sq(f + df, r + dr) for df, dr in KING if on(f + df, r + dr)]
+
+
+def adjacent(a, b):
+    (fa, ra), (fb, rb) = fr(a), fr(b)
+    return max(abs(fa - fb), abs(ra - rb)) <= 1
+
+
+def slides(s, dirs, blockers):
+    f, r = fr(s)
+    out = []
+    for df, dr in dirs:
+        nf, nr = f + df, r + dr
+        while on(nf, nr):
+            t = sq(nf, nr)
+            out.append(t)
+            if t in blockers:
+                break
+            nf, nr = nf + df, nr + dr
+    return out
+
+
+def piece_attacks(kind, s, blockers):
+    if kind == 'Q':
+        return slides(s, ROOK + BISHOP, blockers)
+    if kind == 'R':
+        return slides(s, ROOK, blockers)
+    if kind == 'P':
+        f, r = fr(s)
+        return [sq(f + d, r + 1) for d in (-1, 1) if on(f + d, r + 1)]
+    raise ValueError(kind)
+
+
+def white_attacks(kind, wk, x, bk, target):
+    if adjacent(wk, target):
+        return True
+    # The black king does not block attacks on squares it moves to
+    return target in piece_attacks(kind, x, {wk})
+
+
+# ---------------------------------------------------------------- KXvK
+
+
+@lru_cache(maxsize=None)
+def solve_kx(kind):
+    """Returns dict (wk, x, bk, stm) -> (wdl, dtz) with stm 0 white 1 black"""
+    states = []
+    for wk in range(64):
+        for x in range(64):
+            for bk in range(64):
+                if len({wk, x, bk}) < 3 or adjacent(wk, bk):
+                    continue
+                # White to move: black must not be in check
+                if not white_attacks(kind, wk, x, bk, bk):
+                    states.append((wk, x, bk, 0))
+                states.append((wk, x, bk, 1))
+    children = {}
+    result = {}
+    for st in states:
+        wk, x, bk, stm = st
+        if stm == 0:
+            ch = []
+            for t in king_moves(wk):
+                if t != x and not adjacent(t, bk) and t != bk:
+                    ch.append((t, x, bk, 1))
+            for t in piece_attacks(kind, x, {wk, bk}):
+                if t != wk and t != bk:
+                    ch.append((wk, t, bk, 1))
+            children[st] = ch
+        else:
+            ch = []
+            draw = False
+            for t in king_moves(bk):
+                if t == wk or adjacent(t, wk):
+                    continue
+                if t == x:
+                    if not adjacent(wk, x):
+                        draw = True
+                    continue
+                if white_attacks(kind, wk, x, t, t):
+                    continue
+                ch.append((wk, x, t, 0))
+            if draw:
+                result[st] = (0, 0)
+            elif not ch:
+                in_check = white_attacks(kind, wk, x, bk, bk)
+                result[st] = (-2, 0) if in_check else (0, 0)
+            children[st] = ch
+    # Distance to mate equals dtz as white never zeroes except by mating
+    level = 0
+    changed = True
+    while changed:
+        changed = False
+        level += 1
+        for st in states:
+            if st in result:
+                continue
+            ch = children[st]
+            if st[3] == 0:
+                if any(result.get(c, (None,))[0] == -2 and abs(result[c][1]) == level - 1 for c in ch):
+                    result[st] = (2, level)
+                    changed = True
+            else:
+                if all(result.get(c, (None,))[0] == 2 for c in ch):
+                    worst = max(result[c][1] for c in ch)
+                    if worst + 1 == level:
+                        result[st] = (-2, -level)
+                        changed = True
+    for st in states:
+        result.setdefault(st, (0, 0))
+    return result
+
+
+# ---------------------------------------------------------------- KPvK
+
+
+def promote_value(kind, wk, x, bk):
+    """Value for black to move after promoting, from black's perspective"""
+    return solve_kx(kind)[(wk, x, bk, 1)][0]
+
+
+@lru_cache(maxsize=None)
+def solve_kp():
+    states = []
+    for wk in range(64):
+        for p in range(8, 56):
+            for bk in range(64):
+                if len({wk, p, bk}) < 3 or adjacent(wk, bk):
+                    continue
+                if not white_attacks('P', wk, p, bk, bk):
+                    states.append((wk, p, bk, 0))
+                states.append((wk, p, bk, 1))
+    # For white: list of (zeroing_result or None, child)
+    moves = {}
+    result = {}
+    for st in states:
+        wk, p, bk, stm = st
+        if stm == 0:
+            ms = []
+            for t in king_moves(wk):
+                if t != p and t != bk and not adjacent(t, bk):
+                    ms.append((None, (t, p, bk, 1)))
+            f, r = fr(p)
+            one = sq(f, r + 1)
+            if one not in (wk, bk):
+                if r + 1 == 7:
+                    for kind in ('Q', 'R'):
+                        ms.append(('promote', promote_value(kind, wk, one, bk)))
+                else:
+                    ms.append(('push', (wk, one, bk, 1)))
+                    two = sq(f, r + 2)
+                    if r == 1 and two not in (wk, bk):
+                        ms.append(('push', (wk, two, bk, 1)))
+            moves[st] = ms
+        else:
+            ms = []
+            draw = False
+            for t in king_moves(bk):
+                if t == wk or adjacent(t, wk):
+                    continue
+                if t == p:
+                    if not adjacent(wk, p):
+                        draw = True
+                    continue
+                if white_attacks('P', wk, p, t, t):
+                    continue
+                ms.append((wk, p, t, 0))
+            moves[st] = ms
+            if draw:
+                result[st] = (0, 0)
+            elif not ms:
+                in_check = white_attacks('P', wk, p, bk, bk)
+                result[st] = (-2, 0) if in_check else (0, 0)
+    wdl = {st: v[0] for st, v in result.items()}
+    # First the wdl by fixed point iteration, zeroing moves are decided by the
+    # value of the position they lead to
+    changed = True
+    while changed:
+        changed = False
+        for st in states:
+            if st in wdl:
+                continue
+            if st[3] == 0:
+                for kind, c in moves[st]:
+                    if kind == 'promote':
+                        if c == -2:
+                            wdl[st] = 2
+                            break
+                    elif wdl.get(c) == -2:
+                        wdl[st] = 2
+                        break
+                if st in wdl:
+                    changed = True
+            else:
+                if all(wdl.get(c) == 2 for c in moves[st]):
+                    wdl[st] = -2
+                    changed = True
+    for st in states:
+        wdl.setdefault(st, 0)
+    # Then the dtz of decisive positions by levels of distance to zeroing
+    dtz = {}
+    for st in states:
+        if st[3] == 1 and st in result and result[st][0] == -2:
+            dtz[st] = 0
+    level = 0
+    remaining = [st for st in states if wdl[st] != 0 and st not in dtz]
+    while remaining:
+        level += 1
+        assigned = []
+        for st in remaining:
+            if st[3] == 0 and wdl[st] == 2:
+                best = None
+                for kind, c in moves[st]:
+                    if kind == 'promote':
+                        if c == -2:
+                            best = 1
+                    elif kind == 'push':
+                        if wdl[c] == -2:
+                            best = 1
+                    elif wdl[c] == -2 and c in dtz:
+                        v = 1 + abs(dtz[c])
+                        best = v if best is None else min(best, v)
+                # Only assign once every better option has been settled
+                if best == level:
+                    assigned.append((st, best))
+            elif st[3] == 1 and wdl[st] == -2:
+                ch = moves[st]
+                if all(c in dtz for c in ch):
+                    v = 1 + max(dtz[c] for c in ch)
+                    if v == level:
+                        assigned.append((st, -v))
+        for st, v in assigned:
+            dtz[st] = v
+        remaining = [st for st in remaining if st not in dtz]
+        if level > 200:
+            raise RuntimeError('no convergence %d' % len(remaining))
+    return {st: (wdl[st], dtz.get(st, 0)) for st in states}
+
+
+# ---------------------------------------------------------------- FEN
+
+
+def parse(fen):
+    board, stm = fen.split()[:2]
+    pieces = {}
+    for i, row in enumerate(board.split('/')):
+        f = 0
+        for ch in row:
+            if ch.isdigit():
+                f += int(ch)
+            else:
+                pieces[ch] = pieces.get(ch, []) + [sq(f, 7 - i)]
+                f += 1
+    return pieces, stm
+
+
+def flip(s):
+    f, r = fr(s)
+    return sq(f, 7 - r)
+
+
+def probe(fen):
+    pieces, stm = parse(fen)
+    strong_white = 'K' in pieces and any(k in pieces for k in 'QRP')
+    if strong_white:
+        wk, bk = pieces['K'][0], pieces['k'][0]
+        kind = [k for k in 'QRP' if k in pieces][0]
+        x = pieces[kind][0]
+        side = 0 if stm == 'w' else 1
+    else:
+        wk, bk = flip(pieces['k'][0]), flip(pieces['K'][0])
+        kind = [k.upper() for k in 'qrp' if k in pieces][0]
+        x = flip(pieces[kind.lower()][0])
+        side = 0 if stm == 'b' else 1
+    table = solve_kp() if kind == 'P' else solve_kx(kind)
+    return table[(wk, x, bk, side)]
+
+
+
+
+# ---------------------------------------------------------------- Indexing
+# Squares are numbered A1 = 0, B1 = 1, .., H8 = 63 as in the Syzygy format,
+# pieces are coded pawn 1 to king 6 with 8 added for black.
+
+W_PAWN, W_ROOK, W_QUEEN, W_KING, B_KING = 1, 4, 5, 6, 14
+PIECE_CODES = {'P': W_PAWN, 'R': W_ROOK, 'Q': W_QUEEN}
+
+
+def off_diagonal(s):
+    f, r = fr(s)
+    return r - f
+
+
+def binomial(k, n):
+    if k > n:
+        return 0
+    out = 1
+    for i in range(k):
+        out = out * (n - i) // (i + 1)
+    return out
+
+
+# Squares below the a1-h8 diagonal
+B1H1H7 = {s: i for i, s in enumerate(s for s in range(64) if off_diagonal(s) < 0)}
+# The a1-d1-d4 triangle with the squares on the diagonal last
+A1D1D4 = {
+    s: i
+    for i, s in enumerate(
+        [s for s in range(28) if off_diagonal(s) < 0 and fr(s)[0] <= 3]
+        + [s for s in range(28) if off_diagonal(s) == 0 and fr(s)[0] <= 3]
+    )
+}
+
+
+def pawn_codes():
+    """Pawn squares a2-h7 coded 47 down to 0 with the outer files first"""
+    codes, available = {}, 48
+    for f in range(4):
+        for r in range(1, 7):
+            codes[sq(f, r)] = available - 1
+            codes[sq(7 - f, r)] = available - 2
+            available -= 2
+    return codes
+
+
+MAP_PAWNS = pawn_codes()
+
+
+def unique_index(squares):
+    """Index of three unique pieces, 31332 values in total"""
+    s = list(squares)
+    if fr(s[0])[0] > 3:
+        s = [x ^ 7 for x in s]
+    if fr(s[0])[1] > 3:
+        s = [x ^ 56 for x in s]
+    for i in range(3):
+        off = off_diagonal(s[i])
+        if off == 0:
+            continue
+        if off > 0:
+            s[i:] = [((x >> 3) | (x << 3)) & 63 for x in s[i:]]
+        break
+    s0, s1, s2 = s
+    a1 = int(s1 > s0)
+    a2 = int(s2 > s0) + int(s2 > s1)
+    if off_diagonal(s0) != 0:
+        return (A1D1D4[s0] * 63 + s1 - a1) * 62 + s2 - a2
+    if off_diagonal(s1) != 0:
+        return (6 * 63 + fr(s0)[1] * 28 + B1H1H7[s1]) * 62 + s2 - a2
+    if off_diagonal(s2) != 0:
+        return 6 * 63 * 62 + 4 * 28 * 62 + fr(s0)[1] * 7 * 28 + (fr(s1)[1] - a1) * 28 + B1H1H7[s2]
+    return (
+        6 * 63 * 62
+        + 4 * 28 * 62
+        + 4 * 7 * 28
+        + fr(s0)[1] * 7 * 6
+        + (fr(s1)[1] - a1) * 6
+        + fr(s2)[1] - a2
+    )
+
+
+def pawn_index(squares):
+    """The lead pawn file and the index of a single pawn and two kings"""
+    s = list(squares)
+    tb_file = min(fr(s[0])[0], 7 - fr(s[0])[0])
+    if fr(s[0])[0] > 3:
+        s = [x ^ 7 for x in s]
+    idx = fr(s[0])[1] - 1
+    for i, (x, multiplier) in enumerate(zip(s[1:], (6, 6 * 63)), start=1):
+        idx += (x - sum(1 for y in s[:i] if y < x)) * multiplier
+    return tb_file, idx
+
+
+# ---------------------------------------------------------------- Tables
+
+
+def table_values(kind):
+    """The wdl (stored as wdl + 2) and dtz (stored as ply - 1) of every index
+    by side to move and file, entries not reached by a legal placement are
+    left as None"""
+    pawns = kind == 'P'
+    solved = solve_kp() if pawns else solve_kx(kind)
+    files, size = (4, 6 * 63 * 62) if pawns else (1, 31332)
+    wdl = [[[None] * size for _ in range(files)] for _ in range(2)]
+    dtz = [[None] * size for _ in range(files)]
+    for (wk, x, bk, stm), (w, d) in solved.items():
+        # Pieces in the order written to the table header
+        squares = (x, wk, bk) if pawns else (wk, x, bk)
+        f, idx = pawn_index(squares) if pawns else (0, unique_index(squares))
+        for table, value in ((wdl[stm], w + 2), (dtz, abs(d) - 1 if stm == 0 and w else None)):
+            if value is None:
+                continue
+            if table[f][idx] not in (None, value):
+                raise RuntimeError('Inconsistent values at %s %d %d' % (kind, f, idx))
+            table[f][idx] = value
+    return wdl, [dtz]
+
+
+def fill(values):
+    """Don't care entries repeat their neighbour which helps the compression"""
+    known = next((v for v in values if v is not None), 0)
+    out = []
+    for v in values:
+        known = known if v is None else v
+        out.append(known)
+    return out
+
+
+# ---------------------------------------------------------------- Compression
+
+BLOCK_SIZE_LOG = 8
+SPAN_LOG = 10
+MAX_SYMBOLS = 4095
+MAX_SYMBOL_VALUES = 256
+
+FLAG_WIN_PLIES = 4
+FLAG_LOSS_PLIES = 8
+FLAG_SINGLE_VALUE = 128
+
+
+def pair_symbols(values):
+    """Replace the most frequent adjacent pair of symbols with a new one until
+    that no longer pays off. Returns the symbol definitions, as (value, None)
+    for a leaf and (left, right) otherwise, and the sequence of symbols"""
+    leaves = sorted(set(values))
+    symbols = [(v, None) for v in leaves]
+    lengths = [1] * len(leaves)
+    index = {v: i for i, v in enumerate(leaves)}
+    seq = [index[v] for v in values]
+    while len(symbols) < MAX_SYMBOLS:
+        counts = Counter(zip(seq, seq[1:]))
+        candidates = [
+            (n, pair)
+            for pair, n in counts.items()
+            if lengths[pair[0]] + lengths[pair[1]] <= MAX_SYMBOL_VALUES
+        ]
+        if not candidates:
+            break
+        n, pair = max(candidates)
+        if n < 8:
+            break
+        new = len(symbols)
+        symbols.append(pair)
+        lengths.append(lengths[pair[0]] + lengths[pair[1]])
+        out, i = [], 0
+        while i < len(seq):
+            if i + 1 < len(seq) and (seq[i], seq[i + 1]) == pair:
+                out.append(new)
+                i += 2
+            else:
+                out.append(seq[i])
+                i += 1
+        seq = out
+    return symbols, lengths, seq
+
+
+def code_lengths(seq):
+    counts = Counter(seq)
+    if len(counts) == 1:
+        return {next(iter(counts)): 1}
+    heap = [(n, i, [s]) for i, (s, n) in enumerate(counts.items())]
+    heapq.heapify(heap)
+    lengths = Counter()
+    tiebreak = len(heap)
+    while len(heap) > 1:
+        n1, _, s1 = heapq.heappop(heap)
+        n2, _, s2 = heapq.heappop(heap)
+        for s in s1 + s2:
+            lengths[s] += 1
+        heapq.heappush(heap, (n1 + n2, tiebreak, s1 + s2))
+        tiebreak += 1
+    return dict(lengths)
+
+
+def compress(values):
+    """The size record, sparse index, block lengths and blocks of a sequence
+    of values compressed with pair symbols and canonical Huffman codes"""
+    values = fill(values)
+    if len(set(values)) == 1:
+        return struct.pack('<BB', FLAG_SINGLE_VALUE, values[0]), b'', b'', b''
+    symbols, sym_values, seq = pair_symbols(values)
+    lengths = code_lengths(seq)
+    min_len, max_len = min(lengths.values()), max(lengths.values())
+    n = max_len - min_len + 1
+    counts = [0] * n
+    for l in lengths.values():
+        counts[l - min_len] += 1
+
+    # Longer codes take the lower symbol numbers and codes of equal length are
+    # consecutive, symbols without a code are numbered last
+    lowest = [0] * n
+    for i in range(n - 2, -1, -1):
+        lowest[i] = lowest[i + 1] + counts[i + 1]
+    base = [0] * n
+    for i in range(n - 2, -1, -1):
+        assert (base[i + 1] + counts[i + 1]) % 2 == 0
+        base[i] = (base[i + 1] + counts[i + 1]) // 2
+    assert n == 1 or base[0] + counts[0] == 1 << min_len
+    renumber, codes, next_id = {}, {}, lowest[:]
+    for s in sorted(lengths, key=lambda s: (-lengths[s], s)):
+        i = lengths[s] - min_len
+        renumber[s] = next_id[i]
+        codes[s] = (base[i] + next_id[i] - lowest[i], lengths[s])
+        next_id[i] += 1
+    for s in range(len(symbols)):
+        if s not in renumber:
+            renumber[s] = len(renumber)
+
+    btree = [None] * len(symbols)
+    for s, (left, right) in enumerate(symbols):
+        if right is None:
+            btree[renumber[s]] = (left, 0xFFF)
+        else:
+            btree[renumber[s]] = (renumber[left], renumber[right])
+
+    # Whole symbols are packed into blocks, recording the number of values
+    block_bits = 8 << BLOCK_SIZE_LOG
+    blocks, block_values, starts = [], [], []
+    bits, used, count = [], 0, 0
+    for s in seq:
+        code, length = codes[s]
+        if used + length > block_bits or count + sym_values[s] > 1 << 16:
+            blocks.append(bits)
+            block_values.append(count)
+            bits, used, count = [], 0, 0
+        starts.append((len(blocks), count))
+        bits.append((code, length))
+        used += length
+        count += sym_values[s]
+    blocks.append(bits)
+    block_values.append(count)
+
+    data = b''
+    for bits in blocks:
+        acc, n_bits = 0, 0
+        for code, length in bits:
+            acc, n_bits = (acc << length) | code, n_bits + length
+        acc <<= block_bits - n_bits
+        data += acc.to_bytes(block_bits // 8, 'big')
+
+    # The block and offset of the value in the middle of every span
+    span, total = 1 << SPAN_LOG, len(values)
+    value_blocks = []
+    for (block, offset), s in zip(starts, seq):
+        value_blocks.extend((block, offset + k) for k in range(sym_values[s]))
+    sparse = b''
+    for k in range((total + span - 1) // span):
+        target = k * span + span // 2
+        block, offset = value_blocks[min(target, total - 1)]
+        offset += max(0, target - (total - 1))
+        sparse += struct.pack('<IH', block, offset)
+    block_lengths = b''.join(struct.pack('<H', v - 1) for v in block_values)
+
+    record = struct.pack('<BBBBIBB', 0, BLOCK_SIZE_LOG, SPAN_LOG, 0, len(blocks), max_len, min_len)
+    record += b''.join(struct.pack('<H', v) for v in lowest)
+    record += struct.pack('<H', len(btree))
+    for left, right in btree:
+        record += bytes([left & 0xFF, (left >> 8) | ((right & 0xF) << 4), right >> 4])
+    if len(btree) % 2:
+        record += b'\0'
+    return record, sparse, block_lengths, data
+
+
+def write_table(path, magic, kind, sides, flags=0):
+    """Write the values of each side to move and lead pawn file of a table"""
+    pawns = kind == 'P'
+    strong = PIECE_CODES[kind]
+    pieces = (strong, W_KING, B_KING) if pawns else (W_KING, strong, B_KING)
+    out = bytes(magic) + bytes([1 | (2 if pawns else 0)])
+    for _ in sides[0]:
+        # Each side encodes its single group of pieces first
+        out += bytes([0])
+        out += bytes(p | (p << 4) for p in pieces)
+    out += b'\0' * (len(out) % 2)
+    parts = [[compress(values) for values in side] for side in sides]
+    for f in range(len(sides[0])):
+        for side in parts:
+            record = side[f][0]
+            out += bytes([record[0] | flags]) + record[1:]
+    for index in (1, 2):
+        for f in range(len(sides[0])):
+            for side in parts:
+                out += side[f][index]
+    for f in range(len(sides[0])):
+        for side in parts:
+            out += b'\0' * (-len(out) % 64) + side[f][3]
+    with open(path, 'wb') as file:
+        file.write(out)
+
+
+WDL_MAGIC = [0x71, 0xE8, 0x23, 0x5D]
+DTZ_MAGIC = [0xD7, 0x66, 0x0C, 0xA5]
+
+
+def write_all(directory):
+    for kind in 'QRP':
+        name = os.path.join(directory, 'K%svK' % kind)
+        wdl, dtz = table_values(kind)
+        write_table(name + '.rtbw', WDL_MAGIC, kind, wdl)
+        # Only the stronger side to move is stored and distances are in plies
+        write_table(name + '.rtbz', DTZ_MAGIC, kind, dtz, FLAG_WIN_PLIES | FLAG_LOSS_PLIES)
+
+
+if __name__ == '__main__':
+    if sys.argv[1:2] == ['write']:
+        write_all(sys.argv[2])
+    elif sys.argv[1:2] == ['probe']:
+        for fen in sys.argv[2:]:
+            print(fen, probe(fen))
+    else:
+        print(__doc__)